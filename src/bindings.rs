@@ -0,0 +1,570 @@
+use std::{collections::BTreeMap, fs};
+
+use gamelibrary::log;
+use macroquad::{color::{Color, GRAY, RED, WHITE, YELLOW}, input::{get_last_key_pressed, is_key_down, is_key_pressed, is_key_released, is_mouse_button_down, is_mouse_button_pressed, is_mouse_button_released, KeyCode, MouseButton}, shapes::draw_rectangle, text::draw_text, window::{screen_height, screen_width}};
+use serde::{Deserialize, Serialize};
+
+use crate::gamepad::GamepadState;
+
+// lives next to the executable like the level and state files
+pub const BINDINGS_PATH: &str = "bindings.yaml";
+
+/// Something the player can do that is triggered by a key or mouse button
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Sprint,
+    Crouch,
+    Fire,
+    Reload,
+    SwitchWeapon,
+    PickUp,
+    DropWeapon,
+    UnlockRotations,
+    PlaceTeleporter,
+    Build,
+    TimeStop,
+    Shop,
+    ShopSlot1,
+    ShopSlot2,
+    ShopSlot3,
+    ShopSlot4,
+    ShopSlot5,
+    ShopSlot6,
+    ShopSlot7,
+    ShopSlot8,
+    PlankMode,
+    RotatePlank,
+    PlacePlank,
+    Ready,
+    Scoreboard,
+    Console,
+    ShrinkWindow,
+    ClearHitMarkers,
+    PanCamera,
+    PauseSync,
+    MarkLog,
+    ShowMouseCoordinates,
+    SpawnMenu,
+    SpawnAtMouse,
+    NextSpawnable,
+    PreviousSpawnable,
+    ToggleGodMode,
+    ToggleInfiniteAmmo,
+    ToggleGravity
+}
+
+impl Action {
+    pub const ALL: [Action; 42] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Sprint,
+        Action::Crouch,
+        Action::Fire,
+        Action::Reload,
+        Action::SwitchWeapon,
+        Action::PickUp,
+        Action::DropWeapon,
+        Action::UnlockRotations,
+        Action::PlaceTeleporter,
+        Action::Build,
+        Action::TimeStop,
+        Action::Shop,
+        Action::ShopSlot1,
+        Action::ShopSlot2,
+        Action::ShopSlot3,
+        Action::ShopSlot4,
+        Action::ShopSlot5,
+        Action::ShopSlot6,
+        Action::ShopSlot7,
+        Action::ShopSlot8,
+        Action::PlankMode,
+        Action::RotatePlank,
+        Action::PlacePlank,
+        Action::Ready,
+        Action::Scoreboard,
+        Action::Console,
+        Action::ShrinkWindow,
+        Action::ClearHitMarkers,
+        Action::PanCamera,
+        Action::PauseSync,
+        Action::MarkLog,
+        Action::ShowMouseCoordinates,
+        Action::SpawnMenu,
+        Action::SpawnAtMouse,
+        Action::NextSpawnable,
+        Action::PreviousSpawnable,
+        Action::ToggleGodMode,
+        Action::ToggleInfiniteAmmo,
+        Action::ToggleGravity
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::Jump => "Jump",
+            Action::Sprint => "Sprint",
+            Action::Crouch => "Crouch",
+            Action::Fire => "Fire",
+            Action::Reload => "Reload",
+            Action::SwitchWeapon => "Switch weapon",
+            Action::PickUp => "Pick up weapon",
+            Action::DropWeapon => "Drop weapon",
+            Action::UnlockRotations => "Unlock rotations",
+            Action::PlaceTeleporter => "Place teleporter",
+            Action::Build => "Build",
+            Action::TimeStop => "Time stop",
+            Action::Shop => "Shop",
+            Action::ShopSlot1 => "Buy shop item 1",
+            Action::ShopSlot2 => "Buy shop item 2",
+            Action::ShopSlot3 => "Buy shop item 3",
+            Action::ShopSlot4 => "Buy shop item 4",
+            Action::ShopSlot5 => "Buy shop item 5",
+            Action::ShopSlot6 => "Buy shop item 6",
+            Action::ShopSlot7 => "Buy shop item 7",
+            Action::ShopSlot8 => "Buy shop item 8",
+            Action::PlankMode => "Plank mode",
+            Action::RotatePlank => "Rotate plank",
+            Action::PlacePlank => "Place plank",
+            Action::Ready => "Ready up",
+            Action::Scoreboard => "Scoreboard",
+            Action::Console => "Console",
+            Action::ShrinkWindow => "Shrink window",
+            Action::ClearHitMarkers => "Clear hit markers",
+            Action::PanCamera => "Pan camera",
+            Action::PauseSync => "Pause sync (hold)",
+            Action::MarkLog => "Mark the log",
+            Action::ShowMouseCoordinates => "Show mouse coordinates",
+            Action::SpawnMenu => "Spawn menu",
+            Action::SpawnAtMouse => "Spawn at mouse",
+            Action::NextSpawnable => "Next spawnable",
+            Action::PreviousSpawnable => "Previous spawnable",
+            Action::ToggleGodMode => "Toggle god mode",
+            Action::ToggleInfiniteAmmo => "Toggle infinite ammo",
+            Action::ToggleGravity => "Toggle gravity",
+        }
+    }
+
+    pub fn default_binding(&self) -> Binding {
+        match self {
+            Action::MoveLeft => Binding::Key(KeyCode::A),
+            Action::MoveRight => Binding::Key(KeyCode::D),
+            Action::Jump => Binding::Key(KeyCode::Space),
+            Action::Sprint => Binding::Key(KeyCode::LeftShift),
+            Action::Crouch => Binding::Key(KeyCode::S),
+            Action::Fire => Binding::Mouse(MouseButton::Left),
+            Action::Reload => Binding::Key(KeyCode::R),
+            Action::SwitchWeapon => Binding::Key(KeyCode::Q),
+            Action::PickUp => Binding::Key(KeyCode::W),
+            Action::DropWeapon => Binding::Key(KeyCode::N),
+            Action::UnlockRotations => Binding::Key(KeyCode::F),
+            Action::PlaceTeleporter => Binding::Key(KeyCode::B),
+            Action::Build => Binding::Key(KeyCode::E),
+            Action::TimeStop => Binding::Key(KeyCode::T),
+            Action::Shop => Binding::Key(KeyCode::U),
+            Action::ShopSlot1 => Binding::Key(KeyCode::Key1),
+            Action::ShopSlot2 => Binding::Key(KeyCode::Key2),
+            Action::ShopSlot3 => Binding::Key(KeyCode::Key3),
+            Action::ShopSlot4 => Binding::Key(KeyCode::Key4),
+            Action::ShopSlot5 => Binding::Key(KeyCode::Key5),
+            Action::ShopSlot6 => Binding::Key(KeyCode::Key6),
+            Action::ShopSlot7 => Binding::Key(KeyCode::Key7),
+            Action::ShopSlot8 => Binding::Key(KeyCode::Key8),
+            Action::PlankMode => Binding::Key(KeyCode::Y),
+            Action::RotatePlank => Binding::Key(KeyCode::X),
+            Action::PlacePlank => Binding::Mouse(MouseButton::Right),
+            Action::Ready => Binding::Key(KeyCode::Enter),
+            Action::Scoreboard => Binding::Key(KeyCode::CapsLock),
+            Action::Console => Binding::Key(KeyCode::Tab),
+            Action::ShrinkWindow => Binding::Key(KeyCode::K),
+            Action::ClearHitMarkers => Binding::Key(KeyCode::Delete),
+            Action::PanCamera => Binding::Key(KeyCode::LeftControl),
+            Action::PauseSync => Binding::Key(KeyCode::M),
+            Action::MarkLog => Binding::Key(KeyCode::H),
+            Action::ShowMouseCoordinates => Binding::Key(KeyCode::LeftAlt),
+            Action::SpawnMenu => Binding::Key(KeyCode::Z),
+            Action::SpawnAtMouse => Binding::Key(KeyCode::V),
+            Action::NextSpawnable => Binding::Key(KeyCode::Down),
+            Action::PreviousSpawnable => Binding::Key(KeyCode::Up),
+            Action::ToggleGodMode => Binding::Key(KeyCode::I),
+            Action::ToggleInfiniteAmmo => Binding::Key(KeyCode::O),
+            Action::ToggleGravity => Binding::Key(KeyCode::P),
+        }
+    }
+}
+
+/// A key or mouse button. Saved by name so the config file is easy to edit by hand
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton)
+}
+
+// everything that can be bound and the name it is saved and shown with
+const BINDABLE: &[(Binding, &str)] = &[
+    (Binding::Key(KeyCode::A), "A"),
+    (Binding::Key(KeyCode::B), "B"),
+    (Binding::Key(KeyCode::C), "C"),
+    (Binding::Key(KeyCode::D), "D"),
+    (Binding::Key(KeyCode::E), "E"),
+    (Binding::Key(KeyCode::F), "F"),
+    (Binding::Key(KeyCode::G), "G"),
+    (Binding::Key(KeyCode::H), "H"),
+    (Binding::Key(KeyCode::I), "I"),
+    (Binding::Key(KeyCode::J), "J"),
+    (Binding::Key(KeyCode::K), "K"),
+    (Binding::Key(KeyCode::L), "L"),
+    (Binding::Key(KeyCode::M), "M"),
+    (Binding::Key(KeyCode::N), "N"),
+    (Binding::Key(KeyCode::O), "O"),
+    (Binding::Key(KeyCode::P), "P"),
+    (Binding::Key(KeyCode::Q), "Q"),
+    (Binding::Key(KeyCode::R), "R"),
+    (Binding::Key(KeyCode::S), "S"),
+    (Binding::Key(KeyCode::T), "T"),
+    (Binding::Key(KeyCode::U), "U"),
+    (Binding::Key(KeyCode::V), "V"),
+    (Binding::Key(KeyCode::W), "W"),
+    (Binding::Key(KeyCode::X), "X"),
+    (Binding::Key(KeyCode::Y), "Y"),
+    (Binding::Key(KeyCode::Z), "Z"),
+    (Binding::Key(KeyCode::Key0), "0"),
+    (Binding::Key(KeyCode::Key1), "1"),
+    (Binding::Key(KeyCode::Key2), "2"),
+    (Binding::Key(KeyCode::Key3), "3"),
+    (Binding::Key(KeyCode::Key4), "4"),
+    (Binding::Key(KeyCode::Key5), "5"),
+    (Binding::Key(KeyCode::Key6), "6"),
+    (Binding::Key(KeyCode::Key7), "7"),
+    (Binding::Key(KeyCode::Key8), "8"),
+    (Binding::Key(KeyCode::Key9), "9"),
+    (Binding::Key(KeyCode::F3), "F3"),
+    (Binding::Key(KeyCode::F4), "F4"),
+    (Binding::Key(KeyCode::F7), "F7"),
+    (Binding::Key(KeyCode::F8), "F8"),
+    (Binding::Key(KeyCode::F9), "F9"),
+    (Binding::Key(KeyCode::F10), "F10"),
+    (Binding::Key(KeyCode::F11), "F11"),
+    (Binding::Key(KeyCode::F12), "F12"),
+    (Binding::Key(KeyCode::Space), "Space"),
+    (Binding::Key(KeyCode::Tab), "Tab"),
+    (Binding::Key(KeyCode::CapsLock), "Caps Lock"),
+    (Binding::Key(KeyCode::Enter), "Enter"),
+    (Binding::Key(KeyCode::Backspace), "Backspace"),
+    (Binding::Key(KeyCode::Delete), "Delete"),
+    (Binding::Key(KeyCode::GraveAccent), "`"),
+    (Binding::Key(KeyCode::Minus), "-"),
+    (Binding::Key(KeyCode::Equal), "="),
+    (Binding::Key(KeyCode::LeftBracket), "["),
+    (Binding::Key(KeyCode::RightBracket), "]"),
+    (Binding::Key(KeyCode::Semicolon), ";"),
+    (Binding::Key(KeyCode::Apostrophe), "'"),
+    (Binding::Key(KeyCode::Comma), ","),
+    (Binding::Key(KeyCode::Period), "."),
+    (Binding::Key(KeyCode::Slash), "/"),
+    (Binding::Key(KeyCode::Backslash), "\\"),
+    (Binding::Key(KeyCode::LeftShift), "Left Shift"),
+    (Binding::Key(KeyCode::RightShift), "Right Shift"),
+    (Binding::Key(KeyCode::LeftControl), "Left Control"),
+    (Binding::Key(KeyCode::RightControl), "Right Control"),
+    (Binding::Key(KeyCode::LeftAlt), "Left Alt"),
+    (Binding::Key(KeyCode::Up), "Up"),
+    (Binding::Key(KeyCode::Down), "Down"),
+    (Binding::Key(KeyCode::Left), "Left"),
+    (Binding::Key(KeyCode::Right), "Right"),
+    (Binding::Mouse(MouseButton::Left), "Mouse Left"),
+    (Binding::Mouse(MouseButton::Right), "Mouse Right"),
+    (Binding::Mouse(MouseButton::Middle), "Mouse Middle"),
+];
+
+impl Binding {
+    pub fn name(&self) -> &'static str {
+        BINDABLE.iter()
+            .find(|(binding, _)| binding == self)
+            .map(|(_, name)| *name)
+            .unwrap_or("?")
+    }
+
+    pub fn down(&self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_down(*key),
+            Binding::Mouse(button) => is_mouse_button_down(*button),
+        }
+    }
+
+    pub fn pressed(&self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_pressed(*key),
+            Binding::Mouse(button) => is_mouse_button_pressed(*button),
+        }
+    }
+
+    pub fn released(&self) -> bool {
+        match self {
+            Binding::Key(key) => is_key_released(*key),
+            Binding::Mouse(button) => is_mouse_button_released(*button),
+        }
+    }
+
+    /// Whatever bindable key or button was pressed this frame
+    pub fn last_pressed() -> Option<Binding> {
+
+        if let Some(key) = get_last_key_pressed() {
+            return BINDABLE.iter().map(|(binding, _)| *binding).find(|binding| *binding == Binding::Key(key));
+        }
+
+        [MouseButton::Left, MouseButton::Right, MouseButton::Middle].into_iter()
+            .find(|button| is_mouse_button_pressed(*button))
+            .map(Binding::Mouse)
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        BINDABLE.iter()
+            .find(|(_, bindable_name)| bindable_name.eq_ignore_ascii_case(&name))
+            .map(|(binding, _)| *binding)
+            .ok_or(format!("unknown key {}", name))
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.name().to_string()
+    }
+}
+
+/// Which key or button does each action. Loaded from the user's bindings file, anything missing from it gets the default
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Bindings {
+    pub actions: BTreeMap<Action, Binding>,
+    #[serde(default)]
+    pub aim_assist: bool, // pulls stick aim towards targets
+    #[serde(skip)]
+    pub gamepad: GamepadState, // refreshed by the client every tick
+    #[serde(skip)]
+    pub suppressed: bool // true while a menu is eating input
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            actions: Action::ALL.iter().map(|action| (*action, action.default_binding())).collect(),
+            aim_assist: false,
+            gamepad: GamepadState::default(),
+            suppressed: false
+        }
+    }
+}
+
+impl Bindings {
+
+    pub fn load() -> Self {
+
+        let mut bindings = match fs::read(BINDINGS_PATH) {
+            Ok(bytes) => match serde_yaml::from_slice::<Self>(&bytes) {
+                Ok(bindings) => bindings,
+                Err(error) => {
+                    log(&format!("failed to parse bindings file {}: {}", BINDINGS_PATH, error));
+
+                    Self::default()
+                },
+            },
+            Err(_) => Self::default(),
+        };
+
+        // actions added since the file was saved
+        for action in Action::ALL {
+            bindings.actions.entry(action).or_insert(action.default_binding());
+        }
+
+        bindings
+    }
+
+    pub fn save(&self) {
+
+        let yaml = match serde_yaml::to_string(self) {
+            Ok(yaml) => yaml,
+            Err(error) => {
+                log(&format!("failed to serialize bindings: {}", error));
+
+                return;
+            },
+        };
+
+        if let Err(error) = fs::write(BINDINGS_PATH, yaml) {
+            log(&format!("failed to write bindings file {}: {}", BINDINGS_PATH, error));
+        }
+    }
+
+    pub fn binding(&self, action: Action) -> Binding {
+        self.actions.get(&action).copied().unwrap_or(action.default_binding())
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+        !self.suppressed && (self.binding(action).down() || self.gamepad.down(action))
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        !self.suppressed && (self.binding(action).pressed() || self.gamepad.pressed(action))
+    }
+
+    pub fn released(&self, action: Action) -> bool {
+        !self.suppressed && (self.binding(action).released() || self.gamepad.released(action))
+    }
+
+    /// The name of the key for an action, for hints like "[U] shop"
+    pub fn key_name(&self, action: Action) -> &'static str {
+        self.binding(action).name()
+    }
+
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        self.actions.insert(action, binding);
+    }
+
+    /// Every other action that shares a binding with this one
+    pub fn conflicts(&self, action: Action) -> Vec<Action> {
+
+        let binding = self.binding(action);
+
+        Action::ALL.iter()
+            .filter(|other| **other != action && self.binding(**other) == binding)
+            .copied()
+            .collect()
+    }
+}
+
+/// The screen for changing bindings. Local to each client like the other menus
+pub struct RebindMenu {
+    pub open: bool,
+    pub selected: usize,
+    pub listening: bool // waiting for the new key for the selected action
+}
+
+impl RebindMenu {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            selected: 0,
+            listening: false
+        }
+    }
+
+    /// F1 opens the menu and F2 flips aim assist. The menu keys are fixed so nobody can unbind their way out of it
+    pub fn tick(&mut self, bindings: &mut Bindings) {
+
+        if is_key_released(KeyCode::F1) {
+            self.open = !self.open;
+            self.listening = false;
+        }
+
+        if !self.open {
+            return;
+        }
+
+        if self.listening {
+
+            if is_key_pressed(KeyCode::Escape) {
+                self.listening = false;
+
+                return;
+            }
+
+            if let Some(binding) = Binding::last_pressed() {
+                bindings.rebind(Action::ALL[self.selected], binding);
+                bindings.save();
+
+                self.listening = false;
+            }
+
+            return;
+        }
+
+        if is_key_pressed(KeyCode::Escape) {
+            self.open = false;
+
+            return;
+        }
+
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = (self.selected + Action::ALL.len() - 1) % Action::ALL.len();
+        }
+
+        if is_key_pressed(KeyCode::Down) {
+            self.selected = (self.selected + 1) % Action::ALL.len();
+        }
+
+        if is_key_pressed(KeyCode::Enter) {
+            self.listening = true;
+        }
+
+        if is_key_pressed(KeyCode::Delete) {
+            let action = Action::ALL[self.selected];
+
+            bindings.rebind(action, action.default_binding());
+            bindings.save();
+        }
+
+        if is_key_pressed(KeyCode::F2) {
+            bindings.aim_assist = !bindings.aim_assist;
+            bindings.save();
+        }
+    }
+
+    pub fn draw(&self, bindings: &Bindings) {
+
+        if !self.open {
+            return;
+        }
+
+        let width = 500.;
+        let height = 135. + (Action::ALL.len() as f32 * 22.);
+
+        let x = (screen_width() / 2.) - (width / 2.);
+        let y = ((screen_height() - height) / 2.).max(0.);
+
+        draw_rectangle(x, y, width, height, Color::from_rgba(0, 0, 0, 220));
+
+        draw_text("Controls", x + 10., y + 30., 30., WHITE);
+        draw_text("Up/Down select, Enter rebind, Delete reset, Esc close", x + 10., y + 55., 20., GRAY);
+
+        for (index, action) in Action::ALL.iter().enumerate() {
+
+            let row_y = y + 85. + (index as f32 * 22.);
+
+            let conflicts = bindings.conflicts(*action);
+
+            let color = match (index == self.selected, conflicts.is_empty()) {
+                (true, _) => YELLOW,
+                (false, false) => RED,
+                (false, true) => WHITE,
+            };
+
+            let binding_name = match index == self.selected && self.listening {
+                true => "press a key...",
+                false => bindings.key_name(*action),
+            };
+
+            draw_text(action.name(), x + 10., row_y, 22., color);
+            draw_text(binding_name, x + 230., row_y, 22., color);
+
+            if let Some(conflict) = conflicts.first() {
+                draw_text(&format!("also {}", conflict.name()), x + 340., row_y, 18., RED);
+            }
+        }
+
+        let aim_assist = match bindings.aim_assist {
+            true => "on",
+            false => "off",
+        };
+
+        draw_text(&format!("[F2] gamepad aim assist: {}", aim_assist), x + 10., y + 95. + (Action::ALL.len() as f32 * 22.), 22., WHITE);
+    }
+}
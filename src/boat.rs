@@ -0,0 +1,437 @@
+use diff::Diff;
+use gamelibrary::{rapier_to_macroquad, space::{Space, SyncColliderHandle, SyncImpulseJointHandle, SyncRigidBodyHandle}, texture_loader::TextureLoader};
+use macroquad::{color::{Color, WHITE}, math::{vec2, Vec2}, shapes::{draw_rectangle_ex, DrawRectangleParams}, texture::{draw_texture_ex, DrawTextureParams}};
+use nalgebra::{point, vector, Isometry2};
+use rapier2d::prelude::{ColliderBuilder, FixedJointBuilder, RigidBodyBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{bindings::{Action, Bindings}, TickContext};
+
+// both boat sprites are this size. the hull is drawn from the bottom one and the cabin from the top one
+const SPRITE_SIZE: Vec2 = Vec2::new(75., 63.);
+const BOAT_SCALE: f32 = 3.;
+
+const PLANK_SIZE: Vec2 = Vec2::new(60., 8.);
+const PLANK_COLOR: Color = Color::new(0.55, 0.35, 0.17, 1.);
+
+// a weld takes this much impulse in one step before the plank snaps off
+const WELD_BREAK_IMPULSE: f32 = 3000.;
+
+// planks snap to a grid this size in the boat's frame
+const SNAP_GRID: f32 = 8.;
+
+// how far from the middle of the hull you can still place planks
+const BUILD_RANGE: f32 = 250.;
+
+/// One rigid body of the boat
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct BoatPart {
+    pub body_handle: SyncRigidBodyHandle,
+    pub collider_handle: SyncColliderHandle,
+    pub sprite_path: String,
+    pub sprite_offset: Vec2 // from the body to the middle of the sprite, in the body's frame
+}
+
+impl BoatPart {
+    pub fn new(space: &mut Space, pos: Vec2, half_extents: Vec2, mass: f32, sprite_path: &str, sprite_offset: Vec2) -> Self {
+
+        let body_handle = space.sync_rigid_body_set.insert_sync(
+            RigidBodyBuilder::dynamic()
+                .position(vector![pos.x, pos.y].into())
+                .ccd_enabled(true)
+                .build()
+        );
+
+        let collider_handle = space.sync_collider_set.insert_with_parent_sync(
+            ColliderBuilder::cuboid(half_extents.x, half_extents.y)
+                .mass(mass)
+                .build(),
+            body_handle,
+            &mut space.sync_rigid_body_set
+        );
+
+        Self {
+            body_handle,
+            collider_handle,
+            sprite_path: sprite_path.to_string(),
+            sprite_offset
+        }
+    }
+
+    pub fn despawn(&self, space: &mut Space) {
+        space.sync_rigid_body_set.remove_sync(
+            self.body_handle,
+            &mut space.island_manager,
+            &mut space.sync_collider_set,
+            &mut space.sync_impulse_joint_set,
+            &mut space.multibody_joint_set,
+            true
+        );
+    }
+
+    pub async fn draw(&self, space: &Space, textures: &mut TextureLoader) {
+
+        let body = space.sync_rigid_body_set.get_sync(self.body_handle).unwrap();
+
+        // the sprite covers the whole boat so it isnt centered on this part's body
+        let sprite_center = body.position() * point![self.sprite_offset.x, self.sprite_offset.y];
+
+        let draw_pos = rapier_to_macroquad(&vec2(sprite_center.x, sprite_center.y));
+
+        let size = SPRITE_SIZE * BOAT_SCALE;
+
+        let texture = textures.get(&self.sprite_path).await;
+
+        draw_texture_ex(
+            texture,
+            draw_pos.x - (size.x / 2.),
+            draw_pos.y - (size.y / 2.),
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(size),
+                rotation: body.rotation().angle() * -1.,
+                ..Default::default()
+            }
+        );
+    }
+}
+
+/// A bought plank welded onto a boat. Once the weld breaks it's just loose wood
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Plank {
+    pub body_handle: SyncRigidBodyHandle,
+    pub collider_handle: SyncColliderHandle,
+    pub weld: Option<SyncImpulseJointHandle>
+}
+
+impl Plank {
+    pub fn draw(&self, space: &Space) {
+
+        let body = space.sync_rigid_body_set.get_sync(self.body_handle).unwrap();
+
+        let draw_pos = rapier_to_macroquad(&vec2(body.translation().x, body.translation().y));
+
+        draw_plank(draw_pos, body.rotation().angle(), PLANK_COLOR);
+    }
+}
+
+/// Draw a plank centered on a macroquad position
+fn draw_plank(draw_pos: Vec2, angle: f32, color: Color) {
+    draw_rectangle_ex(draw_pos.x, draw_pos.y, PLANK_SIZE.x, PLANK_SIZE.y, DrawRectangleParams {
+        offset: Vec2::new(0.5, 0.5),
+        rotation: angle * -1.,
+        color,
+    });
+}
+
+/// A boat made of a hull and a cabin welded together that players can add planks to.
+/// Whoever owns the boat simulates all of it, planks included
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Boat {
+    pub owner: String,
+    pub hull: BoatPart,
+    pub cabin: BoatPart,
+    pub cabin_joint: SyncImpulseJointHandle,
+    pub planks: Vec<Plank>
+}
+
+impl Boat {
+    pub fn new(space: &mut Space, pos: Vec2, owner: String) -> Self {
+
+        let size = SPRITE_SIZE * BOAT_SCALE;
+
+        // the hull is the bottom half of the sprite and the cabin sits on top of it
+        let hull = BoatPart::new(
+            space,
+            pos - vec2(0., size.y / 4.),
+            vec2(size.x / 2., size.y / 4.),
+            200.,
+            "assets/boat/bottom.png",
+            vec2(0., size.y / 4.)
+        );
+
+        let cabin = BoatPart::new(
+            space,
+            pos + vec2(0., size.y / 4.),
+            vec2(size.x / 3., size.y / 4.),
+            50.,
+            "assets/boat/top.png",
+            vec2(0., -size.y / 4.)
+        );
+
+        let local_hull_body = space.sync_rigid_body_set.get_local_handle(hull.body_handle);
+        let local_cabin_body = space.sync_rigid_body_set.get_local_handle(cabin.body_handle);
+
+        let cabin_joint = space.sync_impulse_joint_set.insert_sync(
+            local_hull_body,
+            local_cabin_body,
+            FixedJointBuilder::new()
+                .local_anchor1(vector![0., size.y / 4.].into())
+                .local_anchor2(vector![0., -size.y / 4.].into())
+                .contacts_enabled(false)
+            .build(),
+            true
+        );
+
+        Self {
+            owner,
+            hull,
+            cabin,
+            cabin_joint,
+            planks: Vec::new()
+        }
+    }
+
+    pub fn despawn(self, space: &mut Space) {
+        self.hull.despawn(space);
+        self.cabin.despawn(space);
+
+        for plank in self.planks {
+            space.sync_rigid_body_set.remove_sync(
+                plank.body_handle,
+                &mut space.island_manager,
+                &mut space.sync_collider_set,
+                &mut space.sync_impulse_joint_set,
+                &mut space.multibody_joint_set,
+                true
+            );
+        }
+    }
+
+    pub fn tick(&mut self, space: &mut Space, ctx: &mut TickContext) {
+
+        if self.owner != *ctx.uuid {
+            return;
+        }
+
+        for part in [&self.hull, &self.cabin] {
+            ctx.owned_rigid_bodies.push(part.body_handle);
+            ctx.owned_colliders.push(part.collider_handle);
+        }
+
+        ctx.owned_impulse_joints.push(self.cabin_joint);
+
+        self.break_overloaded_welds(space);
+
+        for plank in &self.planks {
+            ctx.owned_rigid_bodies.push(plank.body_handle);
+            ctx.owned_colliders.push(plank.collider_handle);
+
+            if let Some(weld) = plank.weld {
+                ctx.owned_impulse_joints.push(weld);
+            }
+        }
+    }
+
+    /// Snap planks off if they got hit too hard
+    pub fn break_overloaded_welds(&mut self, space: &mut Space) {
+
+        for plank in self.planks.iter_mut() {
+
+            let weld = match plank.weld {
+                Some(weld) => weld,
+                None => continue,
+            };
+
+            let impulse = match space.sync_impulse_joint_set.get_sync_mut(weld) {
+                Some(joint) => joint.impulses.magnitude(),
+                // the joint is already gone
+                None => {
+                    plank.weld = None;
+
+                    continue;
+                },
+            };
+
+            if impulse > WELD_BREAK_IMPULSE {
+                space.sync_impulse_joint_set.remove_sync(weld, true);
+
+                plank.weld = None;
+            }
+        }
+    }
+
+    /// Where a plank near `world_pos` would go, in the hull's frame. None if it's too far away to build
+    pub fn snap(&self, space: &Space, world_pos: Vec2) -> Option<Vec2> {
+
+        let hull_position = space.sync_rigid_body_set.get_sync(self.hull.body_handle)?.position();
+
+        let local_pos = hull_position.inverse_transform_point(&point![world_pos.x, world_pos.y]);
+
+        if local_pos.coords.norm() > BUILD_RANGE {
+            return None;
+        }
+
+        Some(vec2(
+            (local_pos.x / SNAP_GRID).round() * SNAP_GRID,
+            (local_pos.y / SNAP_GRID).round() * SNAP_GRID
+        ))
+    }
+
+    /// Turn a position and rotation in the hull's frame into world space. None if the hull hasnt synced yet
+    pub fn hull_to_world(&self, space: &Space, local_pos: Vec2, local_rotation: f32) -> Option<Isometry2<f32>> {
+
+        let hull_position = space.sync_rigid_body_set.get_sync(self.hull.body_handle)?.position();
+
+        Some(hull_position * Isometry2::new(vector![local_pos.x, local_pos.y], local_rotation))
+    }
+
+    /// Create a plank and weld it to the hull. Only the boat's owner can build on it. Returns whether the plank was attached
+    pub fn attach_plank(&mut self, space: &mut Space, local_pos: Vec2, local_rotation: f32, uuid: &String) -> bool {
+
+        if self.owner != *uuid {
+            return false;
+        }
+
+        let world_position = match self.hull_to_world(space, local_pos, local_rotation) {
+            Some(world_position) => world_position,
+            None => return false,
+        };
+
+        let body_handle = space.sync_rigid_body_set.insert_sync(
+            RigidBodyBuilder::dynamic()
+                .position(world_position)
+                .ccd_enabled(true)
+                .build()
+        );
+
+        let collider_handle = space.sync_collider_set.insert_with_parent_sync(
+            ColliderBuilder::cuboid(PLANK_SIZE.x / 2., PLANK_SIZE.y / 2.)
+                .mass(5.)
+                .build(),
+            body_handle,
+            &mut space.sync_rigid_body_set
+        );
+
+        let local_hull_body = space.sync_rigid_body_set.get_local_handle(self.hull.body_handle);
+        let local_plank_body = space.sync_rigid_body_set.get_local_handle(body_handle);
+
+        let weld = space.sync_impulse_joint_set.insert_sync(
+            local_hull_body,
+            local_plank_body,
+            FixedJointBuilder::new()
+                .local_frame1(Isometry2::new(vector![local_pos.x, local_pos.y], local_rotation))
+                .local_frame2(Isometry2::identity())
+                .contacts_enabled(false)
+            .build(),
+            true
+        );
+
+        self.planks.push(
+            Plank {
+                body_handle,
+                collider_handle,
+                weld: Some(weld)
+            }
+        );
+
+        true
+    }
+
+    pub async fn draw(&self, space: &Space, textures: &mut TextureLoader) {
+
+        self.hull.draw(space, textures).await;
+        self.cabin.draw(space, textures).await;
+
+        for plank in &self.planks {
+            plank.draw(space);
+        }
+    }
+}
+
+/// Where the plank we are about to place will go
+pub struct PlankPlacement {
+    pub boat: usize, // index into the level's boats
+    pub local_pos: Vec2,
+    pub local_rotation: f32,
+    pub world_position: Isometry2<f32>
+}
+
+/// Local plank placing state. Each client has its own so this isnt synced
+pub struct PlankPlacer {
+    pub active: bool,
+    pub rotation: f32, // relative to the boat
+    pub preview: Option<PlankPlacement>
+}
+
+impl PlankPlacer {
+    pub fn new() -> Self {
+        Self {
+            active: false,
+            rotation: 0.,
+            preview: None
+        }
+    }
+
+    /// Toggles placing, rotates the plank and places it. Returns the placement when the player places a plank
+    pub fn tick(&mut self, boats: &[Boat], space: &Space, mouse_pos: Vec2, planks_left: u32, bindings: &Bindings, uuid: &String) -> Option<PlankPlacement> {
+
+        self.preview = None;
+
+        if planks_left == 0 {
+            self.active = false;
+
+            return None;
+        }
+
+        if bindings.released(Action::PlankMode) {
+            self.active = !self.active;
+        }
+
+        if !self.active {
+            return None;
+        }
+
+        if bindings.released(Action::RotatePlank) {
+            self.rotation = (self.rotation + std::f32::consts::FRAC_PI_4) % std::f32::consts::TAU;
+        }
+
+        // snap to the closest of our boats we are in range of
+        let closest = boats.iter()
+            .enumerate()
+            .filter(|(_, boat)| boat.owner == *uuid)
+            .filter_map(|(boat_index, boat)| Some((boat_index, boat.snap(space, mouse_pos)?)))
+            .min_by(|(_, a), (_, b)| a.length().total_cmp(&b.length()));
+
+        let (boat_index, local_pos) = closest?;
+
+        self.preview = Some(
+            PlankPlacement {
+                boat: boat_index,
+                local_pos,
+                local_rotation: self.rotation,
+                world_position: boats[boat_index].hull_to_world(space, local_pos, self.rotation)?
+            }
+        );
+
+        if !bindings.released(Action::PlacePlank) {
+            return None;
+        }
+
+        self.preview.take()
+    }
+
+    /// A see through plank where it will be placed
+    pub fn draw(&self) {
+
+        let preview = match &self.preview {
+            Some(preview) => preview,
+            None => return,
+        };
+
+        let translation = preview.world_position.translation;
+
+        let draw_pos = rapier_to_macroquad(&vec2(translation.x, translation.y));
+
+        draw_plank(draw_pos, preview.world_position.rotation.angle(), Color { a: 0.5, ..PLANK_COLOR });
+    }
+}
@@ -1,10 +1,9 @@
-use std::{fs, net::SocketAddr, str::FromStr, sync::{mpsc, Arc, Mutex}, thread::{sleep, Thread}, time::{Duration, Instant}};
+use std::{collections::HashMap, fs, net::SocketAddr, str::FromStr, sync::{mpsc, Arc, Mutex}, thread::{sleep, Thread}, time::{Duration, Instant}};
 
 use futures::{executor::block_on, future::Select};
 use gamelibrary::{animation_loader::AnimationLoader, arenaiter::SyncArenaIterator, font_loader::FontLoader, log, mouse_world_pos, rapier_mouse_world_pos, sound::soundmanager::SoundManager, sync::client::SyncClient, texture_loader::TextureLoader, time::Time, traits::HasPhysics, uuid_string};
-use gilrs::GamepadId;
-use liquidators_lib::{console::Console, editor_client::EditorClient, editor_server::EditorServer, game_state::GameState, level::Level, main_menu::MainMenu, player::player::Player, server::Server, vec_remove_iter::IntoVecRemoveIter, ScreenShakeParameters, TickContext};
-use macroquad::{audio::set_sound_volume, camera::{set_camera, set_default_camera, Camera2D}, color::WHITE, input::{self, is_key_down, is_key_released, is_mouse_button_down, is_quit_requested, mouse_delta_position, mouse_position, mouse_wheel, prevent_quit, KeyCode}, math::{vec2, Rect, Vec2}, prelude::{camera::mouse, gl_use_default_material, gl_use_material, load_material, MaterialParams, PipelineParams, ShaderSource, UniformDesc, UniformType}, text::{draw_text, draw_text_ex, TextParams}, texture::{draw_texture_ex, DrawTextureParams}, time::get_fps, window::{next_frame, request_new_screen_size, screen_height, screen_width}};
+use liquidators_lib::{console::Console, editor_client::EditorClient, editor_server::EditorServer, game_state::GameState, level::Level, main_menu::MainMenu, player::{animation::AnimationLibrary, player::Player}, sandbox::SpawnMenu, shop::ShopMenu, boat::PlankPlacer, time_stop::Freezer, water::Splashes, bindings::{Action, Bindings, RebindMenu}, gamepad::GamepadInput, profile::Profile, cosmetics::CosmeticsCatalog, server::Server, sync_rate::SyncRate, vec_remove_iter::IntoVecRemoveIter, ScreenShakeParameters, TickContext};
+use macroquad::{audio::set_sound_volume, camera::{set_camera, set_default_camera, Camera2D}, color::WHITE, input::{self, is_key_released, is_mouse_button_down, is_quit_requested, mouse_delta_position, mouse_position, mouse_wheel, prevent_quit}, math::{vec2, Rect, Vec2}, prelude::{camera::mouse, gl_use_default_material, gl_use_material, load_material, MaterialParams, PipelineParams, ShaderSource, UniformDesc, UniformType}, text::{draw_text, draw_text_ex, TextParams}, texture::{draw_texture_ex, DrawTextureParams}, time::get_fps, window::{next_frame, request_new_screen_size, screen_height, screen_width}};
 use noise::{NoiseFn, Perlin};
 use tungstenite::http::request;
 
@@ -27,9 +26,20 @@ pub struct Client {
     pub update_count: i32,
     pub sync_client: Option<SyncClient<GameState>>,
     pub last_sync: web_time::Instant,
+    pub sync_rate: SyncRate,
     pub camera_rect: Rect,
-    pub active_gamepad: Option<GamepadId>,
+    pub gamepad: GamepadInput,
     pub console: Console,
+    pub spawn_menu: SpawnMenu,
+    pub shop_menu: ShopMenu,
+    pub plank_placer: PlankPlacer,
+    pub freezer: Freezer,
+    pub splashes: Splashes,
+    pub bindings: Bindings,
+    pub animation_library: AnimationLibrary, // the player clips, loaded from a data file
+    pub profile: Profile,
+    pub cosmetics: CosmeticsCatalog, // every head, body and tint players can pick, used to resolve everyone's picks
+    pub rebind_menu: RebindMenu,
     pub sounds: SelectedSoundManager,
     pub last_tick_mouse_world_pos: Vec2,
     pub main_menu: Option<MainMenu>,
@@ -56,13 +66,23 @@ impl Client {
 
         self.console.tick();
 
-        if is_key_released(KeyCode::Tab) {
+        self.gamepad.poll();
+
+        self.bindings.gamepad = self.gamepad.state.clone();
+
+        self.rebind_menu.tick(&mut self.bindings);
+
+        // nothing in game should react to keys pressed while rebinding
+        self.bindings.suppressed = self.rebind_menu.open;
+
+        // tab is used for the scoreboard
+        if self.bindings.released(Action::Console) {
             self.console.enabled = !self.console.enabled
         }
 
         
 
-        if is_key_released(KeyCode::K) {
+        if self.bindings.released(Action::ShrinkWindow) {
             request_new_screen_size(886., 480.);
         }
 
@@ -73,8 +93,17 @@ impl Client {
             camera_offset: &mut self.camera_offset,
             last_tick: &self.last_tick,
             camera_rect: &mut self.camera_rect,
-            active_gamepad: &self.active_gamepad,
+            active_gamepad: &self.gamepad.active,
             console: &mut self.console,
+            spawn_menu: &mut self.spawn_menu,
+            shop_menu: &mut self.shop_menu,
+            plank_placer: &mut self.plank_placer,
+            freezer: &mut self.freezer,
+            splashes: &mut self.splashes,
+            bindings: &self.bindings,
+            animations: &self.animation_library,
+            profile: &self.profile,
+            cosmetics: &self.cosmetics,
             owned_rigid_bodies: &mut vec![],
             owned_colliders: &mut vec![],
             owned_impulse_joints: &mut vec![],
@@ -210,7 +239,7 @@ impl Client {
             self.camera_rect.h /= 1.1;
         }
 
-        if is_key_down(KeyCode::LeftControl) {
+        if self.bindings.down(Action::PanCamera) {
             self.camera_rect.x += mouse_delta.x;
             self.camera_rect.y += mouse_delta.y;
         }
@@ -240,6 +269,41 @@ impl Client {
 
     }
 
+    /// Write the current time into our player and echo back the timestamps we have seen from everyone else
+    pub fn stamp_sync_timestamp(&mut self) {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+
+        let remote_timestamps: HashMap<String, u64> = self.game_state.level.players.iter()
+            .filter(|(_, player)| player.owner != self.uuid && player.last_sync_timestamp != 0)
+            .map(|(_, player)| (player.owner.clone(), player.last_sync_timestamp))
+            .collect();
+
+        for (_, player) in self.game_state.level.players.iter_mut() {
+            if player.owner == self.uuid {
+                player.last_sync_timestamp = now;
+                player.sync_echoes = remote_timestamps.clone();
+            }
+        }
+    }
+
+    /// Time the round trip from other players echoing our own timestamp back, so both ends of the measurement use our clock
+    pub fn sample_round_trip(&mut self) {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+
+        for (_, player) in self.game_state.level.players.iter() {
+            if player.owner == self.uuid {
+                continue;
+            }
+
+            // players that havent seen us sync yet dont have an echo for us
+            let Some(echoed_timestamp) = player.sync_echoes.get(&self.uuid) else {
+                continue;
+            };
+
+            self.sync_rate.record_echo(&player.owner, *echoed_timestamp, now);
+        }
+    }
+
     pub fn reset_level(&mut self) {
         log("resetting");
         let reset_level: Level = serde_yaml::from_str(&fs::read_to_string("level.yaml").unwrap()).unwrap();
@@ -254,7 +318,9 @@ impl Client {
             brick.owner = Some(self.uuid.clone());
         }
 
-        Player::spawn(&mut self.game_state.level.players, &mut self.game_state.level.space, self.uuid.clone(), &vec2(100., 300.), &mut self.textures);
+        let spawn_position = self.game_state.level.player_spawn_position(None, &[]);
+
+        Player::spawn(&mut self.game_state.level.players, &mut self.game_state.level.space, self.uuid.clone(), &spawn_position, &mut self.textures);
 
     }
 
@@ -313,11 +379,13 @@ impl Client {
                 // self.tick() updates self.last_tick automatically unlike self.last_sync
             }
             
-            // only sync 30 tps
+            // the sync rate adapts to the connection, starting at 120 tps
             // this could probably be optimized but this is more readable
 
-            if !is_key_down(KeyCode::M) {
-                if self.last_sync.elapsed().as_secs_f32() > 1./120. {
+            if !self.bindings.down(Action::PauseSync) {
+                if self.sync_rate.should_sync(&self.last_sync) {
+
+                    self.stamp_sync_timestamp();
 
                     if let Some(sync_client) = &mut self.sync_client {
 
@@ -325,9 +393,13 @@ impl Client {
                         
                         sync_client.sync(&mut self.game_state);
 
+                        self.sync_rate.record_sync(then.elapsed());
+
                         //println!("sync: {:?}", then.elapsed());
                     }
 
+                    self.sample_round_trip();
+
                     self.last_sync =web_time::Instant::now();
 
                 }   
@@ -336,7 +408,7 @@ impl Client {
             
             //println!("FPS: {}", 1. / then.elapsed().as_secs_f64());
             
-            if is_key_released(KeyCode::H) {
+            if self.bindings.released(Action::MarkLog) {
                 log("paused");
             }
 
@@ -416,7 +488,7 @@ impl Client {
         self.screen_shake.y_intensity = (self.screen_shake.y_intensity - y_intensity_decay).max(0.0);
 
     
-        self.game_state.draw(&mut self.textures, &self.camera_rect, &mut self.font_loader, &camera).await;
+        self.game_state.draw(&mut self.textures, &self.camera_rect, &mut self.font_loader, &camera, &self.uuid, &self.plank_placer, &self.splashes).await;
 
         set_default_camera();
 
@@ -427,8 +499,17 @@ impl Client {
             camera_offset: &mut self.camera_offset,
             last_tick: &self.last_tick,
             camera_rect: &mut self.camera_rect,
-            active_gamepad: &self.active_gamepad,
+            active_gamepad: &self.gamepad.active,
             console: &mut self.console,
+            spawn_menu: &mut self.spawn_menu,
+            shop_menu: &mut self.shop_menu,
+            plank_placer: &mut self.plank_placer,
+            freezer: &mut self.freezer,
+            splashes: &mut self.splashes,
+            bindings: &self.bindings,
+            animations: &self.animation_library,
+            profile: &self.profile,
+            cosmetics: &self.cosmetics,
             owned_rigid_bodies: &mut vec![],
             owned_colliders: &mut vec![],
             owned_impulse_joints: &mut vec![],
@@ -441,6 +522,8 @@ impl Client {
 
         self.game_state.draw_hud(&mut tick_context).await;
 
+        self.rebind_menu.draw(&self.bindings);
+
         self.console.draw().await;
 
         self.game_state.chat.draw().await;
@@ -453,7 +536,7 @@ impl Client {
         
         draw_text(format!("fps: {}", get_fps()).as_str(), screen_width() - 120., 25., 30., WHITE);
 
-        if is_key_down(KeyCode::LeftAlt) {
+        if self.bindings.down(Action::ShowMouseCoordinates) {
             let macroquad_screen_mouse_pos = mouse_position();
             let macroquad_world_mouse_pos = mouse_world_pos(&self.camera_rect);
             let rapier_world_mouse_pos = rapier_mouse_world_pos(&self.camera_rect);
@@ -544,9 +627,20 @@ impl Client {
             update_count: 0,
             sync_client: None,
             last_sync:web_time::Instant::now(),
+            sync_rate: SyncRate::new(),
             camera_rect: Rect::new(0., 200., 1280., 720.),
-            active_gamepad: None,
+            gamepad: GamepadInput::new(),
             console: Console::new(),
+            spawn_menu: SpawnMenu::new(),
+            shop_menu: ShopMenu::new(),
+            plank_placer: PlankPlacer::new(),
+            freezer: Freezer::new(),
+            splashes: Splashes::new(),
+            bindings: Bindings::load(),
+            animation_library: AnimationLibrary::load(),
+            profile: Profile::load(),
+            cosmetics: CosmeticsCatalog::load(),
+            rebind_menu: RebindMenu::new(),
             sounds: sound_manager,
             last_tick_mouse_world_pos: rapier_mouse_world_pos(&Rect::new(0., 200., 1280., 720.)),
             main_menu: Some(main_menu),
@@ -589,13 +683,12 @@ impl Client {
         // if we are the only player when connecting we are the host
         let is_host = game_state.level.players.len() == 0;
 
-        Player::spawn(&mut game_state.level.players, &mut game_state.level.space, uuid.clone(), &vec2(100., 300.), &mut textures);
-
-        //let gilrs = Gilrs::new().unwrap();
+        let spawn_position = game_state.level.player_spawn_position(None, &[]);
 
-        let active_gamepad: Option<GamepadId> = None; 
+        Player::spawn(&mut game_state.level.players, &mut game_state.level.space, uuid.clone(), &spawn_position, &mut textures);
 
-        // active_gamepad = gilrs.gamepads().next().map_or(None, |gamepad|{Some(gamepad.0)});
+        // picks up any gamepad that is already plugged in
+        let gamepad = GamepadInput::new();
 
         let mut sounds = SelectedSoundManager::new();
 
@@ -611,10 +704,21 @@ impl Client {
             camera_offset: Vec2::new(0., 0.),
             update_count: 0,
             last_sync:web_time::Instant::now(),
+            sync_rate: SyncRate::new(),
             camera_rect,
-            active_gamepad,
+            gamepad,
             sync_client: Some(sync_client),
             console: Console::new(),
+            spawn_menu: SpawnMenu::new(),
+            shop_menu: ShopMenu::new(),
+            plank_placer: PlankPlacer::new(),
+            freezer: Freezer::new(),
+            splashes: Splashes::new(),
+            bindings: Bindings::load(),
+            animation_library: AnimationLibrary::load(),
+            profile: Profile::load(),
+            cosmetics: CosmeticsCatalog::load(),
+            rebind_menu: RebindMenu::new(),
             sounds: sounds,
             last_tick_mouse_world_pos: rapier_mouse_world_pos(&camera_rect),
             main_menu: None,
@@ -2,4 +2,5 @@ use rapier2d::prelude::Group;
 
 pub const BODY_PART_GROUP: Group = Group::GROUP_1;
 pub const PARTICLES_GROUP: Group = Group::GROUP_2;
-pub const DETACHED_BODY_PART_GROUP: Group = Group::GROUP_3;
\ No newline at end of file
+pub const DETACHED_BODY_PART_GROUP: Group = Group::GROUP_3;
+pub const FLAG_GROUP: Group = Group::GROUP_4;
\ No newline at end of file
@@ -0,0 +1,154 @@
+use std::fs;
+
+use diff::Diff;
+use gamelibrary::log;
+use macroquad::color::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::profile::Profile;
+
+pub const COSMETICS_PATH: &str = "assets/cat/cosmetics.yaml";
+
+// what we fall back to if the catalog is missing or a pick is no longer in it
+pub const DEFAULT_HEAD_SPRITE: &str = "assets/cat/head.png";
+pub const DEFAULT_BODY_SPRITE: &str = "assets/cat/body.png";
+
+/// A color the whole player gets multiplied by
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Copy)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Tint {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8
+}
+
+impl Tint {
+    pub fn color(&self) -> Color {
+        Color::from_rgba(self.r, self.g, self.b, 255)
+    }
+}
+
+/// The parts of the character screen you can flip through
+#[derive(PartialEq, Clone, Copy)]
+pub enum CosmeticSlot {
+    Head,
+    Body,
+    Tint
+}
+
+impl CosmeticSlot {
+    pub const ALL: [CosmeticSlot; 3] = [
+        CosmeticSlot::Head,
+        CosmeticSlot::Body,
+        CosmeticSlot::Tint
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CosmeticSlot::Head => "Head",
+            CosmeticSlot::Body => "Body",
+            CosmeticSlot::Tint => "Color",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CosmeticSprite {
+    pub name: String,
+    pub sprite: String
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CosmeticTint {
+    pub name: String,
+    pub color: Tint
+}
+
+/// Every head, body and tint players can pick from, loaded from the cosmetics file
+#[derive(Serialize, Deserialize, Default)]
+pub struct CosmeticsCatalog {
+    #[serde(default)]
+    pub heads: Vec<CosmeticSprite>,
+    #[serde(default)]
+    pub bodies: Vec<CosmeticSprite>,
+    #[serde(default)]
+    pub tints: Vec<CosmeticTint>
+}
+
+impl CosmeticsCatalog {
+    pub fn load() -> Self {
+        match fs::read(COSMETICS_PATH) {
+            Ok(bytes) => match serde_yaml::from_slice::<Self>(&bytes) {
+                Ok(catalog) => catalog,
+                Err(error) => {
+                    log(&format!("failed to parse cosmetics file {}: {}", COSMETICS_PATH, error));
+
+                    Self::default()
+                },
+            },
+            Err(error) => {
+                log(&format!("failed to read cosmetics file {}: {}", COSMETICS_PATH, error));
+
+                Self::default()
+            },
+        }
+    }
+
+    /// The names of everything in a slot, in catalog order
+    pub fn names(&self, slot: CosmeticSlot) -> Vec<&str> {
+        match slot {
+            CosmeticSlot::Head => self.heads.iter().map(|head| head.name.as_str()).collect(),
+            CosmeticSlot::Body => self.bodies.iter().map(|body| body.name.as_str()).collect(),
+            CosmeticSlot::Tint => self.tints.iter().map(|tint| tint.name.as_str()).collect(),
+        }
+    }
+
+    /// Unknown or empty names get the first head in the catalog
+    pub fn head_sprite(&self, name: &str) -> &str {
+        Self::find_sprite(&self.heads, name).unwrap_or(DEFAULT_HEAD_SPRITE)
+    }
+
+    pub fn body_sprite(&self, name: &str) -> &str {
+        Self::find_sprite(&self.bodies, name).unwrap_or(DEFAULT_BODY_SPRITE)
+    }
+
+    /// None means the sprites are drawn as they are
+    pub fn tint(&self, name: &str) -> Option<Tint> {
+        self.tints.iter()
+            .find(|tint| tint.name == name)
+            .or(self.tints.first())
+            .map(|tint| tint.color)
+    }
+
+    fn find_sprite<'a>(sprites: &'a [CosmeticSprite], name: &str) -> Option<&'a str> {
+        sprites.iter()
+            .find(|sprite| sprite.name == name)
+            .or(sprites.first())
+            .map(|sprite| sprite.sprite.as_str())
+    }
+
+    /// Move the profile's pick in a slot forwards or backwards, wrapping around
+    pub fn cycle(&self, profile: &mut Profile, slot: CosmeticSlot, step: i32) {
+
+        let names = self.names(slot);
+
+        if names.is_empty() {
+            return;
+        }
+
+        let picked = match slot {
+            CosmeticSlot::Head => &mut profile.head,
+            CosmeticSlot::Body => &mut profile.body,
+            CosmeticSlot::Tint => &mut profile.tint,
+        };
+
+        // something thats not in the catalog counts as the first entry, same as when it gets drawn
+        let current = names.iter().position(|name| *name == picked.as_str()).unwrap_or(0) as i32;
+
+        let next = (current + step).rem_euclid(names.len() as i32) as usize;
+
+        *picked = names[next].to_string();
+    }
+}
@@ -0,0 +1,194 @@
+use std::{cell::Cell, ops::{Deref, DerefMut}};
+
+use diff::Diff;
+use serde::{Deserialize, Serialize};
+
+/// Wraps a part of the game state that rarely changes so the sync client can skip diffing it.
+///
+/// Any mutable access marks the value as dirty. When neither side of a diff is dirty the diff is skipped entirely without comparing the contents.
+/// This means you should only take a mutable reference when you actually intend to change something, otherwise the optimization does nothing.
+///
+/// - `diff` clears the flag on both sides because the sync client diffs against its own copy and that copy is caught up once the diff is sent
+/// - `apply` marks the value dirty so a copy that receives a remote change still gets compared on the next diff
+/// - `Clone` copies the flag along with the value
+///
+/// Only wrap values whose whole state lives inside them. Anything that keeps part of its state somewhere else (like bodies in the `Space`) can change without touching the wrapper
+pub struct Dirty<T> {
+    inner: T,
+    // this is a cell so that diff() can clear it even though it only gets a shared reference
+    dirty: Cell<bool>
+}
+
+impl<T> Dirty<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            // new values always need to be synced at least once
+            dirty: Cell::new(true)
+        }
+    }
+
+}
+
+impl<T> Deref for Dirty<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for Dirty<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.dirty.set(true);
+
+        &mut self.inner
+    }
+}
+
+impl<T: Clone> Clone for Dirty<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            dirty: self.dirty.clone()
+        }
+    }
+}
+
+// the dirty flag is just bookkeeping so it isnt part of equality
+impl<T: PartialEq> PartialEq for Dirty<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T: Default> Default for Dirty<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+// serialize as if the wrapper doesnt exist so existing level files still load
+impl<T: Serialize> Serialize for Dirty<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer {
+        self.inner.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Dirty<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de> {
+        Ok(Self::new(T::deserialize(deserializer)?))
+    }
+}
+
+impl<T: Diff> Diff for Dirty<T> {
+    // None means the subtree was skipped
+    type Repr = Option<T::Repr>;
+
+    fn diff(&self, other: &Self) -> Self::Repr {
+
+        if !self.dirty.get() && !other.dirty.get() {
+            return None;
+        }
+
+        // the diff is about to be sent so neither side needs to be compared again until something changes
+        self.dirty.set(false);
+        other.dirty.set(false);
+
+        Some(self.inner.diff(&other.inner))
+    }
+
+    fn apply(&mut self, diff: &Self::Repr) {
+        // whoever holds us still has to compare against the new contents, otherwise a copy that was caught up would never see the change
+        if let Some(diff) = diff {
+            self.inner.apply(diff);
+
+            self.dirty.set(true);
+        }
+    }
+
+    fn identity() -> Self {
+        Self::new(T::identity())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diff::Diff;
+
+    use super::Dirty;
+
+    // pretend we already synced once so both sides start out clean
+    fn synced(value: u32) -> (Dirty<u32>, Dirty<u32>) {
+        let ours = Dirty::new(value);
+        let theirs = ours.clone();
+
+        assert!(theirs.diff(&ours).is_some());
+
+        (ours, theirs)
+    }
+
+    #[test]
+    fn new_values_are_diffed() {
+        let ours = Dirty::new(1);
+        let theirs = Dirty::new(1);
+
+        assert!(theirs.diff(&ours).is_some());
+    }
+
+    #[test]
+    fn clean_values_are_skipped() {
+        let (ours, theirs) = synced(1);
+
+        assert!(theirs.diff(&ours).is_none());
+    }
+
+    #[test]
+    fn mutable_access_marks_dirty() {
+        let (mut ours, mut theirs) = synced(1);
+
+        *ours = 2;
+
+        let diff = theirs.diff(&ours);
+
+        assert!(diff.is_some());
+
+        theirs.apply(&diff);
+
+        assert_eq!(*theirs, 2);
+    }
+
+    #[test]
+    fn applying_a_remote_change_marks_dirty() {
+        let (mut ours, mut remote) = synced(1);
+
+        // a copy of ours that is already caught up, like the one the sync client diffs against
+        let previous = ours.clone();
+
+        *remote = 2;
+
+        let remote_diff = ours.diff(&remote);
+
+        ours.apply(&remote_diff);
+
+        assert!(previous.diff(&ours).is_some());
+    }
+
+    #[test]
+    fn clone_copies_the_flag() {
+        let (mut ours, _) = synced(1);
+
+        assert!(ours.clone().diff(&ours.clone()).is_none());
+
+        *ours = 2;
+
+        let copy = ours.clone();
+        let (_, clean) = synced(2);
+
+        assert!(clean.diff(&copy).is_some());
+    }
+}
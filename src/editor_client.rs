@@ -25,7 +25,8 @@ pub struct EditorClient {
     pub released: bool,
     clipboard: SyncArena<Structure>, // we need to make this generic somehow
     copy_mouse_position: Vec2, // the position of the mouse when we copied the structures
-    pasted: bool
+    pasted: bool,
+    resizing_water: Option<usize> // the water whose corner handle we are dragging
 }                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                 
 
 impl EditorClient {
@@ -75,7 +76,8 @@ impl EditorClient {
             released: true,
             clipboard: SyncArena::default(),
             copy_mouse_position: Vec2::ZERO,
-            pasted: false
+            pasted: false,
+            resizing_water: None
 
         }
     }
@@ -100,7 +102,7 @@ impl EditorClient {
                 owned_rigid_bodies.push(shotgun.rigid_body())
             }
 
-            for (_, structure) in &self.level.structures {
+            for (_, structure) in self.level.structures.iter() {
                 owned_rigid_bodies.push(structure.rigid_body_handle);
                 owned_colliders.push(structure.collider_handle);
             }
@@ -174,7 +176,7 @@ impl EditorClient {
         self.delete_selected_structures();
         self.create_drag_select();
         self.select_physics_objects();
-        self.level.editor_tick(&self.camera_rect, &self.uuid, &mut self.textures);
+        self.level.editor_tick(&self.camera_rect, &self.uuid, &mut self.textures, &mut self.resizing_water);
 
         self.copy_selected_structures();
         self.paste_copied_structures();
@@ -231,7 +233,7 @@ impl EditorClient {
 
             let joint_handle = self.level.space.sync_impulse_joint_set.insert_sync(body_handle, next_body_handle, joint, true);
 
-            for (_, structure) in &mut self.level.structures {
+            for (_, structure) in self.level.structures.iter_mut() {
                 if *structure.rigid_body_handle() == sync_body_handle {
                     structure.joint_handle = Some(joint_handle);
                 }
@@ -293,7 +295,7 @@ impl EditorClient {
     }
 
     pub fn highlight_selected_physics_objects(&self) {
-        for (_, structure) in &self.level.structures {
+        for (_, structure) in self.level.structures.iter() {
             
             let local_handle = self.level.space.sync_rigid_body_set.get_local_handle(structure.rigid_body_handle);
             if self.selected_physics_objects.contains(&local_handle) {
@@ -308,7 +310,7 @@ impl EditorClient {
             return;
         }
 
-        let mut structure_iter = SyncArenaIterator::new(&mut self.level.structures);
+        let mut structure_iter = SyncArenaIterator::new(&mut *self.level.structures);
 
         while let Some((structure, structures)) = structure_iter.next() {
 
@@ -333,7 +335,7 @@ impl EditorClient {
 
         let mut copied_structures = SyncArena::new();
 
-        for (_, structure) in &self.level.structures {
+        for (_, structure) in self.level.structures.iter() {
             if self.selected_physics_objects.contains(
                 &self.level.space.sync_rigid_body_set.get_local_handle(structure.rigid_body_handle)
             ) {
@@ -436,7 +438,7 @@ impl EditorClient {
     pub fn handle_menus(&mut self) {
         // this needs to be a function on the editor struct because structures cannot delete themselves
 
-        let mut structurs_iter = SyncArenaIterator::new(&mut self.level.structures);
+        let mut structurs_iter = SyncArenaIterator::new(&mut *self.level.structures);
 
         while let Some((structure, structures)) = structurs_iter.next() {
 
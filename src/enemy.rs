@@ -6,10 +6,10 @@ use gamelibrary::{space::{Space, SyncImpulseJointHandle}, sync_arena::{Index, Sy
 use macroquad::{input::{is_key_down, KeyCode}, math::Vec2};
 use nalgebra::{vector};
 use parry2d::math::Vector;
-use rapier2d::prelude::{Group, InteractionGroups, RevoluteJointBuilder};
+use rapier2d::prelude::RevoluteJointBuilder;
 use serde::{Deserialize, Serialize};
 
-use crate::{collider_groups::{BODY_PART_GROUP, DETACHED_BODY_PART_GROUP}, player::{self, body_part::BodyPart, player::{Facing, Player}}, weapon::BulletImpactData, TickContext};
+use crate::{player::{self, body_part::BodyPart, player::{Facing, Player}, ragdoll::{limp_interaction_groups, Limbs, LIMB_SEVER_DAMAGE, RAGDOLL_LIFETIME}}, weapon::BulletImpactData, TickContext};
 
 #[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
 #[diff(attr(
@@ -24,6 +24,16 @@ pub struct Enemy {
     head_body_joint: Option<SyncImpulseJointHandle>,
     last_jump: Time,
     player_target: Option<Index>,
+    #[serde(default)]
+    pub limbs: Limbs,
+    #[serde(default)]
+    pub died_at: Option<Time>,
+    #[serde(default)]
+    pub killer: Option<String>, // the owner of whoever killed us
+    #[serde(default)]
+    pub killer_weapon: Option<String>,
+    #[serde(default)]
+    pub death_recorded: bool // the host has posted our death to the kill feed
 }
 
 impl Enemy {
@@ -52,6 +62,8 @@ impl Enemy {
             Vec2::new(22., 19.)
         );
 
+        let limbs = Limbs::new(&body, position, space, textures, owner.clone());
+
         let head_body_joint = space.sync_impulse_joint_set.insert_sync(
             space.sync_rigid_body_set.get_local_handle(body.body_handle), 
             space.sync_rigid_body_set.get_local_handle(head.body_handle), 
@@ -72,15 +84,50 @@ impl Enemy {
             owner,
             head_body_joint: Some(head_body_joint),
             last_jump: Time::new(0),
-            player_target: None
+            player_target: None,
+            limbs,
+            died_at: None,
+            killer: None,
+            killer_weapon: None,
+            death_recorded: false
         }
     }
 
+    pub fn despawn(self, space: &mut Space) {
+
+        // removes the body AND the collider!
+        space.sync_rigid_body_set.remove_sync(
+            self.body.body_handle, 
+            &mut space.island_manager, 
+            &mut space.sync_collider_set, 
+            &mut space.sync_impulse_joint_set, 
+            &mut space.multibody_joint_set, 
+            true
+        );
+
+        space.sync_rigid_body_set.remove_sync(
+            self.head.body_handle, 
+            &mut space.island_manager, 
+            &mut space.sync_collider_set, 
+            &mut space.sync_impulse_joint_set, 
+            &mut space.multibody_joint_set, 
+            true
+        );
+
+        self.limbs.despawn(space);
+    }
+
+    /// Whether we have been lying dead long enough to clean up
+    pub fn ragdoll_expired(&self) -> bool {
+        self.died_at.as_ref().is_some_and(|died_at| died_at.elapsed().num_milliseconds() > RAGDOLL_LIFETIME)
+    }
+
+    /// Returns true if this impact killed us
     #[inline]
-    pub fn handle_bullet_impact(&mut self, space: &mut Space, bullet_impact: BulletImpactData) {
+    pub fn handle_bullet_impact(&mut self, space: &mut Space, bullet_impact: BulletImpactData) -> bool {
 
         if self.health <= 0 {
-            return;
+            return false;
         }
 
         let our_pos = space.sync_collider_set.get_sync(bullet_impact.impacted_collider).unwrap().position().translation;
@@ -89,21 +136,31 @@ impl Enemy {
 
         let fall_off_multiplier = (-0.01 * distance.norm()).exp();
 
+        let mut damage = 0;
+
         // body shot
         if bullet_impact.impacted_collider == self.body.collider_handle {
-            let damage = (50.0 * fall_off_multiplier).round() as i32;
-
-            self.health -= damage;
-
+            damage = (50.0 * fall_off_multiplier).round() as i32;
         }
         // head shot
         else if bullet_impact.impacted_collider == self.head.collider_handle {
+            damage = (100.0 * fall_off_multiplier).round() as i32;
+        }
+
+        self.health -= damage;
 
-            let damage = (100.0 * fall_off_multiplier).round() as i32;
+        let killed = self.health <= 0;
 
-            self.health -= damage;
+        if killed {
+            self.killer = Some(bullet_impact.shooter.clone());
+            self.killer_weapon = Some(bullet_impact.weapon.clone());
+        }
 
+        if killed && damage >= LIMB_SEVER_DAMAGE as i32 {
+            self.limbs.sever_nearest(space, Vec2::new(bullet_impact.shooter_pos.vector.x, bullet_impact.shooter_pos.vector.y));
         }
+
+        killed
     }
 
     pub fn tick(&mut self, space: &mut Space, ctx: &mut TickContext, players: &SyncArena<Player>) {
@@ -119,6 +176,8 @@ impl Enemy {
 
         self.body.tick(space, ctx);
 
+        self.limbs.tick(space, ctx);
+
         self.change_facing_direction(space);
 
         self.detach_head_if_dead(space);
@@ -141,17 +200,12 @@ impl Enemy {
 
             self.head_body_joint = None;
 
-            let new_interaction_groups = InteractionGroups::none()
-                .with_memberships(DETACHED_BODY_PART_GROUP)
-                .with_filter(
-                    Group::ALL
-                        .difference(DETACHED_BODY_PART_GROUP)
-                        .difference(BODY_PART_GROUP)
-                );
+            self.died_at = Some(Time::now());
 
-            
-            space.sync_collider_set.get_sync_mut(self.head.collider_handle).unwrap().set_collision_groups(new_interaction_groups);
-            space.sync_collider_set.get_sync_mut(self.body.collider_handle).unwrap().set_collision_groups(new_interaction_groups);
+            space.sync_collider_set.get_sync_mut(self.head.collider_handle).unwrap().set_collision_groups(limp_interaction_groups());
+            space.sync_collider_set.get_sync_mut(self.body.collider_handle).unwrap().set_collision_groups(limp_interaction_groups());
+
+            self.limbs.go_limp(space);
         }
     }
 
@@ -273,6 +327,8 @@ impl Enemy {
             Facing::Left => true,
         };
 
+        self.limbs.draw(textures, space, flip_x).await;
+
         self.body.draw(textures, space, flip_x).await;
 
         self.head.draw(textures, space, flip_x).await;
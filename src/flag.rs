@@ -0,0 +1,253 @@
+use diff::Diff;
+use gamelibrary::{rapier_to_macroquad, space::{Space, SyncColliderHandle, SyncImpulseJointHandle, SyncRigidBodyHandle}, time::Time, traits::HasPhysics};
+use macroquad::{color::Color, math::{vec2, Vec2}, shapes::{draw_rectangle_ex, draw_rectangle_lines, DrawRectangleParams}};
+use nalgebra::vector;
+use parry2d::math::Rotation;
+use rapier2d::prelude::{ColliderBuilder, Group, InteractionGroups, RevoluteJointBuilder, RigidBodyBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{collider_groups::{BODY_PART_GROUP, FLAG_GROUP, PARTICLES_GROUP}, team::Team, TickContext};
+
+/// Where a team's flag spawns and where that team captures enemy flags. Placed in the editor
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct FlagBase {
+    pub team: Team,
+    pub position: Vec2
+}
+
+impl FlagBase {
+
+    pub fn new(team: Team, position: Vec2) -> Self {
+        Self {
+            team,
+            position
+        }
+    }
+
+    /// Whether a point is close enough to the base to pick up, return or capture a flag
+    pub fn contains(&self, position: Vec2) -> bool {
+        self.position.distance(position) < 60.
+    }
+
+    pub fn draw(&self) {
+        let pos = rapier_to_macroquad(&self.position);
+
+        draw_rectangle_lines(pos.x - 60., pos.y - 60., 120., 120., 4., self.team.hud_color());
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Flag {
+    pub team: Team,
+    pub rigid_body_handle: SyncRigidBodyHandle,
+    pub collider_handle: SyncColliderHandle,
+    pub owner: String, // whoever is simulating the flag. this is the carrier while the flag is carried and the host otherwise
+    pub carrier: Option<String>, // the owner of the player carrying the flag
+    pub carrier_joint_handle: Option<SyncImpulseJointHandle>,
+    pub dropped_at: Option<Time>, // None if the flag is at its base or being carried
+    selected: bool,
+    dragging: bool,
+    drag_offset: Option<Vec2>
+}
+
+impl Flag {
+    pub fn new(team: Team, pos: Vec2, space: &mut Space, owner: String) -> Self {
+
+        let rigid_body_handle = space.sync_rigid_body_set.insert_sync(
+            RigidBodyBuilder::dynamic()
+                .position(vector![pos.x, pos.y].into())
+                .ccd_enabled(true)
+                .build()
+        );
+
+        // flags shouldnt push players around while being carried
+        let interaction_groups = InteractionGroups::none()
+            .with_memberships(FLAG_GROUP)
+            .with_filter(
+                Group::ALL
+                    .difference(BODY_PART_GROUP)
+                    .difference(PARTICLES_GROUP)
+            );
+
+        let collider_handle = space.sync_collider_set.insert_with_parent_sync(
+            ColliderBuilder::cuboid(15., 30.)
+                .mass(5.)
+                .collision_groups(interaction_groups)
+                .build(),
+            rigid_body_handle,
+            &mut space.sync_rigid_body_set
+        );
+
+        Self {
+            team,
+            rigid_body_handle,
+            collider_handle,
+            owner,
+            carrier: None,
+            carrier_joint_handle: None,
+            dropped_at: None,
+            selected: false,
+            dragging: false,
+            drag_offset: None
+        }
+    }
+
+    pub fn at_base(&self) -> bool {
+        self.carrier.is_none() && self.dropped_at.is_none()
+    }
+
+    pub fn position(&self, space: &Space) -> Vec2 {
+        let translation = space.sync_rigid_body_set.get_sync(self.rigid_body_handle).unwrap().translation();
+
+        vec2(translation.x, translation.y)
+    }
+
+    /// Attach the flag to a player's body. The carrier simulates the flag until it is dropped
+    pub fn pick_up(&mut self, space: &mut Space, carrier: String, carrier_body_handle: SyncRigidBodyHandle) {
+
+        let local_carrier_body_handle = space.sync_rigid_body_set.get_local_handle(carrier_body_handle);
+        let local_flag_body_handle = space.sync_rigid_body_set.get_local_handle(self.rigid_body_handle);
+
+        // hold the flag above the carrier's back
+        self.carrier_joint_handle = Some(space.sync_impulse_joint_set.insert_sync(
+            local_carrier_body_handle,
+            local_flag_body_handle,
+            RevoluteJointBuilder::new()
+                .local_anchor1(vector![0., 20.].into())
+                .local_anchor2(vector![0., -30.].into())
+                .limits([-0.3, 0.3])
+                .contacts_enabled(false)
+            .build(),
+            true
+        ));
+
+        self.owner = carrier.clone();
+        self.carrier = Some(carrier);
+        self.dropped_at = None;
+    }
+
+    /// Detach the flag from its carrier wherever they are
+    pub fn drop_from_carrier(&mut self, space: &mut Space, host: String) {
+
+        if let Some(carrier_joint_handle) = self.carrier_joint_handle {
+            space.sync_impulse_joint_set.remove_sync(carrier_joint_handle, true);
+        }
+
+        self.carrier_joint_handle = None;
+        self.carrier = None;
+        self.owner = host;
+        self.dropped_at = Some(Time::now());
+    }
+
+    /// Send the flag back to its base
+    pub fn return_to_base(&mut self, space: &mut Space, base: &FlagBase, host: String) {
+
+        if let Some(carrier_joint_handle) = self.carrier_joint_handle {
+            space.sync_impulse_joint_set.remove_sync(carrier_joint_handle, true);
+        }
+
+        self.carrier_joint_handle = None;
+        self.carrier = None;
+        self.owner = host;
+        self.dropped_at = None;
+
+        let body = space.sync_rigid_body_set.get_sync_mut(self.rigid_body_handle).unwrap();
+
+        body.set_position(vector![base.position.x, base.position.y].into(), true);
+        body.set_rotation(Rotation::new(0.), true);
+        body.set_linvel(vector![0., 0.], true);
+        body.set_angvel(0., true);
+    }
+
+    pub fn despawn(self, space: &mut Space) {
+        space.sync_rigid_body_set.remove_sync(
+            self.rigid_body_handle,
+            &mut space.island_manager,
+            &mut space.sync_collider_set,
+            &mut space.sync_impulse_joint_set,
+            &mut space.multibody_joint_set,
+            true
+        );
+    }
+
+    pub fn tick(&mut self, ctx: &mut TickContext) {
+
+        if self.owner != *ctx.uuid {
+            return;
+        }
+
+        ctx.owned_rigid_bodies.push(self.rigid_body_handle);
+        ctx.owned_colliders.push(self.collider_handle);
+
+        if let Some(carrier_joint_handle) = self.carrier_joint_handle {
+            ctx.owned_impulse_joints.push(carrier_joint_handle);
+        }
+    }
+
+    pub fn draw(&self, space: &Space) {
+
+        let body = space.sync_rigid_body_set.get_sync(self.rigid_body_handle).unwrap();
+
+        let pos = rapier_to_macroquad(&vec2(body.translation().x, body.translation().y));
+
+        let rotation = body.rotation().angle() * -1.;
+
+        // pole
+        draw_rectangle_ex(
+            pos.x,
+            pos.y,
+            4.,
+            60.,
+            DrawRectangleParams {
+                offset: Vec2::new(0.5, 0.5),
+                rotation,
+                color: Color::from_rgba(120, 80, 40, 255)
+            }
+        );
+
+        // cloth
+        draw_rectangle_ex(
+            pos.x,
+            pos.y,
+            26.,
+            18.,
+            DrawRectangleParams {
+                offset: Vec2::new(0., 1.6),
+                rotation,
+                color: self.team.hud_color()
+            }
+        );
+    }
+}
+
+impl HasPhysics for Flag {
+    fn collider_handle(&self) -> &SyncColliderHandle {
+        &self.collider_handle
+    }
+
+    fn rigid_body_handle(&self) -> &SyncRigidBodyHandle {
+        &self.rigid_body_handle
+    }
+
+    fn selected(&self) -> &bool {
+        &self.selected
+    }
+
+    fn selected_mut(&mut self) -> &mut bool {
+        &mut self.selected
+    }
+
+    fn dragging(&mut self) -> &mut bool {
+        &mut self.dragging
+    }
+
+    fn drag_offset(&mut self) -> &mut Option<Vec2> {
+        &mut self.drag_offset
+    }
+}
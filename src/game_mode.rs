@@ -0,0 +1,117 @@
+use gamelibrary::sync_arena::SyncArena;
+
+use crate::{chat::Chat, dirty::Dirty, level::Level, player::player::Player, sandbox::SandboxData, shop::Economy, TickContext};
+
+/// Everything in the game state a mode is allowed to touch while it ticks
+pub struct ModeContext<'a> {
+    pub level: &'a mut Level,
+    pub chat: &'a mut Dirty<Chat>,
+    pub economy: &'a mut Economy
+}
+
+/// Something that happened in a fight this tick. Emitted by `Player::damage`, which only runs on the host
+#[derive(Clone, Debug, PartialEq)]
+pub enum CombatEvent {
+    Damage {
+        attacker: Option<String>, // owners of the players involved. None if it wasnt a player
+        victim: String,
+        amount: u32
+    },
+    Kill {
+        killer: Option<String>,
+        victim: String,
+        weapon: Option<String>
+    }
+}
+
+/// The rules of a mode. Implemented by each mode's data so the state stays serializable and diffable.
+/// Add a variant to `Mode` and a line to `Mode::game_mode` and `Mode::game_mode_mut` and everything else is driven through these hooks
+pub trait GameMode {
+
+    fn name(&self) -> &'static str;
+
+    /// Whether players on the same team can damage each other
+    fn friendly_fire(&self) -> bool {
+        true
+    }
+
+    /// The number of players needed before warmup starts counting down
+    fn min_players(&self) -> usize {
+        2
+    }
+
+    /// Modes without rounds skip warmup and stay live forever
+    fn has_rounds(&self) -> bool {
+        true
+    }
+
+    /// Whether rounds end when the match timer runs out. Modes that end on their own condition return false
+    fn timed_rounds(&self) -> bool {
+        true
+    }
+
+    /// Whether dead players come back on their own after the respawn delay
+    fn respawns_players(&self) -> bool {
+        true
+    }
+
+    /// Shown to dead players in modes that dont respawn them on a timer
+    fn respawn_hint(&self) -> &'static str {
+        "Waiting to respawn"
+    }
+
+    /// Whether players slowly heal after staying out of the fight for a while
+    fn health_regen(&self) -> bool {
+        false
+    }
+
+    /// Whether players can buy from anywhere right now instead of walking to a shop
+    fn shop_open_everywhere(&self) -> bool {
+        false
+    }
+
+    /// The sandbox settings. None in every other mode so debug tools cant be used there
+    fn sandbox(&self) -> Option<&SandboxData> {
+        None
+    }
+
+    /// Clear scores and progress so a new round can start, keeping the mode's settings
+    fn reset(&mut self);
+
+    /// Whether the mode's own win condition has been met
+    fn round_over(&self, _players: &SyncArena<Player>) -> bool {
+        false
+    }
+
+    /// The big line at the top of the results screen
+    fn round_headline(&self, players: &SyncArena<Player>) -> String {
+        most_kills_headline(players)
+    }
+
+    /// Called on the host when a player connects
+    fn on_player_join(&mut self, _owner: &String, _mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {}
+
+    /// Called on the host when a player disconnects
+    fn on_player_leave(&mut self, _owner: &String, _mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {}
+
+    /// Called on the host whenever a player takes damage during a live round
+    fn on_damage(&mut self, _attacker: Option<&String>, _victim: &String, _amount: u32, _mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {}
+
+    /// Called on the host whenever a player dies during a live round
+    fn on_kill(&mut self, _killer: Option<&String>, _victim: &String, _mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {}
+
+    /// Called on every client each tick of a live round. Check `ctx.is_host` for anything authoritative
+    fn tick(&mut self, _mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {}
+
+    /// Draw mode specific things in the world
+    fn draw(&self, _level: &Level, _uuid: &String) {}
+
+    fn draw_hud(&self, _level: &Level, _ctx: &TickContext) {}
+}
+
+pub fn most_kills_headline(players: &SyncArena<Player>) -> String {
+    match players.iter().max_by_key(|(_, player)| player.kills) {
+        Some((_, player)) => format!("{} wins!", player.name()),
+        None => "Round over".to_string(),
+    }
+}
@@ -1,58 +1,852 @@
 
-use std::collections::HashSet;
+use std::{collections::HashSet, ops::{Deref, DerefMut}};
 
 use diff::Diff;
-use gamelibrary::{arenaiter::SyncArenaIterator, font_loader::FontLoader, log, rapier_mouse_world_pos, sync_arena::Index, texture_loader::TextureLoader, time::Time, traits::HasPhysics};
-use macroquad::{camera::Camera2D, input::is_key_released, math::{Rect, Vec2}};
+use gamelibrary::{font_loader::FontLoader, log, rapier_mouse_world_pos, sync_arena::{Index, SyncArena}, texture_loader::TextureLoader, time::Time, traits::HasPhysics};
+use macroquad::{camera::Camera2D, color::{Color, GREEN, LIGHTGRAY, ORANGE, RED, WHITE, YELLOW}, input::is_key_released, math::{Rect, Vec2}, shapes::draw_rectangle, text::draw_text, window::screen_width};
 use serde::{Deserialize, Serialize};
 
-use crate::{chat::Chat, enemy::Enemy, events::{self, Event}, level::Level, player::player::Player, structure::Structure, TickContext};
+use crate::{bindings::Action, boat::PlankPlacer, chat::Chat, dirty::Dirty, enemy::Enemy, events::{self, Event}, game_mode::{CombatEvent, GameMode, ModeContext}, level::Level, match_state::{MatchPhase, MatchRotation, MatchState, RoundResults}, player::player::Player, round_score::{RoundScore, Scorer}, sandbox::SandboxData, shop::{award_everyone_money, award_money, award_team_money, near_shop, Economy, EconomyRequestKind, OBJECTIVE_REWARD, WAVE_REWARD}, structure::Structure, water::Splashes, team::{assign_teams, kill_scoring_team, Team}, TickContext};
 
 #[derive(Serialize, Deserialize, Diff, Clone, PartialEq)]
 #[diff(attr(
     #[derive(Serialize, Deserialize)]
 ))]
 pub enum Mode {
-    Deathmatch,
-    Sandbox,
-    WaveSurvival(WaveSurvivalData)
+    Deathmatch(DeathmatchData),
+    Sandbox(SandboxData),
+    WaveSurvival(WaveSurvivalData),
+    TeamDeathmatch(TeamDeathmatchData),
+    CaptureTheFlag(CaptureTheFlagData),
+    KingOfTheHill(KingOfTheHillData)
+}
+
+impl Mode {
+
+    /// The rules for whichever mode this is. New modes need to be listed here and in `game_mode_mut`
+    pub fn game_mode(&self) -> &(dyn GameMode + 'static) {
+        match self {
+            Mode::Deathmatch(data) => data,
+            Mode::Sandbox(data) => data,
+            Mode::WaveSurvival(data) => data,
+            Mode::TeamDeathmatch(data) => data,
+            Mode::CaptureTheFlag(data) => data,
+            Mode::KingOfTheHill(data) => data,
+        }
+    }
+
+    pub fn game_mode_mut(&mut self) -> &mut (dyn GameMode + 'static) {
+        match self {
+            Mode::Deathmatch(data) => data,
+            Mode::Sandbox(data) => data,
+            Mode::WaveSurvival(data) => data,
+            Mode::TeamDeathmatch(data) => data,
+            Mode::CaptureTheFlag(data) => data,
+            Mode::KingOfTheHill(data) => data,
+        }
+    }
+}
+
+// lets us call the hooks straight on the mode like mode.tick(...)
+impl Deref for Mode {
+    type Target = dyn GameMode;
+
+    fn deref(&self) -> &Self::Target {
+        self.game_mode()
+    }
+}
+
+impl DerefMut for Mode {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.game_mode_mut()
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, Clone, PartialEq)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct TeamDeathmatchData {
+    pub friendly_fire: bool,
+    pub score_limit: u32, // the first team to reach this many kills wins the round
+    #[serde(default)]
+    pub score: RoundScore
+}
+
+impl TeamDeathmatchData {
+    pub fn new() -> Self {
+        Self {
+            friendly_fire: false,
+            score_limit: 20,
+            score: RoundScore::new()
+        }
+    }
+
+    pub fn start_new_round(&mut self) {
+        self.score.reset();
+    }
+}
+
+impl GameMode for TeamDeathmatchData {
+
+    fn name(&self) -> &'static str {
+        "Team Deathmatch"
+    }
+
+    fn friendly_fire(&self) -> bool {
+        self.friendly_fire
+    }
+
+    fn reset(&mut self) {
+        self.start_new_round();
+    }
+
+    fn round_over(&self, _players: &SyncArena<Player>) -> bool {
+        self.score.round_over()
+    }
+
+    fn round_headline(&self, players: &SyncArena<Player>) -> String {
+        self.score.headline(players)
+    }
+
+    fn on_player_join(&mut self, _owner: &String, mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {
+        assign_teams(&mut mode_ctx.level.players);
+    }
+
+    fn on_player_leave(&mut self, _owner: &String, mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {
+        assign_teams(&mut mode_ctx.level.players);
+    }
+
+    fn on_kill(&mut self, killer: Option<&String>, victim: &String, mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {
+
+        // the match lifecycle takes over once someone wins
+        if self.score.round_over() {
+            return;
+        }
+
+        if let Some(team) = kill_scoring_team(killer, victim, &mode_ctx.level.players) {
+            self.score.add_point(&Scorer::Team(team), self.score_limit);
+        }
+
+        announce_winner(&self.score, mode_ctx);
+    }
+
+    fn draw_hud(&self, level: &Level, ctx: &TickContext) {
+        draw_team_scores(&self.score, &format!("first to {}", self.score_limit));
+
+        draw_winner(&self.score, level, ctx, 120.);
+    }
+}
+
+/// Post the winner to chat on the tick the round is won
+fn announce_winner(score: &RoundScore, mode_ctx: &mut ModeContext) {
+    if let Some(winner) = &score.winner {
+        mode_ctx.chat.add_message("Server".to_string(), format!("{} wins!", winner.name(&mode_ctx.level.players)));
+    }
+}
+
+/// Red and blue scores side by side at the top of the screen
+fn draw_team_scores(score: &RoundScore, goal: &str) {
+
+    draw_text(&format!("{}", score.score(&Scorer::Team(Team::Red))), (screen_width() / 2.) - 60., 40., 40., Team::Red.hud_color());
+    draw_text("-", (screen_width() / 2.) - 5., 40., 40., WHITE);
+    draw_text(&format!("{}", score.score(&Scorer::Team(Team::Blue))), (screen_width() / 2.) + 30., 40., 40., Team::Blue.hud_color());
+
+    draw_text(goal, (screen_width() / 2.) - (goal.len() as f32 * 4.5), 65., 20., WHITE);
+}
+
+fn draw_winner(score: &RoundScore, level: &Level, ctx: &TickContext, y: f32) {
+    if let Some(winner) = &score.winner {
+        draw_text(&format!("{} wins!", winner.name(&level.players)), (screen_width() / 2.) - 150., y, 50., winner.color(ctx.uuid));
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, Clone, PartialEq)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct CaptureTheFlagData {
+    pub friendly_fire: bool,
+    pub score_limit: u32, // the first team to capture this many flags wins the round
+    #[serde(default)]
+    pub score: RoundScore,
+    pub flag_return_duration: u32 // the number of ms a dropped flag sits before going back to its base
+}
+
+impl CaptureTheFlagData {
+    pub fn new() -> Self {
+        Self {
+            friendly_fire: false,
+            score_limit: 3,
+            score: RoundScore::new(),
+            flag_return_duration: 30000
+        }
+    }
+}
+
+impl GameMode for CaptureTheFlagData {
+
+    fn name(&self) -> &'static str {
+        "Capture the Flag"
+    }
+
+    fn friendly_fire(&self) -> bool {
+        self.friendly_fire
+    }
+
+    fn reset(&mut self) {
+        self.score.reset();
+    }
+
+    fn round_over(&self, _players: &SyncArena<Player>) -> bool {
+        self.score.round_over()
+    }
+
+    fn round_headline(&self, players: &SyncArena<Player>) -> String {
+        self.score.headline(players)
+    }
+
+    fn on_player_join(&mut self, _owner: &String, mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {
+        assign_teams(&mut mode_ctx.level.players);
+    }
+
+    fn on_player_leave(&mut self, _owner: &String, mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {
+        assign_teams(&mut mode_ctx.level.players);
+    }
+
+    fn tick(&mut self, mode_ctx: &mut ModeContext, ctx: &mut TickContext) {
+
+        if !*ctx.is_host {
+            return;
+        }
+
+        mode_ctx.level.spawn_missing_flags(ctx.uuid);
+
+        // the match lifecycle takes over once someone wins
+        if self.score.round_over() {
+            return;
+        }
+
+        let captures = mode_ctx.level.update_flags(self.flag_return_duration, ctx.uuid);
+
+        for team in captures {
+            self.score.add_point(&Scorer::Team(team), self.score_limit);
+
+            award_team_money(mode_ctx.economy, &mode_ctx.level.players, team, OBJECTIVE_REWARD);
+
+            mode_ctx.chat.add_message("Server".to_string(), format!("{} team captured the flag!", team.name()));
+        }
+
+        announce_winner(&self.score, mode_ctx);
+    }
+
+    fn draw_hud(&self, level: &Level, ctx: &TickContext) {
+
+        draw_team_scores(&self.score, &format!("first to {} captures", self.score_limit));
+
+        // where each flag is
+        for (index, flag) in level.flags.iter().enumerate() {
+
+            let status = if flag.at_base() {
+                "home"
+            } else if flag.carrier.is_some() {
+                "taken"
+            } else {
+                "dropped"
+            };
+
+            draw_text(&format!("{} flag: {}", flag.team.name(), status), 20., 60. + (index as f32 * 25.), 25., flag.team.hud_color());
+        }
+
+        draw_winner(&self.score, level, ctx, 120.);
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, Clone, PartialEq)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct KingOfTheHillData {
+    pub teams: bool, // play in teams instead of free for all
+    pub friendly_fire: bool,
+    pub active_zone: usize, // index into the level's hill zones
+    pub zone_rotation_interval: Option<u32>, // move the hill to the next zone every this many ms. None keeps it in one place
+    pub last_zone_rotation: Time,
+    pub capture_duration: u32, // the number of ms someone has to stand alone in the hill to take it
+    pub capture_progress: f32, // 0 to 1
+    pub capturing: Option<Scorer>,
+    pub controller: Option<Scorer>,
+    pub contested: bool,
+    pub last_point: Time,
+    pub score_limit: u32,
+    #[serde(default)]
+    pub score: RoundScore
+}
+
+impl KingOfTheHillData {
+    pub fn new(teams: bool) -> Self {
+        Self {
+            teams,
+            friendly_fire: false,
+            active_zone: 0,
+            zone_rotation_interval: Some(60000),
+            last_zone_rotation: Time::now(),
+            capture_duration: 5000,
+            capture_progress: 0.,
+            capturing: None,
+            controller: None,
+            contested: false,
+            last_point: Time::now(),
+            score_limit: 100,
+            score: RoundScore::new()
+        }
+    }
+
+    /// Reset who owns the hill, used when the hill moves or a new round starts
+    pub fn reset_hill(&mut self) {
+        self.capture_progress = 0.;
+        self.capturing = None;
+        self.controller = None;
+        self.contested = false;
+        self.last_zone_rotation = Time::now();
+    }
+
+    pub fn rotation_due(&self) -> bool {
+        match self.zone_rotation_interval {
+            Some(zone_rotation_interval) => self.last_zone_rotation.elapsed().num_milliseconds() >= zone_rotation_interval.into(),
+            None => false,
+        }
+    }
+
+    /// Update capture progress and scoring from who is currently standing in the hill
+    pub fn update_hill(&mut self, occupants: Vec<Scorer>, tick_duration: f32) {
+
+        self.contested = occupants.len() > 1;
+
+        // points and progress only happen when exactly one team or player holds the hill
+        if occupants.len() != 1 {
+            self.last_point = Time::now();
+
+            return;
+        }
+
+        let occupant = occupants.into_iter().next().unwrap();
+
+        if self.controller.as_ref() == Some(&occupant) {
+
+            if self.last_point.elapsed().num_milliseconds() < 1000 {
+                return;
+            }
+
+            self.last_point = Time::now();
+
+            self.score.add_point(&occupant, self.score_limit);
+
+            return;
+        }
+
+        // someone new stepped in so the old progress doesnt count
+        if self.capturing.as_ref() != Some(&occupant) {
+            self.capturing = Some(occupant.clone());
+            self.capture_progress = 0.;
+        }
+
+        self.capture_progress += (tick_duration * 1000.) / self.capture_duration as f32;
+
+        if self.capture_progress >= 1. {
+            self.capture_progress = 0.;
+            self.capturing = None;
+            self.controller = Some(occupant);
+            self.last_point = Time::now();
+        }
+    }
+
+    pub fn start_new_round(&mut self) {
+        self.score.reset();
+        self.active_zone = 0;
+        self.reset_hill();
+    }
+}
+
+impl GameMode for KingOfTheHillData {
+
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+
+    fn friendly_fire(&self) -> bool {
+        self.friendly_fire
+    }
+
+    fn reset(&mut self) {
+        self.start_new_round();
+    }
+
+    fn round_over(&self, _players: &SyncArena<Player>) -> bool {
+        self.score.round_over()
+    }
+
+    fn round_headline(&self, players: &SyncArena<Player>) -> String {
+        self.score.headline(players)
+    }
+
+    fn on_player_join(&mut self, _owner: &String, mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {
+        if self.teams {
+            assign_teams(&mut mode_ctx.level.players);
+        }
+    }
+
+    fn on_player_leave(&mut self, owner: &String, mode_ctx: &mut ModeContext, _ctx: &mut TickContext) {
+
+        if self.teams {
+            assign_teams(&mut mode_ctx.level.players);
+        }
+
+        let holder = Scorer::Player(owner.clone());
+
+        self.score.remove(&holder);
+
+        if self.controller.as_ref() == Some(&holder) || self.capturing.as_ref() == Some(&holder) {
+            self.reset_hill();
+        }
+    }
+
+    fn tick(&mut self, mode_ctx: &mut ModeContext, ctx: &mut TickContext) {
+
+        if !*ctx.is_host {
+            return;
+        }
+
+        if mode_ctx.level.hill_zones.is_empty() {
+            return;
+        }
+
+        // the match lifecycle takes over once someone wins
+        if self.score.round_over() {
+            return;
+        }
+
+        if self.rotation_due() || self.active_zone >= mode_ctx.level.hill_zones.len() {
+            self.active_zone = (self.active_zone + 1) % mode_ctx.level.hill_zones.len();
+
+            self.reset_hill();
+
+            mode_ctx.chat.add_message("Server".to_string(), "The hill has moved!".to_string());
+        }
+
+        let occupants = mode_ctx.level.hill_zones[self.active_zone].occupants(&mode_ctx.level.space, &mode_ctx.level.players);
+
+        let previous_controller = self.controller.clone();
+
+        self.update_hill(occupants, ctx.last_tick_duration.as_secs_f32());
+
+        if self.controller != previous_controller {
+            if let Some(controller) = &self.controller {
+                mode_ctx.chat.add_message("Server".to_string(), format!("{} captured the hill!", controller.name(&mode_ctx.level.players)));
+
+                match controller {
+                    Scorer::Team(team) => award_team_money(mode_ctx.economy, &mode_ctx.level.players, *team, OBJECTIVE_REWARD),
+                    Scorer::Player(owner) => award_money(mode_ctx.economy, owner, OBJECTIVE_REWARD),
+                }
+            }
+        }
+
+        announce_winner(&self.score, mode_ctx);
+    }
+
+    fn draw(&self, level: &Level, uuid: &String) {
+
+        let zone = match level.hill_zones.get(self.active_zone) {
+            Some(zone) => zone,
+            None => return,
+        };
+
+        let color = match (&self.controller, self.contested) {
+            (_, true) => ORANGE,
+            (Some(controller), false) => controller.color(uuid),
+            (None, false) => LIGHTGRAY,
+        };
+
+        zone.draw(&level.space, color);
+    }
+
+    fn draw_hud(&self, level: &Level, ctx: &TickContext) {
+
+        let status = match (&self.controller, self.contested) {
+            (_, true) => "Hill contested!".to_string(),
+            (Some(controller), false) => format!("{} holds the hill", controller.name(&level.players)),
+            (None, false) => "Nobody holds the hill".to_string(),
+        };
+
+        draw_text(&status, (screen_width() / 2.) - 150., 40., 35., WHITE);
+
+        // capture progress bar
+        if let Some(capturing) = &self.capturing {
+            let bar_width = 300.;
+            let bar_x = (screen_width() / 2.) - (bar_width / 2.);
+
+            draw_rectangle(bar_x, 55., bar_width, 15., Color::from_rgba(0, 0, 0, 150));
+            draw_rectangle(bar_x, 55., bar_width * self.capture_progress.min(1.), 15., capturing.color(ctx.uuid));
+        }
+
+        if let Some(zone_rotation_interval) = self.zone_rotation_interval {
+            let remaining_millis = i64::from(zone_rotation_interval) - self.last_zone_rotation.elapsed().num_milliseconds();
+
+            draw_text(&format!("Hill moves in {}", (remaining_millis.max(0) + 999) / 1000), (screen_width() / 2.) - 70., 90., 20., WHITE);
+        }
+
+        draw_text(&format!("first to {}", self.score_limit), 20., 60., 20., WHITE);
+
+        for (index, score) in self.score.standings().iter().enumerate() {
+            draw_text(&format!("{}: {}", score.scorer.name(&level.players), score.score), 20., 85. + (index as f32 * 25.), 25., score.scorer.color(ctx.uuid));
+        }
+
+        draw_winner(&self.score, level, ctx, 140.);
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, Clone, PartialEq)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct  WaveSurvivalData {
+    
+    pub wave: u32,
+    pub last_wave_end: Time,
+    pub ready: HashSet<String>, // owners of the players that are ready. only the host writes this
+    pub wave_active: bool,
+    pub enemy_reserve: u32, // the total number of remaining enemies that will spawn this wave
+    pub batch_spawn_rate: u32, // the number of ms to wait between wave batches
+    pub batch_size: u32, // the number of enemies that will spawn in each batch,
+    pub last_batch_spawn: Time, 
+    pub enemy_health: i32, // the health each enemy spawns with this wave
+    pub intermission_duration: u32, // the number of ms between the end of a wave and the start of the next
+    pub auto_start_ready_fraction: Option<f32>, // start a countdown once this fraction of players are ready. None disables the countdown
+    pub auto_start_delay: u32, // the number of ms the auto start countdown lasts
+    pub auto_start_countdown: Option<Time>, // when the auto start countdown began
+}
+
+impl WaveSurvivalData {
+
+    /// Create new wave survival data. The first wave started will be wave 1
+    pub fn new() -> Self {
+        Self {
+            wave: 0,
+            last_wave_end: Time::new(0),
+            ready: HashSet::new(),
+            wave_active: false,
+            enemy_reserve: 0,
+            batch_spawn_rate: 5000,
+            batch_size: 1,
+            last_batch_spawn: Time::new(0),
+            enemy_health: 100,
+            intermission_duration: 15000,
+            auto_start_ready_fraction: Some(0.5),
+            auto_start_delay: 10000,
+            auto_start_countdown: None
+        }
+    }
+
+    /// Advance to the next wave and scale the difficulty for it
+    pub fn start_next_wave(&mut self) {
+        self.wave += 1;
+
+        // how many waves we have already beaten
+        let difficulty = (self.wave - 1) as f32;
+
+        self.enemy_reserve = 10 + (self.wave - 1) * 5;
+        self.enemy_health = (100. * 1.1_f32.powf(difficulty)).round() as i32;
+        self.batch_spawn_rate = ((5000. * 0.9_f32.powf(difficulty)).round() as u32).max(1000);
+        self.batch_size = 1 + self.wave / 3;
+
+        self.wave_active = true;
+
+        // everyone needs to ready up again for the next wave. the players ready flags were for the wave we just started so they dont count anymore
+        self.ready.clear();
+        self.auto_start_countdown = None;
+
+        // spawn the first batch right away
+        self.last_batch_spawn = Time::new(0);
+    }
+
+    pub fn end_wave(&mut self) {
+        self.wave_active = false;
+        self.enemy_reserve = 0;
+        self.last_wave_end = Time::now();
+    }
+
+    pub fn in_intermission(&self) -> bool {
+        !self.wave_active && self.last_wave_end.elapsed().num_milliseconds() < self.intermission_duration.into()
+    }
+
+    pub fn everyone_ready(&self, players: &SyncArena<Player>) -> bool {
+        players.len() > 0 && self.ready.len() == players.len()
+    }
+
+    /// Rebuild the ready list from what each player asked for and return whether enough players are ready to start the next wave.
+    /// Also handles starting and cancelling the auto start countdown. Only the host should call this
+    pub fn update_ready(&mut self, players: &SyncArena<Player>) -> bool {
+
+        // players that left just drop out of the list
+        self.ready = players.iter()
+            .filter(|(_, player)| player.ready_for_wave == self.wave + 1)
+            .map(|(_, player)| player.owner.clone())
+            .collect();
+
+        let player_count = players.len();
+
+        if player_count == 0 {
+            return false;
+        }
+
+        if self.everyone_ready(players) {
+            return true;
+        }
+
+        let auto_start_ready_fraction = match self.auto_start_ready_fraction {
+            Some(auto_start_ready_fraction) => auto_start_ready_fraction,
+            None => return false,
+        };
+
+        let ready_fraction = self.ready.len() as f32 / player_count as f32;
+
+        if self.ready.is_empty() || ready_fraction < auto_start_ready_fraction {
+            self.auto_start_countdown = None;
+
+            return false;
+        }
+
+        match &self.auto_start_countdown {
+            Some(countdown_start) => {
+                countdown_start.elapsed().num_milliseconds() >= self.auto_start_delay.into()
+            },
+            None => {
+                self.auto_start_countdown = Some(Time::now());
+
+                false
+            },
+        }
+    }
+
+    /// The number of whole seconds left on the auto start countdown
+    pub fn auto_start_remaining(&self) -> Option<i64> {
+        let countdown_start = self.auto_start_countdown.as_ref()?;
+
+        let remaining_millis = i64::from(self.auto_start_delay) - countdown_start.elapsed().num_milliseconds();
+
+        Some((remaining_millis.max(0) + 999) / 1000)
+    }
+
+    /// The number of whole seconds left in the intermission
+    pub fn intermission_remaining(&self) -> i64 {
+        let remaining_millis = i64::from(self.intermission_duration) - self.last_wave_end.elapsed().num_milliseconds();
+
+        (remaining_millis.max(0) + 999) / 1000
+    }
+
+    /// Ask the host to toggle whether our player is ready for the next wave
+    pub fn toggle_our_ready(&self, level: &mut Level, ctx: &TickContext) {
+
+        // you can only ready up between waves
+        if self.wave_active || !ctx.bindings.released(Action::Ready) {
+            return;
+        }
+
+        let next_wave = self.wave + 1;
+
+        for (_, player) in level.players.iter_mut() {
+            if player.owner == *ctx.uuid {
+                player.ready_for_wave = match player.ready_for_wave == next_wave {
+                    true => 0,
+                    false => next_wave,
+                };
+
+                break;
+            }
+        }
+    }
+}
+
+impl GameMode for WaveSurvivalData {
+
+    fn name(&self) -> &'static str {
+        "Wave Survival"
+    }
+
+    fn min_players(&self) -> usize {
+        1
+    }
+
+    // waves end when everyone dies, not on a timer
+    fn timed_rounds(&self) -> bool {
+        false
+    }
+
+    fn respawns_players(&self) -> bool {
+        false
+    }
+
+    fn respawn_hint(&self) -> &'static str {
+        "Respawning after this wave"
+    }
+
+    // dead players dont come back until the wave ends so let the living recover
+    fn health_regen(&self) -> bool {
+        true
+    }
+
+    // no shops needed between waves
+    fn shop_open_everywhere(&self) -> bool {
+        !self.wave_active
+    }
+
+    fn reset(&mut self) {
+        *self = WaveSurvivalData::new();
+    }
+
+    // everyone died
+    fn round_over(&self, players: &SyncArena<Player>) -> bool {
+        self.wave_active && players.len() > 0 && players.iter().all(|(_, player)| player.health == 0)
+    }
+
+    fn round_headline(&self, _players: &SyncArena<Player>) -> String {
+        format!("Survived {} waves", self.wave.saturating_sub(1))
+    }
+
+    fn tick(&mut self, mode_ctx: &mut ModeContext, ctx: &mut TickContext) {
+
+        self.toggle_our_ready(mode_ctx.level, ctx);
+
+        if !*ctx.is_host {
+            return;
+        }
+
+        // start next wave logic
+        if self.wave_active == false {
+
+            let ready = self.update_ready(&mode_ctx.level.players);
+
+            // everyone readying up skips whatever is left of the intermission
+            if self.in_intermission() && !self.everyone_ready(&mode_ctx.level.players) {
+                return;
+            }
+
+            if !ready {
+                return;
+            }
+
+            // clear out the bodies from the last wave
+            mode_ctx.level.despawn_dead_enemies();
+
+            self.start_next_wave();
+        }
+
+        if self.enemy_reserve > 0 && self.last_batch_spawn.elapsed().num_milliseconds() >= self.batch_spawn_rate.into() {
+
+            // dont spawn more than we have left
+            let batch_size = self.batch_size.min(self.enemy_reserve);
+
+            for spawn_location in mode_ctx.level.enemy_spawn_positions(batch_size) {
+
+                let mut enemy = Enemy::new(spawn_location, ctx.uuid.clone(), &mut mode_ctx.level.space, ctx.textures);
+
+                enemy.health = self.enemy_health;
+
+                mode_ctx.level.enemies.insert(enemy);
+            }
+
+            self.enemy_reserve -= batch_size;
+
+            self.last_batch_spawn = Time::now();
+        }
+
+        // the wave is over once everything has spawned and been killed
+        if self.enemy_reserve == 0 {
+
+            let living_enemies = mode_ctx.level.enemies.iter().filter(|(_, enemy)| enemy.health > 0).count();
+
+            if living_enemies == 0 {
+                self.end_wave();
+
+                mode_ctx.chat.add_message("Server".to_string(), format!("Wave {} complete!", self.wave));
+
+                award_everyone_money(mode_ctx.economy, &mode_ctx.level.players, WAVE_REWARD);
+
+                // anyone who died during the wave comes back for the next one
+                let dead_players: Vec<Index> = mode_ctx.level.players.iter()
+                    .filter(|(_, player)| player.death.is_some())
+                    .map(|(player_index, _)| player_index)
+                    .collect();
+
+                mode_ctx.level.respawn_players(&dead_players, ctx.textures);
+            }
+        }
+    }
+
+    fn draw_hud(&self, level: &Level, ctx: &TickContext) {
+
+        let status = if self.wave_active {
+            let living_enemies = level.enemies.iter().filter(|(_, enemy)| enemy.health > 0).count() as u32;
+
+            format!("Wave {} - {} enemies left", self.wave, living_enemies + self.enemy_reserve)
+        } 
+        else if self.in_intermission() {
+            format!("Next wave in {}", self.intermission_remaining())
+        }
+        else if let Some(auto_start_remaining) = self.auto_start_remaining() {
+            format!("Wave {} starting in {}", self.wave + 1, auto_start_remaining)
+        }
+        else {
+            format!("Waiting to start wave {}", self.wave + 1)
+        };
+
+        draw_text(&status, (screen_width() / 2.) - 150., 40., 40., WHITE);
+
+        if self.wave_active {
+            return;
+        }
+
+        draw_text(&format!("Press {} to ready up", ctx.bindings.key_name(Action::Ready)), (screen_width() / 2.) - 150., 75., 30., WHITE);
+
+        // list who is ready
+        for (index, (_, player)) in level.players.iter().enumerate() {
+
+            let (status, color) = match self.ready.contains(&player.owner) {
+                true => ("READY", GREEN),
+                false => ("NOT READY", RED),
+            };
+
+            let label = match player.owner == *ctx.uuid {
+                true => "You".to_string(),
+                false => player.name(),
+            };
+
+            draw_text(&format!("{}: {}", label, status), screen_width() - 250., 60. + (index as f32 * 25.), 25., color);
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Diff, Clone, PartialEq)]
 #[diff(attr(
     #[derive(Serialize, Deserialize)]
 ))]
-pub struct  WaveSurvivalData {
+pub struct DeathmatchData {
     
-    pub wave: u32,
-    pub last_wave_end: Time,
-    pub ready: HashSet<Index>,
-    pub wave_active: bool,
-    pub enemy_reserve: u32, // the total number of remaining enemies that will spawn this wave
-    pub batch_spawn_rate: u32, // the number of ms to wait between wave batches
-    pub batch_size: u32, // the number of enemies that will spawn in each batch,
-    pub last_batch_spawn: Time, 
 }
 
-impl WaveSurvivalData {
-
-    /// Create new wave survival data starting at wave 1
+impl DeathmatchData {
     pub fn new() -> Self {
-        Self {
-            wave: 1,
-            last_wave_end: Time::new(0),
-            ready: HashSet::new(),
-            wave_active: false,
-            enemy_reserve: 10,
-            batch_spawn_rate: 5000,
-            batch_size: 1,
-            last_batch_spawn: Time::new(0),
-        }
+        Self {}
     }
 }
 
-pub struct DeathmatchData {
-    
+impl GameMode for DeathmatchData {
+
+    fn name(&self) -> &'static str {
+        "Deathmatch"
+    }
+
+    fn reset(&mut self) {}
 }
 
 #[derive(Serialize, Deserialize, Diff, Clone, PartialEq)]
@@ -61,10 +855,17 @@ pub struct DeathmatchData {
 ))]
 pub struct GameState {
     pub level: Level,
-    pub game_started: bool,
-    pub chat: Chat,
+    pub chat: Dirty<Chat>,
     pub living_players: u32,
-    pub mode: Mode
+    pub mode: Mode,
+    #[serde(default)]
+    pub match_state: MatchState,
+    #[serde(default)]
+    pub rotation: Dirty<MatchRotation>,
+    #[serde(default)]
+    pub connected_players: Vec<String>, // owners the mode has been told about through on_player_join
+    #[serde(default)]
+    pub economy: Economy
 }
 
 impl GameState {
@@ -72,11 +873,30 @@ impl GameState {
     pub fn empty() -> Self {
         Self {
             level: Level::empty(),
-            game_started: false,
-            chat: Chat::new(),
+            chat: Dirty::new(Chat::new()),
             living_players: 0,
-            mode: Mode::Deathmatch,
+            mode: Mode::Deathmatch(DeathmatchData::new()),
+            match_state: MatchState::new(),
+            rotation: Dirty::new(MatchRotation::default()),
+            connected_players: Vec::new(),
+            economy: Economy::new()
+        }
+    }
+
+    /// Start playing through a rotation from its first entry
+    pub fn start_rotation(&mut self, mut rotation: MatchRotation) {
+
+        if let Some(level) = rotation.current_level() {
+            self.level = level;
         }
+
+        self.mode = rotation.current().unwrap().mode.clone();
+
+        // the new mode needs to hear about everyone that is already here
+        self.connected_players.clear();
+        self.match_state = MatchState::new();
+
+        *self.rotation = rotation;
     }
 
     pub async fn sync_sounds(&mut self, ctx: &mut TickContext<'_>) {
@@ -84,111 +904,185 @@ impl GameState {
     }
 
     pub fn spawn_brick(&mut self, ctx: &mut TickContext) {
-        if is_key_released(macroquad::input::KeyCode::E) {
+        if ctx.bindings.released(Action::Build) {
 
             let pos = rapier_mouse_world_pos(ctx.camera_rect);
 
+            // building costs materials outside of sandbox so the host has to sign off on it
+            if self.mode.sandbox().is_none() {
+
+                if let Some((_, player)) = self.level.players.iter_mut().find(|(_, player)| player.owner == *ctx.uuid) {
+                    Economy::request(player, EconomyRequestKind::Build(pos));
+                }
+
+                return;
+            }
+
             let new_structure = Structure::new(pos, &mut self.level.space, ctx.uuid.clone());
             
             self.level.structures.insert(new_structure);
         }
     }   
 
-    pub fn deathmatch_tick(state: &mut GameState, ctx: &mut TickContext) {
-        
+    pub fn server_tick(&mut self) {
+        self.level.server_tick();
+    }
+
+    pub fn match_tick(&mut self, ctx: &mut TickContext) {
+
         if !*ctx.is_host {
             return;
         }
 
-        if let Mode::Deathmatch = state.mode {
-            // start the game if more than one player is connected
-            if state.level.players.len() > 1 {
-                state.game_started = true;
+        // modes like sandbox are always live
+        if !self.mode.has_rounds() {
+            if self.match_state.phase != MatchPhase::Live {
+                self.match_state.set_phase(MatchPhase::Live);
             }
 
-            if state.level.players.len() < 2 {
-                state.game_started = false
-            }
+            return;
+        }
 
-            if !state.game_started {
-                return;
-            }
+        let enough_players = self.level.players.len() >= self.mode.min_players();
 
-            let mut living_player_count: u32 = 0;
+        match self.match_state.phase {
+            MatchPhase::Warmup => {
 
-            for (_, player) in &state.level.players {
-                if player.health > 0 {
-                    living_player_count += 1;
+                // respawn people quickly so they can mess around
+                self.level.respawn_dead_players(self.match_state.warmup_respawn_delay, ctx.textures);
 
-                }
-            }
+                // the countdown doesnt start until enough people have joined
+                if !enough_players {
+                    self.match_state.phase_start = Time::now();
 
-            if living_player_count < 2 {
+                    return;
+                }
 
-                let mut players = SyncArenaIterator::new(&mut state.level.players);
+                if self.match_state.phase_over(&self.mode) {
+                    self.start_round(ctx);
+                }
+            },
+            MatchPhase::Live => {
 
-                while let Some((player, arena)) = players.next() {
+                if !enough_players {
+                    self.chat.add_message("Server".to_string(), "Not enough players, back to warmup".to_string());
 
-                    let owner = player.owner.clone();
-                    let position = {
-                        state.level.space.sync_rigid_body_set.get_sync(*player.rigid_body_handle()).unwrap().position().translation.clone()
-                    };
+                    self.match_state.set_phase(MatchPhase::Warmup);
 
-                    // respawn the player with the same position and owner
-                    Player::spawn(arena, &mut state.level.space, owner, &Vec2::new(position.x, position.y), ctx.textures);
+                    return;
+                }
 
-                    player.despawn(&mut state.level.space);
+                if self.match_state.phase_over(&self.mode) || self.mode.round_over(&self.level.players) {
+                    self.end_round();
                 }
-            }
+            },
+            MatchPhase::RoundEnd => {
+                if self.match_state.phase_over(&self.mode) {
+                    self.match_state.set_phase(MatchPhase::Intermission);
+                }
+            },
+            MatchPhase::Intermission => {
+                if self.match_state.phase_over(&self.mode) {
+                    self.load_next_level(ctx);
+                }
+            },
         }
-        else {
-            return;
+    }
+
+    /// Reset everything from warmup and go live
+    pub fn start_round(&mut self, ctx: &mut TickContext) {
+
+        self.mode.reset();
+
+        self.level.despawn_enemies();
+        self.level.return_all_flags(ctx.uuid);
+
+        for (_, player) in self.level.players.iter_mut() {
+            player.kills = 0;
+            player.deaths = 0;
+            player.assists = 0;
         }
 
+        self.level.respawn_all_players(ctx.textures);
+
+        self.match_state.results = None;
+        self.match_state.set_phase(MatchPhase::Live);
+
+        self.chat.add_message("Server".to_string(), format!("{} is live!", self.mode.name()));
     }
 
+    pub fn end_round(&mut self) {
 
-    pub fn wave_survival_tick(&mut self, ctx: &mut TickContext) {
+        let headline = self.mode.round_headline(&self.level.players);
 
-        if !*ctx.is_host {
-            return;
-        }
-        if let Mode::WaveSurvival(data) = &mut self.mode {
+        self.match_state.results = Some(RoundResults::new(headline, &self.level.players));
 
-            // start next wave logic
-            if data.wave_active == false {
-                let mut ready = true;
+        self.match_state.set_phase(MatchPhase::RoundEnd);
+    }
 
-                for (player_index, _) in &self.level.players {
-                    if !data.ready.contains(&player_index) {
-                        ready = false
-                    }
-                }
+    /// Load the next level in the rotation. Without a rotation we just stay on the same level
+    pub fn load_next_level(&mut self, ctx: &mut TickContext) {
 
-                if ready {
-                    data.wave += 1;
+        let entry = match self.rotation.entries.is_empty() {
+            true => None,
+            false => self.rotation.advance().cloned(),
+        };
 
-                    data.wave_active = true;
-                }
+        if let Some(entry) = entry {
+
+            // planks and time stops come with us. money and materials are kept in the economy
+            let owners: Vec<(String, u32, u32)> = self.level.players.iter()
+                .map(|(_, player)| (player.owner.clone(), player.planks, player.time_stop_charges))
+                .collect();
+
+            // a level that fails to load keeps us on the current one instead of taking the host down mid match
+            if let Some(level) = self.rotation.current_level() {
+                self.level = level;
             }
 
-            if data.wave_active == false {
-                return;
+            self.mode = entry.mode;
+
+            // the new mode needs to hear about everyone that is already here
+            self.connected_players.clear();
+
+            // the host owns everything in a freshly loaded level
+            for (_, structure) in self.level.structures.iter_mut() {
+                structure.owner = Some(ctx.uuid.clone());
+            }
+
+            for brick in self.level.bricks.iter_mut() {
+                brick.owner = Some(ctx.uuid.clone());
+            }
+
+            for boat in self.level.boats.iter_mut() {
+                boat.owner = ctx.uuid.clone();
             }
 
-            for i in 0..data.batch_size {
+            for shotgun in self.level.shotguns.iter_mut() {
+                shotgun.set_owner(ctx.uuid.clone());
+            }
+
+            // bring everyone over to the new level
+            let mut taken = Vec::new();
+
+            for (owner, planks, time_stop_charges) in owners {
+                let position = self.level.player_spawn_position(None, &taken);
+
+                taken.push(position);
 
-                let spawn_location = Vec2::new(-500. + (i as f32 * 60.) , 0.);
+                let player_index = Player::spawn(&mut self.level.players, &mut self.level.space, owner, &position, ctx.textures);
 
-                self.level.enemies.insert(
-                    Enemy::new(spawn_location, ctx.uuid.clone(), &mut self.level.space, ctx.textures)
-                );
+                let player = self.level.players.get_mut(&player_index).unwrap();
+
+                player.planks = planks;
+                player.time_stop_charges = time_stop_charges;
             }
+
+            self.chat.add_message("Server".to_string(), format!("Now playing {}", self.mode.name()));
         }
-    }
 
-    pub fn server_tick(&mut self) {
-        self.level.server_tick();
+        self.match_state.results = None;
+        self.match_state.set_phase(MatchPhase::Warmup);
     }
 
     pub fn tick(
@@ -196,25 +1090,308 @@ impl GameState {
         ctx: &mut TickContext
     ) { 
 
-        self.level.tick(ctx);
+        let combat_events = self.level.tick(ctx, self.mode.friendly_fire(), self.mode.health_regen(), self.mode.sandbox());
 
         self.spawn_brick(ctx);
 
-        
+        self.match_tick(ctx);
+
+        self.update_connected_players(ctx);
+
+        self.shop_tick(ctx);
+
+        // nothing counts outside of a live round
+        if self.match_state.phase == MatchPhase::Live {
+
+            if *ctx.is_host && self.mode.respawns_players() {
+                self.level.respawn_dead_players(self.match_state.respawn_delay, ctx.textures);
+            }
+
+            let mut mode_ctx = ModeContext {
+                level: &mut self.level,
+                chat: &mut self.chat,
+                economy: &mut self.economy
+            };
+
+            // only the host applies damage so this is empty everywhere else
+            for event in combat_events {
+                match event {
+                    CombatEvent::Damage { attacker, victim, amount } => self.mode.on_damage(attacker.as_ref(), &victim, amount, &mut mode_ctx, ctx),
+                    CombatEvent::Kill { killer, victim, .. } => self.mode.on_kill(killer.as_ref(), &victim, &mut mode_ctx, ctx),
+                }
+            }
+
+            self.mode.tick(&mut mode_ctx, ctx);
+        }
 
         if is_key_released(macroquad::input::KeyCode::Backspace) {
-            self.chat.add_message("Gamer".to_string(), "Test message".to_string());
+
+            let author = match self.level.players.iter().find(|(_, player)| player.owner == *ctx.uuid) {
+                Some((_, player)) => player.name(),
+                None => ctx.profile.display_name.clone(),
+            };
+
+            self.chat.add_message(author, "Test message".to_string());
+        }
+
+    }
+
+    /// Whether a player can buy things right now
+    pub fn can_shop(&self, player: &Player) -> bool {
+        self.match_state.phase == MatchPhase::Intermission || self.mode.shop_open_everywhere() || near_shop(&self.level.shops, &self.level.space, player)
+    }
+
+    /// Send our purchases to the host, let the host charge for them and pick up the ones that went through
+    pub fn shop_tick(&mut self, ctx: &mut TickContext) {
+
+        let available = match self.level.players.iter().find(|(_, player)| player.owner == *ctx.uuid) {
+            Some((_, player)) => self.can_shop(player),
+            None => false,
+        };
+
+        if let Some(item) = ctx.shop_menu.tick(available, ctx.bindings) {
+            if let Some((_, player)) = self.level.players.iter_mut().find(|(_, player)| player.owner == *ctx.uuid) {
+                Economy::request(player, EconomyRequestKind::Buy(item));
+            }
+        }
+
+        if *ctx.is_host {
+            self.economy.pay_for_stats(&self.level.players);
+
+            let shop_open_everywhere = self.match_state.phase == MatchPhase::Intermission || self.mode.shop_open_everywhere();
+
+            let shops = &self.level.shops;
+            let space = &self.level.space;
+
+            self.economy.process_requests(&mut self.level.players, |player| shop_open_everywhere || near_shop(shops, space, player));
+        }
+
+        self.economy.collect_purchases(&mut self.level, ctx.textures, ctx.uuid, ctx.shop_menu);
+    }
+
+    /// Tell the mode about anyone who joined or left since last tick
+    pub fn update_connected_players(&mut self, ctx: &mut TickContext) {
+
+        if !*ctx.is_host {
+            return;
+        }
+
+        let mut owners: Vec<String> = Vec::new();
+
+        for (_, player) in &self.level.players {
+            if !owners.contains(&player.owner) {
+                owners.push(player.owner.clone());
+            }
+        }
+
+        let joined: Vec<String> = owners.iter().filter(|owner| !self.connected_players.contains(owner)).cloned().collect();
+        let left: Vec<String> = self.connected_players.iter().filter(|owner| !owners.contains(owner)).cloned().collect();
+
+        if joined.is_empty() && left.is_empty() {
+            return;
+        }
+
+        self.connected_players = owners;
+
+        let mut mode_ctx = ModeContext {
+            level: &mut self.level,
+            chat: &mut self.chat,
+            economy: &mut self.economy
+        };
+
+        for owner in joined {
+            self.mode.on_player_join(&owner, &mut mode_ctx, ctx);
         }
 
+        for owner in left {
+            mode_ctx.level.despawn_boats(&owner);
+            mode_ctx.level.despawn_ragdolls(&owner);
+
+            self.mode.on_player_leave(&owner, &mut mode_ctx, ctx);
+        }
     }
 
-    pub async fn draw(&self, textures: &mut TextureLoader, camera_rect: &Rect, fonts: &mut FontLoader, camera: &Camera2D) {
+    pub async fn draw(&self, textures: &mut TextureLoader, camera_rect: &Rect, fonts: &mut FontLoader, camera: &Camera2D, uuid: &String, plank_placer: &PlankPlacer, splashes: &Splashes) {
 
         self.level.draw(textures, camera_rect, fonts, camera).await;
+
+        splashes.draw(self.level.space.gravity.y);
+
+        self.mode.draw(&self.level, uuid);
+
+        plank_placer.draw();
     }
 
     pub async fn draw_hud(&self, ctx: &mut TickContext<'_>) {
         self.level.draw_hud(ctx).await;
+
+        self.draw_match_hud();
+
+        // drawn before the results since intermission is a good time to shop
+        if let Some((_, player)) = self.level.players.iter().find(|(_, player)| player.owner == *ctx.uuid) {
+            let wallet = self.economy.wallet(ctx.uuid);
+
+            ctx.shop_menu.draw(self.can_shop(player), wallet.money, wallet.materials, ctx.bindings);
+        }
+
+        if let Some(results) = &self.match_state.results {
+            self.draw_round_results(results, ctx);
+
+            return;
+        }
+
+        self.mode.draw_hud(&self.level, ctx);
+
+        self.level.kill_feed.draw();
+
+        self.draw_death_hud(ctx);
+
+        if ctx.bindings.down(Action::Scoreboard) {
+            self.draw_scoreboard(ctx);
+        }
+    }
+
+    /// Who killed us, who we are watching and when we come back
+    pub fn draw_death_hud(&self, ctx: &TickContext) {
+
+        let our_player = self.level.players.iter().find(|(_, player)| player.owner == *ctx.uuid);
+
+        let death = match our_player {
+            Some((_, Player { death: Some(death), .. })) => death,
+            _ => return,
+        };
+
+        let name_of = |owner: &String| {
+            match self.level.players.iter().find(|(_, player)| player.owner == *owner) {
+                Some((_, player)) => player.name(),
+                None => "Nobody".to_string(),
+            }
+        };
+
+        let killed_by = match &death.killer {
+            Some(killer) if killer != ctx.uuid => format!("Killed by {}", name_of(killer)),
+            _ => "You died".to_string(),
+        };
+
+        let respawn_text = match (self.match_state.phase, &self.mode) {
+            (MatchPhase::Warmup, _) => format!("Respawning in {}", death.respawn_seconds(self.match_state.warmup_respawn_delay)),
+            (MatchPhase::Live, mode) if mode.respawns_players() => format!("Respawning in {}", death.respawn_seconds(self.match_state.respawn_delay)),
+            (MatchPhase::Live, mode) => mode.respawn_hint().to_string(),
+            _ => String::new(),
+        };
+
+        let x = (screen_width() / 2.) - 150.;
+
+        draw_text(&killed_by, x, 150., 40., RED);
+        draw_text(&respawn_text, x, 185., 30., WHITE);
+
+        if let Some(spectating) = &death.spectating {
+            draw_text(&format!("Spectating {} (click to switch)", name_of(spectating)), x, 215., 25., LIGHTGRAY);
+        }
+    }
+
+    pub fn draw_match_hud(&self) {
+
+        let remaining_seconds = self.match_state.remaining_seconds(&self.mode);
+
+        let text = match self.match_state.phase {
+            MatchPhase::Warmup => {
+                let min_players = self.mode.min_players();
+
+                match self.level.players.len() < min_players {
+                    true => format!("Warmup - waiting for players ({}/{})", self.level.players.len(), min_players),
+                    false => format!("Warmup - starting in {}", remaining_seconds.unwrap_or(0)),
+                }
+            },
+            MatchPhase::Live => {
+                match remaining_seconds {
+                    Some(remaining_seconds) => format!("{}:{:02}", remaining_seconds / 60, remaining_seconds % 60),
+                    None => return,
+                }
+            },
+            MatchPhase::RoundEnd => format!("{} - {}", self.match_state.phase.name(), remaining_seconds.unwrap_or(0)),
+            MatchPhase::Intermission => {
+                let next = match self.rotation.upcoming() {
+                    Some(entry) => format!("{} on {}", entry.mode.name(), entry.level),
+                    None => self.mode.name().to_string(),
+                };
+
+                format!("Next: {} in {}", next, remaining_seconds.unwrap_or(0))
+            },
+        };
+
+        draw_text(&text, 20., 30., 30., WHITE);
+    }
+
+    pub fn draw_round_results(&self, results: &RoundResults, ctx: &TickContext) {
+
+        let width = 600.;
+        let height = 140. + (results.players.len() as f32 * 30.);
+
+        let x = (screen_width() / 2.) - (width / 2.);
+        let y = 80.;
+
+        draw_rectangle(x, y, width, height, Color::from_rgba(0, 0, 0, 200));
+
+        draw_text(&results.headline, x + 20., y + 50., 50., YELLOW);
+
+        draw_text("Name", x + 20., y + 100., 30., WHITE);
+        draw_text("K", x + 420., y + 100., 30., WHITE);
+        draw_text("D", x + 480., y + 100., 30., WHITE);
+        draw_text("A", x + 540., y + 100., 30., WHITE);
+
+        for (index, player) in results.players.iter().enumerate() {
+
+            let color = match (player.team, player.owner == *ctx.uuid) {
+                (Some(team), _) => team.hud_color(),
+                (None, true) => YELLOW,
+                (None, false) => WHITE,
+            };
+
+            let row_y = y + 135. + (index as f32 * 30.);
+
+            draw_text(&player.name, x + 20., row_y, 25., color);
+            draw_text(&player.kills.to_string(), x + 420., row_y, 25., color);
+            draw_text(&player.deaths.to_string(), x + 480., row_y, 25., color);
+            draw_text(&player.assists.to_string(), x + 540., row_y, 25., color);
+        }
+    }
+
+    pub fn draw_scoreboard(&self, ctx: &TickContext) {
+
+        let mut players: Vec<&Player> = self.level.players.iter().map(|(_, player)| player).collect();
+
+        // most kills at the top, fewest deaths breaks ties
+        players.sort_by(|a, b| b.kills.cmp(&a.kills).then(a.deaths.cmp(&b.deaths)));
+
+        let width = 500.;
+        let height = 80. + (players.len() as f32 * 30.);
+
+        let x = (screen_width() / 2.) - (width / 2.);
+        let y = 100.;
+
+        draw_rectangle(x, y, width, height, Color::from_rgba(0, 0, 0, 180));
+
+        draw_text("Name", x + 20., y + 40., 30., WHITE);
+        draw_text("K", x + 320., y + 40., 30., WHITE);
+        draw_text("D", x + 380., y + 40., 30., WHITE);
+        draw_text("A", x + 440., y + 40., 30., WHITE);
+
+        for (index, player) in players.iter().enumerate() {
+
+            let color = match (player.team, player.owner == *ctx.uuid) {
+                (Some(team), _) => team.hud_color(),
+                (None, true) => YELLOW,
+                (None, false) => WHITE,
+            };
+
+            let row_y = y + 75. + (index as f32 * 30.);
+
+            draw_text(&player.name(), x + 20., row_y, 25., color);
+            draw_text(&player.kills.to_string(), x + 320., row_y, 25., color);
+            draw_text(&player.deaths.to_string(), x + 380., row_y, 25., color);
+            draw_text(&player.assists.to_string(), x + 440., row_y, 25., color);
+        }
     }
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,200 @@
+use gamelibrary::log;
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use macroquad::{input::mouse_delta_position, math::{vec2, Vec2}};
+
+use crate::bindings::Action;
+
+// stick input smaller than this is treated as zero so worn sticks dont drift
+pub const STICK_DEADZONE: f32 = 0.2;
+
+// how far from the weapon the aim point goes when aiming with the stick
+const STICK_AIM_DISTANCE: f32 = 400.;
+
+/// What the active gamepad is doing this tick. Copied into the bindings so actions can be read from the keyboard and the gamepad the same way
+#[derive(Clone, Default)]
+pub struct GamepadState {
+    pub connected: bool,
+    pub left_stick: Vec2, // deadzone already applied
+    pub right_stick: Vec2,
+    pub aim: Option<Vec2>, // the last direction the right stick pointed, until the mouse moves
+    pub down: Vec<Button>,
+    pub pressed: Vec<Button>,
+    pub released: Vec<Button>
+}
+
+impl GamepadState {
+
+    /// The button that does an action on a gamepad. These arent rebindable
+    pub fn button(action: Action) -> Option<Button> {
+        match action {
+            Action::Jump => Some(Button::South),
+            Action::Sprint => Some(Button::LeftThumb),
+            Action::Crouch => Some(Button::RightThumb),
+            Action::Fire => Some(Button::RightTrigger2),
+            Action::Reload => Some(Button::West),
+            Action::SwitchWeapon => Some(Button::North),
+            Action::PickUp => Some(Button::RightTrigger),
+            Action::DropWeapon => Some(Button::LeftTrigger),
+            Action::Build => Some(Button::East),
+            Action::TimeStop => Some(Button::LeftTrigger2),
+            Action::MoveLeft => Some(Button::DPadLeft),
+            Action::MoveRight => Some(Button::DPadRight),
+            Action::Shop => Some(Button::DPadUp),
+            Action::UnlockRotations => Some(Button::DPadDown),
+            Action::Scoreboard => Some(Button::Select),
+            Action::Ready => Some(Button::Start),
+            _ => None,
+        }
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+
+        // the stick counts as holding the direction
+        let stick = match action {
+            Action::MoveLeft => self.left_stick.x < 0.,
+            Action::MoveRight => self.left_stick.x > 0.,
+            // mostly down, so aiming the walk a little downwards doesnt crouch
+            Action::Crouch => self.left_stick.y < -0.5 && self.left_stick.y.abs() > self.left_stick.x.abs(),
+            _ => false,
+        };
+
+        stick || Self::button(action).is_some_and(|button| self.down.contains(&button))
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        Self::button(action).is_some_and(|button| self.pressed.contains(&button))
+    }
+
+    pub fn released(&self, action: Action) -> bool {
+        Self::button(action).is_some_and(|button| self.released.contains(&button))
+    }
+
+    /// Where to point the weapon when aiming with the stick
+    pub fn aim_point(&self, weapon_pos: Vec2) -> Option<Vec2> {
+        Some(weapon_pos + (self.aim? * STICK_AIM_DISTANCE))
+    }
+}
+
+/// Owns the gilrs context and keeps track of which gamepad we listen to
+pub struct GamepadInput {
+    gilrs: Option<Gilrs>, // none if the platform has no gamepad support
+    pub active: Option<GamepadId>,
+    pub state: GamepadState
+}
+
+impl GamepadInput {
+    pub fn new() -> Self {
+
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(error) => {
+                log(&format!("gamepads unavailable: {}", error));
+
+                None
+            },
+        };
+
+        // use whatever was plugged in before we started
+        let active = gilrs.as_ref().and_then(|gilrs| gilrs.gamepads().next().map(|(id, _)| id));
+
+        Self {
+            gilrs,
+            active,
+            state: GamepadState::default()
+        }
+    }
+
+    /// Handle plugging and unplugging and read the active gamepad. Call once per tick
+    pub fn poll(&mut self) {
+
+        self.state.pressed.clear();
+        self.state.released.clear();
+
+        let gilrs = match &mut self.gilrs {
+            Some(gilrs) => gilrs,
+            None => return,
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::Connected => {
+                    if self.active.is_none() {
+                        self.active = Some(event.id);
+                    }
+                },
+                EventType::Disconnected => {
+                    if self.active == Some(event.id) {
+                        // fall back to any other gamepad that is still plugged in
+                        self.active = gilrs.gamepads().map(|(id, _)| id).find(|id| *id != event.id);
+                    }
+                },
+                // the last gamepad someone touched becomes the active one
+                EventType::ButtonPressed(button, _) => {
+                    self.active = Some(event.id);
+
+                    self.state.pressed.push(button);
+                },
+                EventType::ButtonReleased(button, _) if self.active == Some(event.id) => {
+                    self.state.released.push(button);
+                },
+                _ => {},
+            }
+        }
+
+        let gamepad = match self.active.and_then(|id| gilrs.connected_gamepad(id)) {
+            Some(gamepad) => gamepad,
+            None => {
+                let aim = self.state.aim;
+
+                self.state = GamepadState { aim, ..Default::default() };
+
+                return;
+            },
+        };
+
+        self.state.connected = true;
+
+        self.state.left_stick = deadzone(vec2(gamepad.value(Axis::LeftStickX), gamepad.value(Axis::LeftStickY)));
+        self.state.right_stick = deadzone(vec2(gamepad.value(Axis::RightStickX), gamepad.value(Axis::RightStickY)));
+
+        self.state.down = [
+            Button::South,
+            Button::East,
+            Button::North,
+            Button::West,
+            Button::LeftTrigger,
+            Button::LeftTrigger2,
+            Button::RightTrigger,
+            Button::RightTrigger2,
+            Button::Select,
+            Button::Start,
+            Button::DPadUp,
+            Button::DPadDown,
+            Button::DPadLeft,
+            Button::DPadRight,
+            Button::LeftThumb,
+            Button::RightThumb
+        ].into_iter().filter(|button| gamepad.is_pressed(*button)).collect();
+
+        if self.state.right_stick != Vec2::ZERO {
+            self.state.aim = Some(self.state.right_stick.normalize());
+        }
+
+        // touching the mouse gives aiming back to it
+        if mouse_delta_position() != Vec2::ZERO {
+            self.state.aim = None;
+        }
+    }
+}
+
+/// Zero out small stick movements and rescale the rest so it still goes from 0 to 1
+pub fn deadzone(stick: Vec2) -> Vec2 {
+
+    let length = stick.length();
+
+    if length < STICK_DEADZONE {
+        return Vec2::ZERO;
+    }
+
+    stick.normalize() * ((length - STICK_DEADZONE) / (1. - STICK_DEADZONE)).min(1.)
+}
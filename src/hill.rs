@@ -0,0 +1,105 @@
+use diff::Diff;
+use gamelibrary::{rapier_to_macroquad, space::{Space, SyncColliderHandle, SyncRigidBodyHandle}, sync_arena::SyncArena};
+use macroquad::{color::Color, math::{vec2, Vec2}, shapes::{draw_rectangle, draw_rectangle_lines}};
+use nalgebra::vector;
+use rapier2d::prelude::{ColliderBuilder, QueryFilter, RigidBodyBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{player::player::Player, round_score::Scorer};
+
+/// A sensor area in the level that players fight over in king of the hill
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct HillZone {
+    pub rigid_body_handle: SyncRigidBodyHandle,
+    pub collider_handle: SyncColliderHandle
+}
+
+impl HillZone {
+    pub fn new(pos: Vec2, half_extents: Vec2, space: &mut Space) -> Self {
+
+        let rigid_body_handle = space.sync_rigid_body_set.insert_sync(
+            RigidBodyBuilder::fixed()
+                .position(vector![pos.x, pos.y].into())
+                .build()
+        );
+
+        // sensors report intersections without pushing anything
+        let collider_handle = space.sync_collider_set.insert_with_parent_sync(
+            ColliderBuilder::cuboid(half_extents.x, half_extents.y)
+                .sensor(true)
+                .build(),
+            rigid_body_handle,
+            &mut space.sync_rigid_body_set
+        );
+
+        Self {
+            rigid_body_handle,
+            collider_handle
+        }
+    }
+
+    /// Everyone alive standing in the zone. Each holder only appears once
+    pub fn occupants(&self, space: &Space, players: &SyncArena<Player>) -> Vec<Scorer> {
+
+        let zone_collider = space.sync_collider_set.get_sync(self.collider_handle).unwrap();
+
+        let mut intersecting_colliders = Vec::new();
+
+        space.query_pipeline.intersections_with_shape(
+            &space.sync_rigid_body_set.rigid_body_set,
+            &space.sync_collider_set.collider_set,
+            zone_collider.position(),
+            zone_collider.shape(),
+            QueryFilter::default(),
+            |handle| {
+                intersecting_colliders.push(handle);
+
+                true
+            }
+        );
+
+        let mut occupants = Vec::new();
+
+        for (_, player) in players {
+
+            if player.health == 0 {
+                continue;
+            }
+
+            let local_body_collider_handle = space.sync_collider_set.get_local_handle(player.body.collider_handle);
+
+            if !intersecting_colliders.contains(&local_body_collider_handle) {
+                continue;
+            }
+
+            let occupant = match player.team {
+                Some(team) => Scorer::Team(team),
+                None => Scorer::Player(player.owner.clone()),
+            };
+
+            if !occupants.contains(&occupant) {
+                occupants.push(occupant);
+            }
+        }
+
+        occupants
+    }
+
+    pub fn draw(&self, space: &Space, color: Color) {
+
+        let pos = space.sync_rigid_body_set.get_sync(self.rigid_body_handle).unwrap().translation();
+
+        let half_extents = space.sync_collider_set.get_sync(self.collider_handle).unwrap().shape().as_cuboid().unwrap().half_extents;
+
+        let pos = rapier_to_macroquad(&vec2(pos.x, pos.y));
+
+        let mut fill_color = color;
+        fill_color.a = 0.15;
+
+        draw_rectangle(pos.x - half_extents.x, pos.y - half_extents.y, half_extents.x * 2., half_extents.y * 2., fill_color);
+        draw_rectangle_lines(pos.x - half_extents.x, pos.y - half_extents.y, half_extents.x * 2., half_extents.y * 2., 4., color);
+    }
+}
@@ -0,0 +1,91 @@
+use diff::Diff;
+use gamelibrary::time::Time;
+use macroquad::{color::{Color, RED, WHITE}, shapes::draw_rectangle, text::{draw_text, measure_text}, window::screen_width};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Diff, Clone, PartialEq, Default)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct KillFeed {
+    pub entries: Vec<KillFeedEntry>
+}
+
+impl KillFeed {
+
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    pub fn add_kill(&mut self, killer: String, victim: String, weapon: String) {
+        self.entries.push(
+            KillFeedEntry {
+                killer,
+                victim,
+                weapon,
+                timestamp: Time::now()
+            }
+        );
+
+        // nobody is going to scroll back through hundreds of kills
+        if self.entries.len() > 20 {
+            self.entries.remove(0);
+        }
+    }
+
+    pub fn draw(&self) {
+
+        let mut index = 0;
+
+        // newest kills are drawn at the top
+        for entry in self.entries.iter().rev() {
+
+            if entry.timestamp.elapsed().num_seconds() > 8 {
+                continue;
+            }
+
+            if index >= 5 {
+                break;
+            }
+
+            let text = format!("{} [{}] {}", entry.killer, entry.weapon, entry.victim);
+
+            let text_width = measure_text(&text, None, 24, 1.).width;
+
+            let mut background_color = Color::from_rgba(0, 0, 0, 120);
+
+            // fade out entries that are about to disappear
+            if entry.timestamp.elapsed().num_seconds() > 6 {
+                background_color.a = 0.2;
+            }
+
+            let x = screen_width() - text_width - 30.;
+            let y = 90. + (index as f32 * 30.);
+
+            draw_rectangle(x - 5., y - 20., text_width + 10., 26., background_color);
+
+            draw_text(&entry.killer, x, y, 24., WHITE);
+
+            let killer_width = measure_text(&format!("{} ", entry.killer), None, 24, 1.).width;
+
+            draw_text(&format!("[{}]", entry.weapon), x + killer_width, y, 24., RED);
+
+            let weapon_width = measure_text(&format!("[{}] ", entry.weapon), None, 24, 1.).width;
+
+            draw_text(&entry.victim, x + killer_width + weapon_width, y, 24., WHITE);
+
+            index += 1;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, Clone, PartialEq, Default)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct KillFeedEntry {
+    pub killer: String,
+    pub victim: String,
+    pub weapon: String,
+    pub timestamp: Time
+}
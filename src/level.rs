@@ -2,20 +2,23 @@ use std::{collections::{HashMap, HashSet}, fs};
 
 use diff::Diff;
 use gamelibrary::{arenaiter::SyncArenaIterator, font_loader::FontLoader, log, macroquad_to_rapier, mouse_world_pos, rapier_mouse_world_pos, space::{Space, SyncColliderHandle, SyncRigidBodyHandle}, swapiter::SwapIter, sync_arena::{Index, SyncArena}, texture_loader::TextureLoader, traits::HasPhysics};
-use macroquad::{camera::Camera2D, color::{RED, WHITE}, input::{self, is_key_down, is_key_pressed, is_key_released, KeyCode}, math::{Rect, Vec2}, prelude::camera::mouse::{self, Camera}, shapes::{draw_rectangle, draw_rectangle_ex, draw_rectangle_lines, DrawRectangleParams}, text::draw_text_ex, texture::{draw_texture_ex, DrawTextureParams}};
+use macroquad::{camera::Camera2D, color::{RED, WHITE}, input::{self, is_key_pressed, is_key_released, is_mouse_button_down, is_mouse_button_pressed}, math::{Rect, Vec2}, prelude::camera::mouse::{self, Camera}, shapes::{draw_rectangle, draw_rectangle_ex, draw_rectangle_lines, DrawRectangleParams}, text::draw_text_ex, texture::{draw_texture_ex, DrawTextureParams}};
 use nalgebra::vector;
 use rapier2d::prelude::{ColliderBuilder, RigidBodyBuilder};
 use serde::{Deserialize, Serialize};
 
-use crate::{blood::Blood, brick::Brick, bullet_trail::BulletTrail, damage_number::DamageNumber, enemy::Enemy, grenade::Grenade, pixel::Pixel, player::{self, body_part::BodyPart, player::{Player, WeaponTickParameters}}, portal::Portal, portal_bullet::PortalBullet, radio::{Radio, RadioBuilder}, shotgun::{self, Shotgun}, sky::Sky, structure::Structure, teleporter::Teleporter, weapon::Weapon, TickContext};
+use crate::{bindings::Action, blood::Blood, boat::Boat, dirty::Dirty, game_mode::CombatEvent, kill_feed::KillFeed, brick::Brick, bullet_trail::BulletTrail, damage_number::DamageNumber, enemy::Enemy, flag::{Flag, FlagBase}, hill::HillZone, grenade::Grenade, pickup::{Pickup, PickupKind}, pixel::Pixel, player::{self, body_part::BodyPart, player::{DeathState, Facing, Hit, Player, PlayerWeapon, WeaponTickParameters}, ragdoll::{Ragdoll, LIMB_SEVER_DAMAGE}}, portal::Portal, portal_bullet::PortalBullet, radio::{Radio, RadioBuilder}, sandbox::{SandboxData, SpawnableEntity}, shop::Shop, shotgun::{self, Shotgun}, sky::Sky, spawn_point::{choose_spawn_position, living_enemy_positions, living_player_positions, SpawnPoint, SpawnPurpose, DEFAULT_PLAYER_SPAWN}, structure::Structure, teleporter::Teleporter, team::{earns_kill_credit, Team}, time_stop::TimeStop, water::WaterVolume, weapon::Weapon, TickContext};
 
 
+// how close a weapon on the ground needs to be to pick it up
+const PICKUP_RANGE: f32 = 80.;
+
 #[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
 #[diff(attr(
     #[derive(Serialize, Deserialize)]
 ))]
 pub struct Level {
-    pub structures: SyncArena<Structure>,
+    pub structures: Dirty<SyncArena<Structure>>, // only touched mutably when a structure is built, deleted or shot so it isnt diffed every sync
     pub players: SyncArena<Player>,
     pub space: Space,
     pub bricks: Vec<Brick>,
@@ -23,7 +26,7 @@ pub struct Level {
     pub shotguns: Vec<Shotgun>,
     pub portal_bullets: Vec<PortalBullet>,
     pub portals: Vec<Portal>,
-    pub sky: Sky,
+    pub sky: Dirty<Sky>,
     pub body_parts: Vec<BodyPart>,
     pub teleporters: Vec<Teleporter>,
     pub hit_markers: Vec<Vec2>,
@@ -35,6 +38,32 @@ pub struct Level {
     pub bullet_trails: SyncArena<BulletTrail>,
     #[serde(default)]
     pub blood: HashSet<Blood>,
+    #[serde(default)]
+    pub kill_feed: Dirty<KillFeed>,
+    #[serde(default)]
+    pub flag_bases: Vec<FlagBase>,
+    #[serde(default)]
+    pub flags: Vec<Flag>,
+    #[serde(default)]
+    pub hill_zones: Vec<HillZone>,
+    #[serde(default)]
+    pub spawn_points: Vec<SpawnPoint>,
+    #[serde(default)]
+    pub shops: Vec<Shop>,
+    #[serde(default)]
+    pub boats: Vec<Boat>,
+    #[serde(default)]
+    pub water: Vec<WaterVolume>,
+    #[serde(default)]
+    pub time_stop: Option<TimeStop>,
+    #[serde(default)]
+    pub dropped_weapons: Vec<PlayerWeapon>, // weapons players threw away. the ones placed in the editor are in shotguns
+    #[serde(default)]
+    pub pickups: Vec<Pickup>,
+    #[serde(default)]
+    pub ragdolls: Vec<Ragdoll>, // bodies of players who have since respawned
+    #[serde(default)]
+    pub applied_hits: HashMap<String, u64> // the last hit id the host applied from each shooter. only the host writes this
 }
 
 impl Level {
@@ -45,14 +74,14 @@ impl Level {
         //let ground_rigid_body = 
         let mut level = Level { 
             bricks: vec![],
-            structures: SyncArena::new(),
+            structures: Dirty::new(SyncArena::new()),
             players: SyncArena::new(),
             space: Space::new(),
             radios: vec![],
             shotguns: vec![],
             portal_bullets: vec![],
             portals: vec![],
-            sky: Sky::new(),
+            sky: Dirty::new(Sky::new()),
             body_parts: vec![],
             teleporters: Vec::new(),
             hit_markers: Vec::new(),
@@ -61,7 +90,20 @@ impl Level {
             pixels: HashSet::new(),
             damage_numbers: HashSet::new(),
             bullet_trails: SyncArena::new(),
-            blood: HashSet::new()
+            blood: HashSet::new(),
+            kill_feed: Dirty::new(KillFeed::new()),
+            flag_bases: Vec::new(),
+            flags: Vec::new(),
+            hill_zones: Vec::new(),
+            spawn_points: Vec::new(),
+            shops: Vec::new(),
+            boats: Vec::new(),
+            water: Vec::new(),
+            time_stop: None,
+            dropped_weapons: Vec::new(),
+            pickups: Vec::new(),
+            ragdolls: Vec::new(),
+            applied_hits: HashMap::new()
         };
     
         level.space.gravity.y = -980.;
@@ -79,7 +121,7 @@ impl Level {
         self.pixels.insert(
             Pixel::new(
                 WHITE, 
-                rapier_mouse_world_pos(ctx.camera_rect), 
+                pos, 
                 &mut self.space, 
                 None, 
                 None, 
@@ -88,75 +130,335 @@ impl Level {
         );
     }
 
-    pub fn from_save(path: String) -> Self {
-        
-        
-        let bytes = fs::read(path).unwrap();
+    pub fn despawn_dead_enemies(&mut self) {
+        let mut enemies_iter = SyncArenaIterator::new(&mut self.enemies);
 
+        while let Some((enemy, _)) = enemies_iter.next() {
+            if enemy.health <= 0 {
+                enemy.despawn(&mut self.space);
+            }
 
-        let level: Self = serde_yaml::from_slice(&bytes).unwrap();
-        
-        level
-    }   
+            else {
+                enemies_iter.restore(enemy);
+            }
+        }
+    }
 
-    pub fn server_tick(&mut self) {
+    pub fn despawn_enemies(&mut self) {
+        let mut enemies_iter = SyncArenaIterator::new(&mut self.enemies);
 
+        while let Some((enemy, _)) = enemies_iter.next() {
+            enemy.despawn(&mut self.space);
+        }
     }
 
-    pub fn tick(
-        &mut self,
-        ctx: &mut TickContext,
-    ) {
+    /// Spawn a flag at every base that doesnt have one yet
+    pub fn spawn_missing_flags(&mut self, host: &String) {
+        for base in &self.flag_bases {
+            if self.flags.iter().any(|flag| flag.team == base.team) {
+                continue;
+            }
 
-        
-        for (_, bullet_trail) in &mut self.bullet_trails {
-            if bullet_trail.owner == *ctx.uuid {
-                bullet_trail.tick(ctx);
+            self.flags.push(
+                Flag::new(base.team, base.position, &mut self.space, host.clone())
+            );
+        }
+    }
+
+    pub fn return_all_flags(&mut self, host: &String) {
+        for flag in &mut self.flags {
+            if let Some(base) = self.flag_bases.iter().find(|base| base.team == flag.team) {
+                flag.return_to_base(&mut self.space, base, host.clone());
             }
-        };
+        }
+    }
 
-        //self.spawn_fixed_structure(ctx.camera_rect, ctx.uuid);
+    /// Handle picking up, dropping, returning and capturing flags. Returns the teams that captured a flag
+    pub fn update_flags(&mut self, flag_return_duration: u32, host: &String) -> Vec<Team> {
 
-        self.spawn_damage_number(ctx.camera_rect);
-        
-        if is_key_released(KeyCode::C) {
+        // you can only capture while your own flag is safe at home
+        let flags_at_base: Vec<Team> = self.flags.iter().filter(|flag| flag.at_base()).map(|flag| flag.team).collect();
 
-            let mouse_pos = rapier_mouse_world_pos(ctx.camera_rect);
+        let mut captures = Vec::new();
 
-            self.enemies.insert(
-                Enemy::new(mouse_pos, ctx.uuid.clone(), &mut self.space, ctx.textures)
-            
-            );
-        }
+        for flag in &mut self.flags {
 
-        if is_key_released(KeyCode::H) {
-            let mut players_iter = SyncArenaIterator::new(&mut self.players);
-    
-            while let Some((mut player, _)) = players_iter.next() {
-                if player.contains_point(&mut self.space, rapier_mouse_world_pos(ctx.camera_rect)) {
-                    player.despawn(&mut self.space);
+            let base = match self.flag_bases.iter().find(|base| base.team == flag.team) {
+                Some(base) => base,
+                None => continue,
+            };
+
+            if let Some(carrier) = &flag.carrier {
+
+                let carrier = self.players.iter().find(|(_, player)| player.owner == *carrier && player.health > 0);
+
+                let carrier = match carrier {
+                    Some((_, carrier)) => carrier,
+                    None => {
+                        // the carrier died or left
+                        flag.drop_from_carrier(&mut self.space, host.clone());
+
+                        continue;
+                    },
+                };
+
+                let carrier_position = self.space.sync_rigid_body_set.get_sync(carrier.body.body_handle).unwrap().translation();
+                let carrier_position = Vec2::new(carrier_position.x, carrier_position.y);
+
+                let capturing_team = flag.team.opponent();
+
+                let at_home_base = self.flag_bases.iter().any(|base| base.team == capturing_team && base.contains(carrier_position));
+
+                if at_home_base && flags_at_base.contains(&capturing_team) {
+                    flag.return_to_base(&mut self.space, base, host.clone());
+
+                    captures.push(capturing_team);
                 }
 
-                else {
-                    players_iter.restore(player);
+                continue;
+            }
+
+            if let Some(dropped_at) = &flag.dropped_at {
+                if dropped_at.elapsed().num_milliseconds() >= flag_return_duration.into() {
+                    flag.return_to_base(&mut self.space, base, host.clone());
+
+                    continue;
+                }
+            }
+
+            let flag_position = flag.position(&self.space);
+
+            for (_, player) in &self.players {
+
+                if player.health == 0 {
+                    continue;
+                }
+
+                let team = match player.team {
+                    Some(team) => team,
+                    None => continue,
+                };
+
+                let player_position = self.space.sync_rigid_body_set.get_sync(player.body.body_handle).unwrap().translation();
+
+                if Vec2::new(player_position.x, player_position.y).distance(flag_position) > 60. {
+                    continue;
+                }
+
+                if team != flag.team {
+                    flag.pick_up(&mut self.space, player.owner.clone(), player.body.body_handle);
+
+                    break;
+                }
+
+                // touching your own dropped flag sends it home
+                if flag.dropped_at.is_some() {
+                    flag.return_to_base(&mut self.space, base, host.clone());
+
+                    break;
                 }
             }
         }
 
-        if is_key_down(KeyCode::J) {
-            self.spawn_pixel(rapier_mouse_world_pos(ctx.camera_rect), ctx);
+        captures
+    }
+
+    /// Find the safest spawn for a player. Team spawns are preferred over general player spawns.
+    /// `taken` is for positions already handed out to other players spawning this tick
+    pub fn find_player_spawn(&self, team: Option<Team>, taken: &[Vec2]) -> Option<Vec2> {
+
+        let team_spawns: Vec<Vec2> = self.spawn_points.iter()
+            .filter(|spawn_point| team.is_some() && spawn_point.purpose == SpawnPurpose::Team(team.unwrap()))
+            .map(|spawn_point| spawn_point.position)
+            .collect();
+
+        let candidates: Vec<Vec2> = match team_spawns.is_empty() {
+            true => {
+                self.spawn_points.iter()
+                    .filter(|spawn_point| spawn_point.purpose == SpawnPurpose::Player)
+                    .map(|spawn_point| spawn_point.position)
+                    .collect()
+            },
+            false => team_spawns,
+        };
 
+        if candidates.is_empty() {
+            return None;
         }
 
-        if is_key_released(KeyCode::G) {
+        let mut threats = living_enemy_positions(&self.space, &self.enemies);
 
-            let mouse_pos = rapier_mouse_world_pos(ctx.camera_rect);
+        for (_, player) in &self.players {
 
-            self.grenades.push(
-                Grenade::new(mouse_pos, &mut self.space)
-            );
+            // teammates arent a threat
+            if player.health == 0 || (team.is_some() && player.team == team) {
+                continue;
+            }
+
+            let position = self.space.sync_rigid_body_set.get_sync(player.body.body_handle).unwrap().translation();
+
+            threats.push(Vec2::new(position.x, position.y));
+        }
+
+        let mut occupied = living_player_positions(&self.space, &self.players);
+
+        occupied.extend(living_enemy_positions(&self.space, &self.enemies));
+        occupied.extend_from_slice(taken);
+
+        choose_spawn_position(&candidates, &threats, &occupied)
+    }
+
+    pub fn player_spawn_position(&self, team: Option<Team>, taken: &[Vec2]) -> Vec2 {
+        self.find_player_spawn(team, taken).unwrap_or(DEFAULT_PLAYER_SPAWN)
+    }
+
+    /// Where to put the next batch of wave enemies, as far from players as possible
+    pub fn enemy_spawn_positions(&self, count: u32) -> Vec<Vec2> {
+
+        let candidates: Vec<Vec2> = self.spawn_points.iter()
+            .filter(|spawn_point| spawn_point.purpose == SpawnPurpose::EnemyWave)
+            .map(|spawn_point| spawn_point.position)
+            .collect();
+
+        // old levels dont have enemy spawns
+        if candidates.is_empty() {
+            return (0..count).map(|i| Vec2::new(-500. + (i as f32 * 60.), 0.)).collect();
+        }
+
+        let threats = living_player_positions(&self.space, &self.players);
+
+        let mut occupied = living_enemy_positions(&self.space, &self.enemies);
+
+        let mut positions = Vec::new();
+
+        for _ in 0..count {
+            let position = choose_spawn_position(&candidates, &threats, &occupied).unwrap();
+
+            occupied.push(position);
+            positions.push(position);
+        }
+
+        positions
+    }
+
+    /// Pick a spawn for each player, making sure no two players get the same spot
+    pub fn choose_respawn_positions(&self, player_indices: &[Index]) -> Vec<(String, Vec2)> {
+
+        let mut taken = Vec::new();
+        let mut respawns = Vec::new();
+
+        for player_index in player_indices {
+
+            let player = match self.players.get(player_index) {
+                Some(player) => player,
+                None => continue,
+            };
+
+            // teams without spawn points can still use their flag base
+            let flag_base = self.flag_bases.iter().find(|base| Some(base.team) == player.team);
+
+            let position = match (self.find_player_spawn(player.team, &taken), flag_base) {
+                (Some(position), _) => position,
+                (None, Some(flag_base)) => flag_base.position,
+                (None, None) => DEFAULT_PLAYER_SPAWN,
+            };
+
+            taken.push(position);
+            respawns.push((player.owner.clone(), position));
+        }
+
+        respawns
+    }
 
+    /// Respawn specific players at spawn points, keeping their stats
+    pub fn respawn_players(&mut self, player_indices: &[Index], textures: &mut TextureLoader) {
 
+        if player_indices.is_empty() {
+            return;
+        }
+
+        let respawns = self.choose_respawn_positions(player_indices);
+
+        let mut players = SyncArenaIterator::new(&mut self.players);
+
+        while let Some((player, arena)) = players.next() {
+
+            let position = match respawns.iter().find(|(owner, _)| *owner == player.owner) {
+                Some((_, position)) => *position,
+                None => {
+                    players.restore(player);
+
+                    continue;
+                },
+            };
+
+            let new_player = Player::spawn(arena, &mut self.space, player.owner.clone(), &position, textures);
+
+            arena.get_mut(&new_player).unwrap().inherit_stats(&player);
+
+            // the dead leave their body behind for a while
+            match player.death.is_some() {
+                true => self.ragdolls.push(player.into_ragdoll(&mut self.space)),
+                false => player.despawn(&mut self.space),
+            }
+        }
+    }
+
+    /// Respawn every player at a spawn point, keeping their stats
+    pub fn respawn_all_players(&mut self, textures: &mut TextureLoader) {
+
+        let player_indices: Vec<Index> = self.players.iter().map(|(player_index, _)| player_index).collect();
+
+        self.respawn_players(&player_indices, textures);
+    }
+
+    /// Respawn everyone who has been dead for at least `respawn_delay` ms
+    pub fn respawn_dead_players(&mut self, respawn_delay: u32, textures: &mut TextureLoader) {
+
+        let ready: Vec<Index> = self.players.iter()
+            .filter(|(_, player)| {
+                match &player.death {
+                    Some(death) => death.can_respawn(respawn_delay),
+                    None => false,
+                }
+            })
+            .map(|(player_index, _)| player_index)
+            .collect();
+
+        self.respawn_players(&ready, textures);
+    }
+
+    pub fn from_save(path: &str) -> Result<Self, String> {
+
+        let bytes = fs::read(path).map_err(|error| format!("failed to read level {}: {}", path, error))?;
+
+        serde_yaml::from_slice(&bytes).map_err(|error| format!("failed to parse level {}: {}", path, error))
+    }
+
+    pub fn server_tick(&mut self) {
+
+    }
+
+    pub fn tick(
+        &mut self,
+        ctx: &mut TickContext,
+        friendly_fire: bool,
+        health_regen: bool,
+        sandbox: Option<&SandboxData>
+    ) -> Vec<CombatEvent> {
+
+        
+        for (_, bullet_trail) in &mut self.bullet_trails {
+            if bullet_trail.owner == *ctx.uuid {
+                bullet_trail.tick(ctx);
+            }
+        };
+
+        //self.spawn_fixed_structure(ctx.camera_rect, ctx.uuid);
+
+        // debug spawning is only allowed in sandbox
+        if sandbox.is_some() {
+            self.sandbox_tick(ctx);
         }
 
         for (_, enemy) in &mut self.enemies {
@@ -168,6 +470,8 @@ impl Level {
             enemy.tick(&mut self.space, ctx, &self.players);
         }
 
+        self.ragdoll_tick(ctx);
+
         for grenade in &mut self.grenades {
             grenade.tick(&mut self.space, ctx);
         }
@@ -182,7 +486,7 @@ impl Level {
             pixel.tick(ctx);
         }
 
-        if is_key_released(KeyCode::Delete) {
+        if ctx.bindings.released(Action::ClearHitMarkers) {
             self.hit_markers = Vec::new();
         }
 
@@ -190,11 +494,22 @@ impl Level {
             teleporter.tick(ctx, &mut self.space, &mut self.players);
         }
 
+        for flag in &mut self.flags {
+            flag.tick(ctx);
+        }
+
+        self.boat_tick(ctx);
+
         let mut weapon_tick_parameters = WeaponTickParameters {
             players: &mut self.players,
             enemies: &mut self.enemies,
-            structures: &mut self.structures
-            
+            structures: &mut self.structures,
+            shooter_team: None,
+            friendly_fire,
+            // weapons lying around dont fire until someone picks them up
+            can_fire: false,
+            infinite_ammo: sandbox.is_some_and(|sandbox| sandbox.infinite_ammo),
+            hits: Vec::new()
         };
 
         for shotgun in &mut self.shotguns {
@@ -203,6 +518,10 @@ impl Level {
 
         }
 
+        for weapon in &mut self.dropped_weapons {
+            weapon.tick(&mut self.space, &mut self.hit_markers, ctx, &mut self.damage_numbers, &mut self.bullet_trails, &mut self.blood, &mut weapon_tick_parameters);
+        }
+
         let mut players_iter = &mut SyncArenaIterator::new(&mut self.players);
 
         while let Some((mut player, players)) = players_iter.next() {
@@ -219,7 +538,10 @@ impl Level {
                 &mut self.enemies,
                 &mut self.damage_numbers,
                 &mut self.bullet_trails,
-                &mut self.blood
+                &mut self.blood,
+                friendly_fire,
+                sandbox,
+                &self.applied_hits
             );
                 
 
@@ -227,7 +549,13 @@ impl Level {
 
         }
 
-        let mut structure_iter = SyncArenaIterator::new(&mut self.structures);
+        self.weapon_pickup_tick(ctx);
+
+        let combat_events = self.apply_hits(ctx, friendly_fire);
+
+        self.health_tick(ctx, health_regen);
+
+        self.record_deaths(ctx);
 
         for brick in &mut self.bricks {
 
@@ -245,17 +573,28 @@ impl Level {
             
         }
 
+        self.time_stop_tick(ctx, sandbox);
+
+        self.water_tick(ctx);
+
         self.space.step(&ctx.owned_rigid_bodies, &ctx.owned_colliders, ctx.owned_impulse_joints, ctx.last_tick_duration);
-        
+
+        combat_events
     }
 
-    pub fn editor_tick(&mut self, camera_rect: &Rect, uuid: &String, textures: &mut TextureLoader) {
+    pub fn editor_tick(&mut self, camera_rect: &Rect, uuid: &String, textures: &mut TextureLoader, resizing_water: &mut Option<usize>) {
 
         self.editor_spawn_structure(camera_rect, uuid);
         self.editor_spawn_brick(camera_rect, uuid);
         self.editor_spawn_radio(camera_rect, uuid);
+        self.editor_spawn_flag_base(camera_rect);
+        self.editor_spawn_hill_zone(camera_rect);
+        self.editor_spawn_spawn_point(camera_rect);
+        self.editor_spawn_shop(camera_rect);
+        self.editor_spawn_water(camera_rect, resizing_water);
+        self.editor_spawn_pickup(camera_rect);
 
-        let mut structures_iter = SyncArenaIterator::new(&mut self.structures);
+        let mut structures_iter = SyncArenaIterator::new(&mut *self.structures);
 
         while let Some((structure, structures)) = structures_iter.next() {
 
@@ -297,6 +636,143 @@ impl Level {
 
     }
 
+    /// Each team has one base so placing a new one moves it
+    pub fn editor_spawn_flag_base(&mut self, camera_rect: &Rect) {
+
+        let team = if is_key_released(input::KeyCode::Key1) {
+            Team::Red
+        } else if is_key_released(input::KeyCode::Key2) {
+            Team::Blue
+        } else {
+            return
+        };
+
+        self.flag_bases.retain(|base| base.team != team);
+
+        self.flag_bases.push(
+            FlagBase::new(team, rapier_mouse_world_pos(camera_rect))
+        );
+    }
+
+    pub fn editor_spawn_hill_zone(&mut self, camera_rect: &Rect) {
+
+        if !is_key_released(input::KeyCode::Key3) {
+            return
+        }
+
+        self.hill_zones.push(
+            HillZone::new(rapier_mouse_world_pos(camera_rect), Vec2::new(150., 100.), &mut self.space)
+        );
+    }
+
+    pub fn editor_spawn_spawn_point(&mut self, camera_rect: &Rect) {
+
+        let mouse_pos = rapier_mouse_world_pos(camera_rect);
+
+        // remove the spawn point under the mouse
+        if is_key_released(input::KeyCode::Key0) {
+            self.spawn_points.retain(|spawn_point| spawn_point.position.distance(mouse_pos) > 20.);
+
+            return
+        }
+
+        let purpose = if is_key_released(input::KeyCode::Key4) {
+            SpawnPurpose::Player
+        } else if is_key_released(input::KeyCode::Key5) {
+            SpawnPurpose::Team(Team::Red)
+        } else if is_key_released(input::KeyCode::Key6) {
+            SpawnPurpose::Team(Team::Blue)
+        } else if is_key_released(input::KeyCode::Key7) {
+            SpawnPurpose::EnemyWave
+        } else {
+            return
+        };
+
+        self.spawn_points.push(
+            SpawnPoint::new(mouse_pos, purpose)
+        );
+    }
+
+    pub fn editor_spawn_shop(&mut self, camera_rect: &Rect) {
+
+        let mouse_pos = rapier_mouse_world_pos(camera_rect);
+
+        // remove the shop under the mouse
+        if is_key_released(input::KeyCode::Key9) {
+            self.shops.retain(|shop| shop.position.distance(mouse_pos) > 30.);
+
+            return
+        }
+
+        if !is_key_released(input::KeyCode::Key8) {
+            return
+        }
+
+        self.shops.push(
+            Shop::new(mouse_pos)
+        );
+    }
+
+    /// `resizing_water` is the index of the water whose corner handle the editor is dragging. It belongs to the editor so it isnt synced
+    pub fn editor_spawn_water(&mut self, camera_rect: &Rect, resizing_water: &mut Option<usize>) {
+
+        let mouse_pos = rapier_mouse_world_pos(camera_rect);
+
+        // remove the water under the mouse
+        if is_key_released(input::KeyCode::K) {
+            self.water.retain(|water| !water.contains_point(mouse_pos));
+
+            *resizing_water = None;
+
+            return
+        }
+
+        // drag the corner handle to resize
+        if is_mouse_button_pressed(input::MouseButton::Left) {
+            *resizing_water = self.water.iter().position(|water| water.handle_contains(mouse_pos));
+        }
+
+        if !is_mouse_button_down(input::MouseButton::Left) {
+            *resizing_water = None;
+        }
+
+        if let Some(water) = resizing_water.and_then(|index| self.water.get_mut(index)) {
+            water.resize(mouse_pos);
+        }
+
+        if !is_key_released(input::KeyCode::L) {
+            return
+        }
+
+        self.water.push(
+            WaterVolume::new(mouse_pos, Vec2::new(400., 150.))
+        );
+    }
+
+    pub fn editor_spawn_pickup(&mut self, camera_rect: &Rect) {
+
+        let mouse_pos = rapier_mouse_world_pos(camera_rect);
+
+        // remove the pickup under the mouse
+        if is_key_released(input::KeyCode::N) {
+            self.pickups.retain(|pickup| !pickup.in_reach(mouse_pos));
+
+            return
+        }
+
+        let kind = if is_key_released(input::KeyCode::H) {
+            PickupKind::Health
+        } else if is_key_released(input::KeyCode::G) {
+            PickupKind::Armor
+        } else {
+            return
+        };
+
+        self.pickups.push(
+            Pickup::new(mouse_pos, kind)
+        );
+    }
+
     pub fn editor_spawn_brick(&mut self, camera_rect: &Rect, uuid: &String) {
 
         if !is_key_released(input::KeyCode::B) {
@@ -310,18 +786,464 @@ impl Level {
         );
     }
 
-    pub fn spawn_damage_number(&mut self, camera_rect: &Rect) {
+    pub fn boat_tick(&mut self, ctx: &mut TickContext) {
+
+        for boat in &mut self.boats {
+            boat.tick(&mut self.space, ctx);
+        }
+
+        let our_player = self.players.iter_mut().find(|(_, player)| player.owner == *ctx.uuid && player.death.is_none());
+
+        // the dead and spectators dont have any planks to place
+        let planks_left = match &our_player {
+            Some((_, player)) => player.planks,
+            None => 0,
+        };
+
+        let placement = ctx.plank_placer.tick(&self.boats, &self.space, rapier_mouse_world_pos(ctx.camera_rect), planks_left, ctx.bindings, ctx.uuid);
+
+        if let (Some(placement), Some((_, player))) = (placement, our_player) {
+            if self.boats[placement.boat].attach_plank(&mut self.space, placement.local_pos, placement.local_rotation, ctx.uuid) {
+                player.planks -= 1;
+            }
+        }
+    }
+
+    /// Sink the boats of someone who left. Host only since nobody else is simulating them anymore
+    pub fn despawn_boats(&mut self, owner: &String) {
+
+        let (theirs, remaining): (Vec<Boat>, Vec<Boat>) = self.boats.drain(..).partition(|boat| boat.owner == *owner);
+
+        self.boats = remaining;
+
+        for boat in theirs {
+            boat.despawn(&mut self.space);
+        }
+    }
+
+    /// Clean up the ragdolls of someone who left. Host only since their owner isnt around to do it anymore
+    pub fn despawn_ragdolls(&mut self, owner: &String) {
+
+        let (theirs, remaining): (Vec<Ragdoll>, Vec<Ragdoll>) = self.ragdolls.drain(..).partition(|ragdoll| ragdoll.owner == *owner);
+
+        self.ragdolls = remaining;
+
+        for ragdoll in theirs {
+            ragdoll.despawn(&mut self.space);
+        }
+    }
+
+    /// Throw our weapon away and pick up weapons lying around. Walking into one takes it if we have a free slot, the pick up key swaps it for the one we are holding
+    pub fn weapon_pickup_tick(&mut self, ctx: &mut TickContext) {
 
-        if !is_key_released(KeyCode::L) {
+        let our_player = match self.players.iter_mut().find(|(_, player)| player.owner == *ctx.uuid && player.death.is_none()) {
+            Some((_, player)) => player,
+            None => return,
+        };
+
+        if ctx.bindings.released(Action::DropWeapon) {
+            if let Some(weapon) = our_player.drop_weapon(&mut self.space) {
+                self.dropped_weapons.push(weapon);
+            }
+        }
+
+        let swap = ctx.bindings.released(Action::PickUp);
+
+        if !swap && !our_player.has_free_slot() {
             return;
         }
-        let mouse_pos = rapier_mouse_world_pos(camera_rect);
 
+        let player_pos = {
+            let body = self.space.sync_rigid_body_set.get_sync(our_player.body.body_handle).unwrap();
+
+            Vec2::new(body.translation().x, body.translation().y)
+        };
+
+        let space = &self.space;
+
+        let in_reach = |rigid_body: SyncRigidBodyHandle| {
+            let position = space.sync_rigid_body_set.get_sync(rigid_body).unwrap().translation();
+
+            Vec2::new(position.x, position.y).distance(player_pos) < PICKUP_RANGE
+        };
+
+        // the ones placed in the level first, then ones people dropped
+        let weapon: Option<PlayerWeapon> = match self.shotguns.iter().position(|shotgun| shotgun.weapon.can_pick_up() && in_reach(shotgun.rigid_body())) {
+            Some(index) => Some(self.shotguns.remove(index).into()),
+            None => self.dropped_weapons.iter()
+                .position(|weapon| weapon.can_pick_up() && in_reach(weapon.rigid_body()))
+                .map(|index| self.dropped_weapons.remove(index)),
+        };
+
+        let weapon = match weapon {
+            Some(weapon) => weapon,
+            None => return,
+        };
+
+        // make room by throwing away what we are holding
+        if !our_player.has_free_slot() {
+            if let Some(dropped) = our_player.drop_weapon(&mut self.space) {
+                self.dropped_weapons.push(dropped);
+            }
+        }
+
+        our_player.pick_up(&mut self.space, weapon);
+    }
+
+    /// Simulate the ragdolls we own and clean up the ones that have been lying around too long
+    pub fn ragdoll_tick(&mut self, ctx: &mut TickContext) {
+
+        for ragdoll in &mut self.ragdolls {
+            ragdoll.tick(&mut self.space, ctx);
+        }
+
+        let (expired, ragdolls): (Vec<Ragdoll>, Vec<Ragdoll>) = std::mem::take(&mut self.ragdolls).into_iter()
+            .partition(|ragdoll| ragdoll.owner == *ctx.uuid && ragdoll.expired());
+
+        self.ragdolls = ragdolls;
+
+        for ragdoll in expired {
+            ragdoll.despawn(&mut self.space);
+        }
+
+        let mut enemies_iter = SyncArenaIterator::new(&mut self.enemies);
+
+        while let Some((enemy, _)) = enemies_iter.next() {
+            if enemy.owner == *ctx.uuid && enemy.ragdoll_expired() {
+                enemy.despawn(&mut self.space);
+            }
+
+            else {
+                enemies_iter.restore(enemy);
+            }
+        }
+    }
+
+    /// Hand out pickups to whoever walks over them and regenerate health if the mode wants it. Only the host does this so a pickup cant go to two players
+    pub fn health_tick(&mut self, ctx: &mut TickContext, health_regen: bool) {
+
+        if !*ctx.is_host {
+            return;
+        }
+
+        for (_, player) in &mut self.players {
+
+            if player.health == 0 {
+                continue;
+            }
+
+            if health_regen {
+                player.regenerate();
+            }
+
+            let player_pos = {
+                let body = self.space.sync_rigid_body_set.get_sync(player.body.body_handle).unwrap();
+
+                Vec2::new(body.translation().x, body.translation().y)
+            };
+
+            for pickup in &mut self.pickups {
+                if pickup.in_reach(player_pos) {
+                    pickup.apply(player);
+                }
+            }
+        }
+    }
+
+    /// Apply the damage from every hit the shooters have queued since last time. Only the host does this so health has one writer.
+    /// Shooters already skip teammates but the host checks again so a client that is out of date or lying cant hurt its own team
+    pub fn apply_hits(&mut self, ctx: &TickContext, friendly_fire: bool) -> Vec<CombatEvent> {
+
+        let mut combat_events = Vec::new();
+
+        if !*ctx.is_host {
+            return combat_events;
+        }
+
+        // shooters that left dont need their progress kept
+        let players = &self.players;
+
+        self.applied_hits.retain(|shooter, _| players.iter().any(|(_, player)| player.owner == *shooter));
+
+        let mut new_hits: Vec<(String, Option<Team>, Hit)> = Vec::new();
+
+        for (_, player) in &self.players {
+
+            let applied_id = self.applied_hits.get(&player.owner).copied().unwrap_or(0);
+
+            for hit in player.hits.iter().filter(|hit| hit.id > applied_id) {
+                new_hits.push((player.owner.clone(), player.team, hit.clone()));
+            }
+        }
+
+        for (shooter, shooter_team, hit) in new_hits {
+
+            self.applied_hits.insert(shooter.clone(), hit.id);
+
+            let victim = match self.players.iter_mut().find(|(_, player)| player.owner == hit.victim) {
+                Some((_, victim)) => victim,
+                None => continue,
+            };
+
+            // teammates dont take damage unless friendly fire is on
+            if !friendly_fire && victim.team.is_some() && victim.team == shooter_team {
+                continue;
+            }
+
+            let killed = victim.damage(hit.amount, Some(&shooter), Some(&hit.weapon), &mut combat_events);
+
+            // big hits blow a limb off on the way down
+            if killed && hit.amount >= LIMB_SEVER_DAMAGE {
+                victim.limbs.sever_nearest(&self.space, hit.shooter_pos);
+            }
+        }
+
+        combat_events
+    }
+
+    /// Count kills, deaths and assists and post them to the kill feed. Only the host does this so every death is counted once
+    pub fn record_deaths(&mut self, ctx: &TickContext) {
+
+        if !*ctx.is_host {
+            return;
+        }
+
+        let mut deaths: Vec<(String, String, Option<Team>, DeathState, Vec<String>)> = Vec::new();
+
+        for (_, player) in self.players.iter_mut() {
+
+            let victim_name = player.name();
+
+            let death = match &mut player.death {
+                Some(death) if !death.recorded => death,
+                _ => continue,
+            };
+
+            death.recorded = true;
+
+            player.deaths += 1;
+
+            deaths.push((player.owner.clone(), victim_name, player.team, death.clone(), player.damagers.clone()));
+        }
+
+        for (victim, victim_name, victim_team, death, damagers) in deaths {
+
+            let weapon = death.weapon.unwrap_or("World".to_string());
+
+            // suicides still show up in the feed but dont count as a kill
+            let killer = death.killer.filter(|killer| *killer != victim);
+
+            let mut killer_name = "World".to_string();
+
+            for (_, player) in self.players.iter_mut() {
+
+                // team kills still show up in the feed but dont count or pay out
+                let earns_credit = earns_kill_credit(player.team, victim_team);
+
+                if Some(&player.owner) == killer.as_ref() {
+                    if earns_credit {
+                        player.kills += 1;
+                    }
+
+                    killer_name = player.name();
+                }
+
+                // everyone else who hurt them this life gets an assist
+                else if player.owner != victim && damagers.contains(&player.owner) && earns_credit {
+                    player.assists += 1;
+                }
+            }
+
+            self.kill_feed.add_kill(killer_name, victim_name, weapon);
+        }
+
+        for (_, enemy) in self.enemies.iter_mut() {
+
+            if enemy.health > 0 || enemy.death_recorded {
+                continue;
+            }
+
+            enemy.death_recorded = true;
+
+            let killer_name = self.players.iter()
+                .find(|(_, player)| Some(&player.owner) == enemy.killer.as_ref())
+                .map(|(_, player)| player.name())
+                .unwrap_or("World".to_string());
+
+            self.kill_feed.add_kill(killer_name, "Enemy".to_string(), enemy.killer_weapon.clone().unwrap_or("World".to_string()));
+        }
+    }
+
+    /// Start a time stop with T and freeze or thaw the bodies we own to match the current one
+    pub fn time_stop_tick(&mut self, ctx: &mut TickContext, sandbox: Option<&SandboxData>) {
+
+        if ctx.bindings.released(Action::TimeStop) && self.time_stop.is_none() {
+
+            let our_player = self.players.iter_mut().find(|(_, player)| player.owner == *ctx.uuid && player.death.is_none());
+
+            match our_player {
+                // sandbox has unlimited time stops
+                Some(_) if sandbox.is_some() => self.time_stop = Some(TimeStop::new(ctx.uuid.clone())),
+                Some((_, player)) if player.time_stop_charges > 0 => {
+                    player.time_stop_charges -= 1;
+
+                    self.time_stop = Some(TimeStop::new(ctx.uuid.clone()));
+                },
+                _ => {},
+            }
+        }
+
+        if *ctx.is_host {
+            if let Some(time_stop) = &mut self.time_stop {
+                time_stop.host_tick();
+            }
+        }
+
+        let time_stop = match &self.time_stop {
+            Some(time_stop) => time_stop,
+            None => {
+                ctx.freezer.thaw_all(&mut self.space);
+
+                return;
+            },
+        };
+
+        if time_stop.over {
+            ctx.freezer.thaw_all(&mut self.space);
+
+            // the activator cleans up, or the host if they left
+            if time_stop.activator == *ctx.uuid || *ctx.is_host {
+                self.time_stop = None;
+            }
+
+            return;
+        }
+
+        if !time_stop.frozen {
+            return;
+        }
+
+        let exempt = self.time_stop_exempt_bodies();
+
+        for body_handle in ctx.owned_rigid_bodies.iter() {
+            if !exempt.contains(body_handle) {
+                ctx.freezer.freeze(&mut self.space, *body_handle);
+            }
+        }
+    }
+
+    /// The bodies of whoever stopped time
+    pub fn time_stop_exempt_bodies(&self) -> Vec<SyncRigidBodyHandle> {
+
+        let activator = match &self.time_stop {
+            Some(time_stop) => &time_stop.activator,
+            None => return Vec::new(),
+        };
+
+        self.players.iter()
+            .filter(|(_, player)| player.owner == *activator)
+            .flat_map(|(_, player)| player.body_handles())
+            .collect()
+    }
+
+    /// Float everything we own and splash anything that hits the water
+    pub fn water_tick(&mut self, ctx: &mut TickContext) {
+
+        for water in &self.water {
+            water.tick(&mut self.space, ctx);
+
+            water.splash_tick(&self.space, ctx.splashes, ctx.last_tick_duration.as_secs_f32());
+        }
+
+        ctx.splashes.tick();
+    }
+
+    pub fn spawn_damage_number(&mut self, pos: Vec2) {
         self.damage_numbers.insert(
-            DamageNumber::new(&mut self.space, 5, mouse_pos, Some(24), Some(WHITE))
+            DamageNumber::new(&mut self.space, 5, pos, Some(24), Some(WHITE))
         );
     }
 
+    /// Spawn whatever is selected in the sandbox spawn menu at the mouse
+    pub fn sandbox_tick(&mut self, ctx: &mut TickContext) {
+
+        if !ctx.spawn_menu.spawn_requested(ctx.bindings) {
+            return;
+        }
+
+        let mouse_pos = rapier_mouse_world_pos(ctx.camera_rect);
+
+        let entity = ctx.spawn_menu.selected;
+
+        self.sandbox_spawn(entity, mouse_pos, ctx);
+    }
+
+    pub fn sandbox_spawn(&mut self, entity: SpawnableEntity, pos: Vec2, ctx: &mut TickContext) {
+        match entity {
+            SpawnableEntity::Enemy => {
+                self.enemies.insert(
+                    Enemy::new(pos, ctx.uuid.clone(), &mut self.space, ctx.textures)
+                );
+            },
+            SpawnableEntity::Grenade => {
+                self.grenades.push(
+                    Grenade::new(pos, &mut self.space)
+                );
+            },
+            SpawnableEntity::Pixel => self.spawn_pixel(pos, ctx),
+            SpawnableEntity::DamageNumber => self.spawn_damage_number(pos),
+            SpawnableEntity::Brick => {
+                self.bricks.push(
+                    Brick::new(&mut self.space, pos, Some(ctx.uuid.clone()))
+                );
+            },
+            SpawnableEntity::Structure => {
+                self.structures.insert(
+                    Structure::new(pos, &mut self.space, ctx.uuid.clone())
+                );
+            },
+            SpawnableEntity::Shotgun => {
+                self.shotguns.push(
+                    Shotgun::new(&mut self.space, pos, ctx.uuid.clone(), None, ctx.textures, Facing::Right)
+                );
+            },
+            SpawnableEntity::Boat => {
+                self.boats.push(
+                    Boat::new(&mut self.space, pos, ctx.uuid.clone())
+                );
+            },
+            SpawnableEntity::Radio => {
+                self.radios.push(
+                    RadioBuilder::new(&mut self.space, &pos)
+                        .owner(ctx.uuid.clone())
+                        .build()
+                );
+            },
+            SpawnableEntity::Teleporter => {
+                self.teleporters.push(
+                    Teleporter::new(pos, &mut self.space, ctx.uuid)
+                );
+            },
+            SpawnableEntity::Portal => {
+                // portals have to be stuck to something
+                let attached_collider = self.structures.iter()
+                    .find(|(_, structure)| structure.contains_point(&mut self.space, pos))
+                    .map(|(_, structure)| structure.collider_handle);
+
+                if let Some(attached_collider) = attached_collider {
+                    self.portals.push(
+                        Portal { attached_collider }
+                    );
+                }
+            },
+            SpawnableEntity::Flag => {
+                self.flags.push(
+                    Flag::new(Team::Red, pos, &mut self.space, ctx.uuid.clone())
+                );
+            },
+        }
+    }
+
     pub fn spawn_fixed_structure(&mut self, camera_rect: &Rect, uuid: &String) {
         if is_key_released(input::KeyCode::Q) {
 
@@ -431,7 +1353,7 @@ impl Level {
     }
 
     pub async fn editor_draw(&self, textures: &mut TextureLoader, camera_rect: &Rect) {
-        for (_, structure) in &self.structures {
+        for (_, structure) in self.structures.iter() {
 
             let texture_path = structure.sprite_path.clone() ;
             structure.debug_draw(&self.space, &texture_path, textures).await;
@@ -449,6 +1371,35 @@ impl Level {
             brick.editor_draw(&self.space, textures).await
         }
 
+        for flag_base in &self.flag_bases {
+            flag_base.draw();
+        }
+
+        // every zone looks the same in the editor
+        for hill_zone in &self.hill_zones {
+            hill_zone.draw(&self.space, WHITE);
+        }
+
+        for spawn_point in &self.spawn_points {
+            spawn_point.draw();
+        }
+
+        for shop in &self.shops {
+            shop.draw();
+        }
+
+        for pickup in &self.pickups {
+            pickup.draw();
+        }
+        for boat in &self.boats {
+            boat.draw(&self.space, textures).await;
+        }
+
+        for water in &self.water {
+            water.draw();
+            water.draw_handle();
+        }
+
         let mouse_world_pos = mouse_world_pos(camera_rect);
         // draw structure cursor
         draw_rectangle_lines(mouse_world_pos.x - 20., mouse_world_pos.y - 20., 40., 40., 4., WHITE);
@@ -458,6 +1409,10 @@ impl Level {
         for (_, player) in &self.players {
             player.draw_hud(ctx).await
         }
+
+        if let Some(time_stop) = &self.time_stop {
+            time_stop.draw_hud();
+        }
     }
     pub async fn draw(
         &self, 
@@ -488,9 +1443,28 @@ impl Level {
         for teleporter in &self.teleporters {
             teleporter.draw(&self.space);
         }
+        for flag_base in &self.flag_bases {
+            flag_base.draw();
+        }
+        for flag in &self.flags {
+            flag.draw(&self.space);
+        }
+        for shop in &self.shops {
+            shop.draw();
+        }
+
+        for pickup in &self.pickups {
+            pickup.draw();
+        }
+        for boat in &self.boats {
+            boat.draw(&self.space, textures).await;
+        }
         for shotgun in &self.shotguns {
             shotgun.draw(&self.space, textures, false, false).await;
         }
+        for weapon in &self.dropped_weapons {
+            weapon.draw(&self.space, textures, false, false).await;
+        }
 
         for (_, bullet_trail) in &self.bullet_trails {
             bullet_trail.draw();
@@ -499,6 +1473,10 @@ impl Level {
             enemy.draw(&self.space, textures).await;
         }
 
+        for ragdoll in &self.ragdolls {
+            ragdoll.draw(textures, &self.space).await;
+        }
+
         for pixel in &self.pixels {
             pixel.draw(&self.space).await;
         }
@@ -541,5 +1519,14 @@ impl Level {
         for blood in &self.blood {
             blood.draw(&self.space).await
         }
+
+        // water goes over everything so whatever is in it looks submerged
+        for water in &self.water {
+            water.draw();
+        }
+
+        if let Some(time_stop) = &self.time_stop {
+            time_stop.draw(&self.space, &self.time_stop_exempt_bodies());
+        }
     }
 }
\ No newline at end of file
@@ -1,6 +1,15 @@
 use std::{fs, path::Path, time::{Duration, Instant}};
 
 use console::Console;
+use sandbox::SpawnMenu;
+use shop::ShopMenu;
+use boat::PlankPlacer;
+use time_stop::Freezer;
+use water::Splashes;
+use bindings::Bindings;
+use player::animation::AnimationLibrary;
+use profile::Profile;
+use cosmetics::CosmeticsCatalog;
 use diff::Diff;
 use futures::executor::block_on;
 use gamelibrary::{font_loader::FontLoader, rapier_mouse_world_pos, sound::soundmanager::SoundManager, space::{Space, SyncColliderHandle, SyncImpulseJointHandle, SyncRigidBodyHandle}, texture_loader::TextureLoader, traits::HasPhysics};
@@ -52,6 +61,26 @@ pub mod collider_groups;
 pub mod blood;
 pub mod events;
 pub mod updates;
+pub mod dirty;
+pub mod sync_rate;
+pub mod team;
+pub mod kill_feed;
+pub mod flag;
+pub mod hill;
+pub mod match_state;
+pub mod spawn_point;
+pub mod sandbox;
+pub mod game_mode;
+pub mod shop;
+pub mod boat;
+pub mod water;
+pub mod time_stop;
+pub mod bindings;
+pub mod gamepad;
+pub mod pickup;
+pub mod profile;
+pub mod cosmetics;
+pub mod round_score;
 
 
 #[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
@@ -262,6 +291,15 @@ pub struct TickContext<'a> {
     pub camera_offset: &'a mut Vec2,
     pub active_gamepad: &'a Option<GamepadId>,
     pub console: &'a mut Console,
+    pub spawn_menu: &'a mut SpawnMenu,
+    pub shop_menu: &'a mut ShopMenu,
+    pub plank_placer: &'a mut PlankPlacer,
+    pub freezer: &'a mut Freezer,
+    pub splashes: &'a mut Splashes,
+    pub bindings: &'a Bindings,
+    pub animations: &'a AnimationLibrary,
+    pub profile: &'a Profile,
+    pub cosmetics: &'a CosmeticsCatalog,
     pub owned_rigid_bodies: &'a mut Vec<SyncRigidBodyHandle>,
     pub owned_colliders: &'a mut Vec<SyncColliderHandle>,
     pub owned_impulse_joints: &'a mut Vec<SyncImpulseJointHandle>,
@@ -2,11 +2,11 @@ use std::{f32::consts::PI, time::Instant};
 
 use futures::executor::block_on;
 use gamelibrary::{font_loader::FontLoader, get_angle_to_mouse, macroquad_to_rapier, menu::Button, space::{Space, SyncImpulseJointHandle, SyncRigidBodyHandle}, texture_loader::TextureLoader};
-use macroquad::{color::{Color, BLACK, DARKGRAY}, math::{Rect, Vec2}, miniquad::window::request_quit, text::{draw_text_ex, load_ttf_font, Font, TextParams}, window::clear_background};
+use macroquad::{color::{Color, BLACK, DARKGRAY, GRAY, WHITE}, input::{get_char_pressed, is_key_pressed, is_mouse_button_released, mouse_position, KeyCode, MouseButton}, math::{Rect, Vec2}, miniquad::window::request_quit, shapes::draw_rectangle_lines, text::{draw_text_ex, load_ttf_font, Font, TextParams}, texture::{draw_texture_ex, DrawTextureParams}, window::clear_background};
 use nalgebra::vector;
 use rapier2d::prelude::{RevoluteJointBuilder, RigidBodyBuilder};
 
-use crate::{player::body_part::BodyPart, TickContext};
+use crate::{cosmetics::{CosmeticSlot, CosmeticsCatalog}, player::body_part::BodyPart, profile::{Profile, MAX_NAME_LENGTH}, TickContext};
 
 // the main menu can be rendered on top of anything else
 pub struct MainMenu {
@@ -22,9 +22,27 @@ pub struct MainMenu {
     pub new_game: bool,
     pub connect: bool,
     pub quit: bool,
-    pub launch_editor: bool
+    pub launch_editor: bool,
+    pub profile: Profile,
+    pub editing_name: bool,
+    character_button: Button,
+    back_button: Button,
+    cosmetic_buttons: Vec<(CosmeticSlot, i32, Button)>, // the arrows on the character screen and which way they flip
+    cosmetics: CosmeticsCatalog,
+    pub character_screen: bool
 }
 
+// where the display name box sits
+const NAME_FIELD: Rect = Rect { x: 50., y: 190., w: 400., h: 60. };
+
+// the rows of the character screen
+const COSMETIC_ROW_Y: f32 = 200.;
+const COSMETIC_ROW_SPACING: f32 = 90.;
+
+// where the cat is drawn on the character screen
+const PREVIEW_POSITION: Vec2 = Vec2 { x: 650., y: 220. };
+const PREVIEW_SCALE: f32 = 4.;
+
 impl MainMenu {
 
     pub async fn new(textures: &mut TextureLoader) -> Self {
@@ -41,6 +59,25 @@ impl MainMenu {
 
         let editor_button = Button::new("Editor".to_string(), Rect::new(50., 540., 150., 60.), clear_color, Some(clear_color), Some(clear_color), 50, "assets/fonts/CutePixel.ttf".to_string()).await;
 
+        let character_button = Button::new("Character".to_string(), Rect::new(50., 380., 250., 60.), clear_color, Some(clear_color), Some(clear_color), 50, "assets/fonts/CutePixel.ttf".to_string()).await;
+
+        let back_button = Button::new("Back".to_string(), Rect::new(50., COSMETIC_ROW_Y + COSMETIC_ROW_SPACING * 3., 150., 60.), clear_color, Some(clear_color), Some(clear_color), 50, "assets/fonts/CutePixel.ttf".to_string()).await;
+
+        let mut cosmetic_buttons = Vec::new();
+
+        for (row, slot) in CosmeticSlot::ALL.into_iter().enumerate() {
+
+            let y = COSMETIC_ROW_Y + COSMETIC_ROW_SPACING * row as f32;
+
+            cosmetic_buttons.push(
+                (slot, -1, Button::new("<".to_string(), Rect::new(50., y, 60., 60.), clear_color, Some(clear_color), Some(clear_color), 50, "assets/fonts/CutePixel.ttf".to_string()).await)
+            );
+
+            cosmetic_buttons.push(
+                (slot, 1, Button::new(">".to_string(), Rect::new(470., y, 60., 60.), clear_color, Some(clear_color), Some(clear_color), 50, "assets/fonts/CutePixel.ttf".to_string()).await)
+            );
+        }
+
         let font = load_ttf_font("assets/fonts/CutePixel.ttf").await.unwrap();
         
         let mut space = Space::new();
@@ -95,8 +132,14 @@ impl MainMenu {
             quit: false,
             editor_button,
             new_game: false,
-            launch_editor: false
-            
+            launch_editor: false,
+            profile: Profile::load(),
+            editing_name: false,
+            character_button,
+            back_button,
+            cosmetic_buttons,
+            cosmetics: CosmeticsCatalog::load(),
+            character_screen: false
         }
     }
 
@@ -142,14 +185,195 @@ impl MainMenu {
         );
 
         //self.head.draw(textures, &self.space, false).await;
+
+        if self.character_screen {
+            self.draw_character_screen(textures).await;
+
+            return;
+        }
+
+        self.draw_name_field();
         
         //self.new_game_button.draw().await;
         self.connect_game_button.draw().await;
+        self.character_button.draw().await;
         //self.quit_button.draw().await;
         //self.editor_button.draw().await;
     
     }
 
+    async fn draw_character_screen(&self, textures: &mut TextureLoader) {
+
+        for (row, slot) in CosmeticSlot::ALL.into_iter().enumerate() {
+
+            let picked = match slot {
+                CosmeticSlot::Head => &self.profile.head,
+                CosmeticSlot::Body => &self.profile.body,
+                CosmeticSlot::Tint => &self.profile.tint,
+            };
+
+            // show what will actually be used when nothing was picked yet
+            let names = self.cosmetics.names(slot);
+
+            let name = match names.iter().find(|name| **name == picked.as_str()).or(names.first()) {
+                Some(name) => name.to_string(),
+                None => "Default".to_string(),
+            };
+
+            draw_text_ex(
+                &format!("{}: {}", slot.name(), name),
+                130.,
+                COSMETIC_ROW_Y + COSMETIC_ROW_SPACING * row as f32 + 42.,
+                TextParams {
+                    font: Some(&self.font),
+                    font_size: 40,
+                    color: WHITE,
+                    ..Default::default()
+                }
+            );
+        }
+
+        for (_, _, button) in &self.cosmetic_buttons {
+            button.draw().await;
+        }
+
+        self.back_button.draw().await;
+
+        self.draw_preview(textures).await;
+    }
+
+    /// The cat as it will look in game. The body is drawn first so the head sits on top of it
+    async fn draw_preview(&self, textures: &mut TextureLoader) {
+
+        let tint = match self.cosmetics.tint(&self.profile.tint) {
+            Some(tint) => tint.color(),
+            None => WHITE,
+        };
+
+        let body_size = Vec2::new(22., 19.) * PREVIEW_SCALE;
+        let head_size = Vec2::new(30., 28.) * PREVIEW_SCALE;
+
+        let body_position = PREVIEW_POSITION + Vec2::new(-body_size.x / 2., head_size.y - 8. * PREVIEW_SCALE);
+        let head_position = PREVIEW_POSITION + Vec2::new(-head_size.x / 2., 0.);
+
+        for (sprite, position, size) in [
+            (self.cosmetics.body_sprite(&self.profile.body), body_position, body_size),
+            (self.cosmetics.head_sprite(&self.profile.head), head_position, head_size)
+        ] {
+            draw_texture_ex(
+                textures.get(&sprite.to_string()).await,
+                position.x,
+                position.y,
+                tint,
+                DrawTextureParams {
+                    dest_size: Some(size),
+                    ..Default::default()
+                }
+            );
+        }
+    }
+
+    fn draw_name_field(&self) {
+
+        let name = match (self.profile.display_name.is_empty(), self.editing_name) {
+            (true, false) => "click to pick a name".to_string(),
+            (_, true) => format!("{}_", self.profile.display_name),
+            (false, false) => self.profile.display_name.clone(),
+        };
+
+        let color = match self.editing_name {
+            true => WHITE,
+            false => GRAY,
+        };
+
+        draw_rectangle_lines(NAME_FIELD.x, NAME_FIELD.y, NAME_FIELD.w, NAME_FIELD.h, 2., color);
+
+        draw_text_ex(
+            &format!("Name: {}", name),
+            NAME_FIELD.x + 10.,
+            NAME_FIELD.y + 42.,
+            TextParams {
+                font: Some(&self.font),
+                font_size: 40,
+                color,
+                ..Default::default()
+            }
+        );
+    }
+
+    /// Click the name box and type. Enter or clicking somewhere else saves it
+    pub fn update_name_field(&mut self) {
+
+        if is_mouse_button_released(MouseButton::Left) {
+
+            let (mouse_x, mouse_y) = mouse_position();
+
+            let clicked_field = NAME_FIELD.contains(Vec2::new(mouse_x, mouse_y));
+
+            if self.editing_name && !clicked_field {
+                self.finish_editing_name();
+            }
+
+            else if clicked_field {
+                self.editing_name = true;
+            }
+        }
+
+        // always drain the typed characters so they dont pile up until the box is clicked
+        while let Some(character) = get_char_pressed() {
+            if self.editing_name && !character.is_control() && self.profile.display_name.chars().count() < MAX_NAME_LENGTH {
+                self.profile.display_name.push(character);
+            }
+        }
+
+        if !self.editing_name {
+            return;
+        }
+
+        if is_key_pressed(KeyCode::Backspace) {
+            self.profile.display_name.pop();
+        }
+
+        if is_key_pressed(KeyCode::Enter) {
+            self.finish_editing_name();
+        }
+    }
+
+    fn finish_editing_name(&mut self) {
+        self.editing_name = false;
+
+        let name = self.profile.display_name.clone();
+
+        self.profile.set_display_name(&name);
+        self.profile.save();
+    }
+
+    /// Flip through the catalog. Every pick is saved straight away
+    fn update_character_screen(&mut self, ctx: &mut TickContext) {
+
+        let mut changed = false;
+
+        for (slot, step, button) in &mut self.cosmetic_buttons {
+            button.update(Some(ctx.camera_rect));
+
+            if button.clicked {
+                self.cosmetics.cycle(&mut self.profile, *slot, *step);
+
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.profile.save();
+        }
+
+        self.back_button.update(Some(ctx.camera_rect));
+
+        if self.back_button.clicked {
+            self.character_screen = false;
+        }
+    }
+
     pub fn tick(&mut self, ctx: &mut TickContext) {
         self.head.tick(&self.space, ctx);
 
@@ -157,6 +381,27 @@ impl MainMenu {
 
         self.space.step(&ctx.owned_rigid_bodies, &ctx.owned_colliders, &Vec::new(), ctx.last_tick_duration);
 
+        if self.character_screen {
+            self.update_character_screen(ctx);
+
+            return;
+        }
+
+        self.update_name_field();
+
+        self.character_button.update(Some(ctx.camera_rect));
+
+        if self.character_button.clicked {
+            // same as connecting, dont throw away a half typed name
+            if self.editing_name {
+                self.finish_editing_name();
+            }
+
+            self.character_screen = true;
+
+            return;
+        }
+
         //self.new_game_button.update(Some(ctx.camera_rect));
         self.connect_game_button.update(Some(ctx.camera_rect));
         //self.quit_button.update(Some(ctx.camera_rect));
@@ -168,6 +413,11 @@ impl MainMenu {
         // }
 
         if self.connect_game_button.clicked {
+            // dont lose a name that was still being typed
+            if self.editing_name {
+                self.finish_editing_name();
+            }
+
             self.connect = true;
         };
 
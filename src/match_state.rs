@@ -0,0 +1,302 @@
+use std::{collections::HashMap, fs};
+
+use diff::Diff;
+use gamelibrary::{log, sync_arena::SyncArena, time::Time};
+use serde::{Deserialize, Serialize};
+
+use crate::{game_state::Mode, level::Level, player::player::Player, team::Team};
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Eq, Clone, Copy, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub enum MatchPhase {
+    Warmup, // waiting for enough players. nothing counts
+    Live,
+    RoundEnd, // showing the results of the round
+    Intermission // waiting for the next round or level to load
+}
+
+impl MatchPhase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MatchPhase::Warmup => "Warmup",
+            MatchPhase::Live => "Live",
+            MatchPhase::RoundEnd => "Round over",
+            MatchPhase::Intermission => "Intermission",
+        }
+    }
+}
+
+/// The phase the match is in and how long each phase lasts. Every client counts down from the same synced `phase_start`
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct MatchState {
+    pub phase: MatchPhase,
+    pub phase_start: Time,
+    pub warmup_duration: u32, // ms of warmup once enough players are connected
+    pub round_duration: Option<u32>, // the time limit for a round in ms. None means the mode decides when the round ends
+    pub round_end_duration: u32, // ms the results screen is shown for
+    pub intermission_duration: u32, // ms to wait before the next round starts
+    pub results: Option<RoundResults>,
+    #[serde(default = "default_respawn_delay")]
+    pub respawn_delay: u32, // ms a dead player waits before respawning during a round
+    #[serde(default = "default_warmup_respawn_delay")]
+    pub warmup_respawn_delay: u32 // warmup respawns are quicker so people can mess around
+}
+
+fn default_respawn_delay() -> u32 {
+    5000
+}
+
+fn default_warmup_respawn_delay() -> u32 {
+    3000
+}
+
+impl Default for MatchState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MatchState {
+    pub fn new() -> Self {
+        Self {
+            phase: MatchPhase::Warmup,
+            phase_start: Time::now(),
+            warmup_duration: 30000,
+            round_duration: Some(600000),
+            round_end_duration: 10000,
+            intermission_duration: 15000,
+            results: None,
+            respawn_delay: default_respawn_delay(),
+            warmup_respawn_delay: default_warmup_respawn_delay()
+        }
+    }
+
+    pub fn set_phase(&mut self, phase: MatchPhase) {
+        self.phase = phase;
+        self.phase_start = Time::now();
+    }
+
+    /// The length of the current phase in ms. None if the phase doesnt end on a timer
+    pub fn phase_duration(&self, mode: &Mode) -> Option<u32> {
+        match self.phase {
+            MatchPhase::Warmup => Some(self.warmup_duration),
+            MatchPhase::Live => {
+                match mode.timed_rounds() {
+                    true => self.round_duration,
+                    false => None,
+                }
+            },
+            MatchPhase::RoundEnd => Some(self.round_end_duration),
+            MatchPhase::Intermission => Some(self.intermission_duration),
+        }
+    }
+
+    pub fn phase_over(&self, mode: &Mode) -> bool {
+        match self.phase_duration(mode) {
+            Some(phase_duration) => self.phase_start.elapsed().num_milliseconds() >= phase_duration.into(),
+            None => false,
+        }
+    }
+
+    /// The number of whole seconds left in the current phase
+    pub fn remaining_seconds(&self, mode: &Mode) -> Option<i64> {
+        let phase_duration = self.phase_duration(mode)?;
+
+        let remaining_millis = i64::from(phase_duration) - self.phase_start.elapsed().num_milliseconds();
+
+        Some((remaining_millis.max(0) + 999) / 1000)
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct RoundResults {
+    pub headline: String,
+    pub players: Vec<PlayerResult> // sorted best first
+}
+
+impl RoundResults {
+    pub fn new(headline: String, players: &SyncArena<Player>) -> Self {
+
+        let mut player_results: Vec<PlayerResult> = players.iter()
+            .map(|(_, player)| {
+                PlayerResult {
+                    name: player.name(),
+                    owner: player.owner.clone(),
+                    team: player.team,
+                    kills: player.kills,
+                    deaths: player.deaths,
+                    assists: player.assists
+                }
+            })
+            .collect();
+
+        player_results.sort_by(|a, b| b.kills.cmp(&a.kills).then(a.deaths.cmp(&b.deaths)));
+
+        Self {
+            headline,
+            players: player_results
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct PlayerResult {
+    pub name: String,
+    pub owner: String,
+    pub team: Option<Team>,
+    pub kills: u32,
+    pub deaths: u32,
+    pub assists: u32
+}
+
+/// The levels and modes the server cycles through. Loaded from a yaml file next to the server
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Default)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct MatchRotation {
+    pub entries: Vec<RotationEntry>,
+    #[serde(default)]
+    pub current: usize,
+    #[serde(skip)]
+    pub loaded_levels: LoadedLevels
+}
+
+impl MatchRotation {
+
+    /// Load the rotation and every level in it. Entries whose level cant be loaded are left out
+    pub fn from_file(path: &str) -> Option<Self> {
+
+        let bytes = fs::read(path).ok()?;
+
+        let mut rotation = match serde_yaml::from_slice::<Self>(&bytes) {
+            Ok(rotation) => rotation,
+            Err(error) => {
+                log(&format!("failed to parse rotation file {}: {}", path, error));
+
+                return None;
+            },
+        };
+
+        // load every level up front so a bad file is caught before the match starts instead of when we get to it
+        let mut loaded_levels = LoadedLevels::default();
+
+        rotation.entries.retain(|entry| {
+            match Level::from_save(&entry.level) {
+                Ok(level) => {
+                    loaded_levels.levels.insert(entry.level.clone(), level);
+
+                    true
+                },
+                Err(error) => {
+                    log(&format!("skipping rotation entry: {}", error));
+
+                    false
+                },
+            }
+        });
+
+        rotation.loaded_levels = loaded_levels;
+
+        if rotation.entries.is_empty() {
+            return None;
+        }
+
+        if rotation.current >= rotation.entries.len() {
+            rotation.current = 0;
+        }
+
+        Some(rotation)
+    }
+
+    pub fn current(&self) -> Option<&RotationEntry> {
+        self.entries.get(self.current)
+    }
+
+    /// The level for the current entry. Whoever doesnt have it loaded yet, like a host that got the rotation from the server, loads it from disk and keeps it for next time
+    pub fn current_level(&mut self) -> Option<Level> {
+
+        let path = self.current()?.level.clone();
+
+        if let Some(level) = self.loaded_levels.levels.get(&path) {
+            return Some(level.clone());
+        }
+
+        match Level::from_save(&path) {
+            Ok(level) => {
+                self.loaded_levels.levels.insert(path, level.clone());
+
+                Some(level)
+            },
+            Err(error) => {
+                log(&format!("failed to load rotation level: {}", error));
+
+                None
+            },
+        }
+    }
+
+    pub fn upcoming(&self) -> Option<&RotationEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        self.entries.get((self.current + 1) % self.entries.len())
+    }
+
+    /// Move to the next entry, wrapping back to the start
+    pub fn advance(&mut self) -> Option<&RotationEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        self.current = (self.current + 1) % self.entries.len();
+
+        self.current()
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct RotationEntry {
+    pub level: String, // path to the level file
+    pub mode: Mode
+}
+
+/// The rotation's levels, keyed by path. Every level has its own space so they are kept on whoever loaded them instead of being synced with the rotation
+#[derive(Clone, Default)]
+pub struct LoadedLevels {
+    pub levels: HashMap<String, Level>
+}
+
+// nothing in here is synced so every copy counts as the same
+impl PartialEq for LoadedLevels {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Diff for LoadedLevels {
+    type Repr = ();
+
+    fn diff(&self, _other: &Self) -> Self::Repr {}
+
+    fn apply(&mut self, _diff: &Self::Repr) {}
+
+    fn identity() -> Self {
+        Self::default()
+    }
+}
@@ -18,6 +18,10 @@ impl MuzzleFlash {
         self.last_flash = Time::now()
     }
 
+    pub fn last_flash(&self) -> Time {
+        self.last_flash.clone()
+    }
+
     // creating a new muzzle flash does not flash it by default
     pub fn new(sprite_path: String, duration: Duration) -> Self {
         Self {
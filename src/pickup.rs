@@ -0,0 +1,111 @@
+use diff::Diff;
+use gamelibrary::{rapier_to_macroquad, time::Time};
+use macroquad::{color::{Color, GREEN, SKYBLUE, WHITE}, math::Vec2, shapes::{draw_circle_lines, draw_rectangle}};
+use serde::{Deserialize, Serialize};
+
+use crate::player::player::Player;
+
+// how close a player needs to get to grab one
+const PICKUP_RADIUS: f32 = 50.;
+
+const DEFAULT_RESPAWN_DELAY: u32 = 20000;
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Copy)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub enum PickupKind {
+    Health,
+    Armor
+}
+
+impl PickupKind {
+    pub fn color(&self) -> Color {
+        match self {
+            PickupKind::Health => GREEN,
+            PickupKind::Armor => SKYBLUE,
+        }
+    }
+}
+
+/// Health or armor placed in the level in the editor. It disappears when someone grabs it and comes back after a delay
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Pickup {
+    pub position: Vec2, // rapier coords
+    pub kind: PickupKind,
+    pub amount: u32,
+    pub respawn_delay: u32, // ms
+    pub taken_at: Option<Time> // None while it is there to grab
+}
+
+impl Pickup {
+    pub fn new(position: Vec2, kind: PickupKind) -> Self {
+        Self {
+            position,
+            kind,
+            amount: 50,
+            respawn_delay: DEFAULT_RESPAWN_DELAY,
+            taken_at: None
+        }
+    }
+
+    pub fn available(&self) -> bool {
+        match &self.taken_at {
+            Some(taken_at) => taken_at.elapsed().num_milliseconds() >= self.respawn_delay.into(),
+            None => true,
+        }
+    }
+
+    pub fn in_reach(&self, position: Vec2) -> bool {
+        self.position.distance(position) < PICKUP_RADIUS
+    }
+
+    /// Give the player what we hold. Full players leave it for someone else. Returns true if it was used up
+    pub fn apply(&mut self, player: &mut Player) -> bool {
+
+        if !self.available() {
+            return false;
+        }
+
+        let gained = match self.kind {
+            PickupKind::Health => player.heal(self.amount),
+            PickupKind::Armor => player.add_armor(self.amount),
+        };
+
+        if gained == 0 {
+            return false;
+        }
+
+        self.taken_at = Some(Time::now());
+
+        true
+    }
+
+    pub fn draw(&self) {
+
+        let draw_pos = rapier_to_macroquad(&self.position);
+
+        if !self.available() {
+            // just an outline so people know something comes back here
+            draw_circle_lines(draw_pos.x, draw_pos.y, 20., 2., Color { a: 0.3, ..self.kind.color() });
+
+            return;
+        }
+
+        draw_rectangle(draw_pos.x - 15., draw_pos.y - 15., 30., 30., self.kind.color());
+
+        match self.kind {
+            // a cross for health and a plain plate for armor
+            PickupKind::Health => {
+                draw_rectangle(draw_pos.x - 3., draw_pos.y - 10., 6., 20., WHITE);
+                draw_rectangle(draw_pos.x - 10., draw_pos.y - 3., 20., 6., WHITE);
+            },
+            PickupKind::Armor => {
+                draw_rectangle(draw_pos.x - 8., draw_pos.y - 8., 16., 16., WHITE);
+            },
+        }
+    }
+}
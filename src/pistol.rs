@@ -2,12 +2,15 @@ use std::{collections::HashSet, time::Duration};
 
 use diff::Diff;
 use gamelibrary::{sound::soundmanager::{SoundHandle, SoundManager}, space::{Space, SyncColliderHandle, SyncImpulseJointHandle, SyncRigidBodyHandle}, sync_arena::SyncArena, texture_loader::TextureLoader, time::Time};
-use macroquad::{input::{is_key_released, is_mouse_button_released}, math::Vec2};
+use macroquad::{input::is_key_released, math::Vec2};
 use serde::{Deserialize, Serialize};
 
-use crate::{blood::Blood, bullet_trail::BulletTrail, damage_number::DamageNumber, enemy::Enemy, player::{self, player::{Facing, Player, WeaponTickParameters}}, weapon::Weapon, TickContext};
+use crate::{bindings::Action, blood::Blood, bullet_trail::BulletTrail, damage_number::DamageNumber, enemy::Enemy, player::{self, player::{Facing, Player, WeaponTickParameters}}, weapon::Weapon, TickContext};
+
+pub const PISTOL_NAME: &str = "Pistol";
 
 #[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[serde(from = "SavedPistol")]
 #[diff(attr(
     #[derive(Serialize, Deserialize)]
 ))]
@@ -15,6 +18,24 @@ pub struct Pistol {
     weapon: Weapon
 }
 
+// pistols saved before weapons had names load with an empty one
+#[derive(Deserialize)]
+struct SavedPistol {
+    weapon: Weapon
+}
+
+impl From<SavedPistol> for Pistol {
+    fn from(saved: SavedPistol) -> Self {
+        let mut weapon = saved.weapon;
+
+        if weapon.name.is_empty() {
+            weapon.name = PISTOL_NAME.to_string();
+        }
+
+        Self { weapon }
+    }
+}
+
 impl Pistol {
     pub fn new(
         space: &mut Space, 
@@ -46,6 +67,7 @@ impl Pistol {
                 12,
                 12,
                 24,
+                PISTOL_NAME
             )
         }
     }
@@ -96,10 +118,42 @@ impl Pistol {
         self.fire(space, hit_markers, damage_numbers, bullet_trails, blood, ctx, weapon_tick_parameters);
     }
 
+    pub fn refill_ammo(&mut self, magazines: u32) {
+        self.weapon.refill_ammo(magazines)
+    }
+
     pub fn set_facing(&mut self, facing: Facing) {
         self.weapon.facing = facing
     }
 
+    pub fn name(&self) -> &String {
+        &self.weapon.name
+    }
+
+    pub fn set_owner(&mut self, owner: String) {
+        self.weapon.owner = owner
+    }
+
+    pub fn attach(&mut self, space: &mut Space, player_rigid_body_handle: SyncRigidBodyHandle) {
+        self.weapon.attach(space, player_rigid_body_handle)
+    }
+
+    pub fn detach(&mut self, space: &mut Space, velocity: Vec2) {
+        self.weapon.detach(space, velocity)
+    }
+
+    pub fn can_pick_up(&self) -> bool {
+        self.weapon.can_pick_up()
+    }
+
+    pub fn last_fired(&self) -> Time {
+        self.weapon.last_fired()
+    }
+
+    pub fn reloading(&self) -> bool {
+        self.weapon.reloading()
+    }
+
     pub fn fire(
         &mut self, 
         space: &mut Space, 
@@ -111,7 +165,7 @@ impl Pistol {
         weapon_tick_parameters: &mut WeaponTickParameters,
     ) {
 
-        if !is_mouse_button_released(macroquad::input::MouseButton::Left) {
+        if !weapon_tick_parameters.can_fire || !ctx.bindings.released(Action::Fire) {
             return;
         }
         
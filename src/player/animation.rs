@@ -0,0 +1,210 @@
+use std::{collections::BTreeMap, fs};
+
+use diff::Diff;
+use gamelibrary::{log, time::Time};
+use serde::{Deserialize, Serialize};
+
+pub const ANIMATIONS_PATH: &str = "assets/cat/animations.yaml";
+
+// faster than this along the ground counts as walking
+const WALK_SPEED: f32 = 30.;
+
+// how long after a shot we still count as firing
+const FIRE_WINDOW: i64 = 150;
+
+/// What the player is doing as far as animation cares. Ordered from least to most important
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Default)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Walk,
+    Airborne,
+    Fire,
+    Reload
+}
+
+/// Something a clip wants to happen when it reaches a frame
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum AnimationEvent {
+    Footstep,
+    ReloadClick
+}
+
+/// Target angles in radians for each limb joint, as if facing right. The joint limits still apply
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct Pose {
+    #[serde(default)]
+    pub left_arm: f32,
+    #[serde(default)]
+    pub right_arm: f32,
+    #[serde(default)]
+    pub left_foot: f32,
+    #[serde(default)]
+    pub right_foot: f32
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub struct AnimationFrame {
+    pub duration: u32, // ms
+    #[serde(default)]
+    pub pose: Pose,
+    #[serde(default)]
+    pub event: Option<AnimationEvent> // fired when the frame starts
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+pub struct AnimationClip {
+    pub frames: Vec<AnimationFrame>,
+    #[serde(default = "default_true")]
+    pub looping: bool, // clips that dont loop hold their last frame
+    #[serde(default = "default_true")]
+    pub interruptible: bool // false means the clip plays to the end before anything else can start
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Every clip, loaded from the animations file
+#[derive(Serialize, Deserialize, Default)]
+pub struct AnimationLibrary {
+    pub clips: BTreeMap<AnimationState, AnimationClip>
+}
+
+impl AnimationLibrary {
+    pub fn load() -> Self {
+        match fs::read(ANIMATIONS_PATH) {
+            Ok(bytes) => match serde_yaml::from_slice::<Self>(&bytes) {
+                Ok(library) => library,
+                Err(error) => {
+                    log(&format!("failed to parse animations file {}: {}", ANIMATIONS_PATH, error));
+
+                    Self::default()
+                },
+            },
+            Err(error) => {
+                log(&format!("failed to read animations file {}: {}", ANIMATIONS_PATH, error));
+
+                Self::default()
+            },
+        }
+    }
+}
+
+/// The synced things the state machine looks at. Every client can work these out for every player
+pub struct AnimationInputs {
+    pub speed: f32, // along the ground
+    pub grounded: bool,
+    pub last_fired: Option<Time>,
+    pub reloading: bool
+}
+
+impl AnimationInputs {
+    /// The state these inputs ask for if nothing is holding on to the current clip
+    pub fn desired_state(&self) -> AnimationState {
+
+        if self.reloading {
+            return AnimationState::Reload;
+        }
+
+        if self.last_fired.as_ref().is_some_and(|last_fired| last_fired.elapsed().num_milliseconds() < FIRE_WINDOW) {
+            return AnimationState::Fire;
+        }
+
+        if !self.grounded {
+            return AnimationState::Airborne;
+        }
+
+        if self.speed.abs() > WALK_SPEED {
+            return AnimationState::Walk;
+        }
+
+        AnimationState::Idle
+    }
+}
+
+/// Which clip a player is playing and how far into it they are. Worked out locally on every client from the synced state so none of this is sent
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct Animator {
+    #[serde(skip)]
+    pub state: AnimationState,
+    #[serde(skip)]
+    pub frame: usize,
+    #[serde(skip)]
+    pub frame_started: Time
+}
+
+impl Animator {
+
+    fn switch(&mut self, state: AnimationState, clips: &AnimationLibrary) -> Vec<AnimationEvent> {
+        self.state = state;
+        self.frame = 0;
+        self.frame_started = Time::now();
+
+        // the first frame's event fires as soon as we start
+        clips.clips.get(&state)
+            .and_then(|clip| clip.frames.first())
+            .and_then(|frame| frame.event)
+            .into_iter()
+            .collect()
+    }
+
+    /// Move between states and frames. Returns the events of every frame we entered
+    pub fn tick(&mut self, clips: &AnimationLibrary, inputs: &AnimationInputs) -> Vec<AnimationEvent> {
+
+        let desired = inputs.desired_state();
+
+        let clip = match clips.clips.get(&self.state) {
+            Some(clip) => clip,
+            // nothing to play for this state so go straight to whatever is wanted
+            None => return match desired == self.state {
+                true => Vec::new(),
+                false => self.switch(desired, clips),
+            },
+        };
+
+        let finished = !clip.looping && self.frame + 1 >= clip.frames.len();
+
+        if desired != self.state && (clip.interruptible || finished) {
+            return self.switch(desired, clips);
+        }
+
+        let frame = match clip.frames.get(self.frame) {
+            Some(frame) => frame,
+            None => return Vec::new(),
+        };
+
+        if self.frame_started.elapsed().num_milliseconds() < frame.duration.into() || finished {
+            return Vec::new();
+        }
+
+        self.frame = (self.frame + 1) % clip.frames.len();
+        self.frame_started = Time::now();
+
+        clip.frames[self.frame].event.into_iter().collect()
+    }
+
+    pub fn pose<'a>(&self, clips: &'a AnimationLibrary) -> Option<&'a Pose> {
+        clips.clips.get(&self.state)
+            .and_then(|clip| clip.frames.get(self.frame))
+            .map(|frame| &frame.pose)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AnimatorDiff;
+
+impl Diff for Animator {
+    type Repr = AnimatorDiff;
+
+    // every client animates every player itself so there is never anything to send
+    fn diff(&self, _other: &Self) -> Self::Repr {
+        AnimatorDiff
+    }
+
+    fn apply(&mut self, _diff: &Self::Repr) {}
+
+    fn identity() -> Self {
+        Self::default()
+    }
+}
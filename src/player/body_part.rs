@@ -1,6 +1,6 @@
 use diff::Diff;
-use gamelibrary::{space::{Space, SyncColliderHandle, SyncRigidBodyHandle}, texture_loader::TextureLoader, traits::draw_texture_onto_physics_body};
-use macroquad::math::Vec2;
+use gamelibrary::{rapier_to_macroquad, space::{Space, SyncColliderHandle, SyncRigidBodyHandle}, texture_loader::TextureLoader, traits::draw_texture_onto_physics_body};
+use macroquad::{color::Color, math::Vec2, texture::{draw_texture_ex, DrawTextureParams}};
 use nalgebra::vector;
 use rapier2d::prelude::{ColliderBuilder, ColliderHandle, Group, InteractionGroups, RigidBodyBuilder, RigidBodyHandle};
 use serde::{Deserialize, Serialize};
@@ -103,4 +103,30 @@ impl BodyPart {
         ).await
         
     }
+
+    /// Same as draw but multiplies the sprite by a color
+    pub async fn draw_tinted(&self, textures: &mut TextureLoader, space: &Space, flip_x: bool, tint: Color) {
+
+        let body = space.sync_rigid_body_set.get_sync(self.body_handle).unwrap();
+        let collider = space.sync_collider_set.get_sync(self.collider_handle).unwrap();
+
+        let half_extents = collider.shape().as_cuboid().unwrap().half_extents;
+
+        let draw_pos = rapier_to_macroquad(&Vec2::new(body.translation().x, body.translation().y));
+
+        let texture = textures.get(&self.sprite_path).await;
+
+        draw_texture_ex(
+            texture, 
+            draw_pos.x - half_extents.x, 
+            draw_pos.y - half_extents.y, 
+            tint, 
+            DrawTextureParams {
+                dest_size: Some(Vec2::new(half_extents.x * 2., half_extents.y * 2.)),
+                rotation: body.rotation().angle() * -1.,
+                flip_x,
+                ..Default::default()
+            }
+        );
+    }
 }
\ No newline at end of file
@@ -1,2 +1,5 @@
 pub mod player;
-pub mod body_part;
\ No newline at end of file
+pub mod body_part;
+pub mod ragdoll;
+pub mod animation;
+pub mod movement;
\ No newline at end of file
@@ -0,0 +1,186 @@
+use diff::Diff;
+use gamelibrary::{space::{Space, SyncRigidBodyHandle}, time::Time};
+use macroquad::math::{vec2, Vec2};
+use nalgebra::vector;
+use parry2d::{math::Isometry, query::ShapeCastOptions, shape::Cuboid};
+use rapier2d::prelude::{Collider, ColliderHandle, QueryFilter, RigidBodyHandle};
+use serde::{Deserialize, Serialize};
+
+// the body collider's half extents while standing. crouching squashes the height
+pub const STANDING_HALF_EXTENTS: Vec2 = Vec2::new(22., 19.);
+
+// the probes are shrunk a little on the axis they arent checking so a wall doesnt count as ground and the floor doesnt count as a wall
+const PROBE_INSET: f32 = 3.;
+
+// right after jumping we are still close enough to the floor for the probe to find it
+const JUMP_GROUND_GRACE: u32 = 100;
+
+/// Everything about how the player moves. Each player carries their own copy so it can be tuned per player
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct MovementSettings {
+    pub acceleration: f32, // speed added every tick a direction is held
+    pub walk_speed: f32, // we stop accelerating past these
+    pub sprint_acceleration: f32,
+    pub sprint_speed: f32,
+    pub crouch_speed: f32,
+    pub crouch_height: f32, // share of the standing height left when crouched
+    pub jump_velocity: f32,
+    pub wall_jump_velocity: Vec2, // x is away from the wall
+    pub wall_jump_control_lock: u32, // ms after a wall jump before steering works again so holding towards the wall doesnt cancel it
+    pub coyote_time: u32, // ms after walking off a ledge that we can still jump
+    pub jump_buffer: u32, // ms a jump pressed just before landing is remembered for
+    pub ground_check_distance: f32,
+    pub wall_check_distance: f32
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        Self {
+            acceleration: 50.,
+            walk_speed: 350.,
+            sprint_acceleration: 80.,
+            sprint_speed: 550.,
+            crouch_speed: 150.,
+            crouch_height: 0.6,
+            jump_velocity: 700.,
+            wall_jump_velocity: vec2(450., 650.),
+            wall_jump_control_lock: 200,
+            coyote_time: 100,
+            jump_buffer: 150,
+            ground_check_distance: 4.,
+            wall_check_distance: 4.
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Copy, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub enum WallSide {
+    Left,
+    Right
+}
+
+impl WallSide {
+    /// Which way the wall is along x
+    pub fn direction(&self) -> f32 {
+        match self {
+            WallSide::Left => -1.,
+            WallSide::Right => 1.,
+        }
+    }
+}
+
+/// What the owner worked out about our surroundings this tick. Synced so everyone animates us the same
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Default)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct MovementState {
+    pub grounded: bool,
+    pub wall: Option<WallSide>, // only set while we are in the air
+    pub crouching: bool,
+    pub sprinting: bool,
+    pub left_ground_at: Option<Time>, // None while grounded or once the coyote jump has been used
+    pub jump_pressed_at: Option<Time>, // None once the buffered jump has been used
+    pub jumped_at: Option<Time>,
+    pub wall_jumped_at: Option<Time>
+}
+
+fn within(time: &Option<Time>, window: u32) -> bool {
+    time.as_ref().is_some_and(|time| time.elapsed().num_milliseconds() <= window.into())
+}
+
+impl MovementState {
+
+    pub fn jump_buffered(&self, settings: &MovementSettings) -> bool {
+        within(&self.jump_pressed_at, settings.jump_buffer)
+    }
+
+    pub fn in_coyote_time(&self, settings: &MovementSettings) -> bool {
+        within(&self.left_ground_at, settings.coyote_time)
+    }
+
+    pub fn steering_locked(&self, settings: &MovementSettings) -> bool {
+        within(&self.wall_jumped_at, settings.wall_jump_control_lock)
+    }
+
+    /// Refresh grounded and wall from what the probes found
+    pub fn update_contacts(&mut self, space: &Space, body: SyncRigidBodyHandle, half_extents: Vec2, ignored: &[RigidBodyHandle], settings: &MovementSettings) {
+
+        let just_jumped = within(&self.jumped_at, JUMP_GROUND_GRACE);
+
+        let grounded = !just_jumped && probe(
+            space,
+            body,
+            vec2(half_extents.x - PROBE_INSET, half_extents.y),
+            vec2(0., -1.),
+            settings.ground_check_distance,
+            ignored
+        );
+
+        // walked off a ledge, start the coyote timer. jumping clears it so it cant be used twice
+        if self.grounded && !grounded && !just_jumped {
+            self.left_ground_at = Some(Time::now());
+        }
+
+        if grounded {
+            self.left_ground_at = None;
+        }
+
+        self.grounded = grounded;
+
+        let wall_half_extents = vec2(half_extents.x, half_extents.y - PROBE_INSET);
+
+        self.wall = match grounded {
+            true => None,
+            false => [WallSide::Left, WallSide::Right].into_iter().find(|side| {
+                probe(space, body, wall_half_extents, vec2(side.direction(), 0.), settings.wall_check_distance, ignored)
+            }),
+        };
+    }
+}
+
+fn ignore_parents(ignored: &[RigidBodyHandle]) -> impl Fn(ColliderHandle, &Collider) -> bool + '_ {
+    |_, collider| !matches!(collider.parent(), Some(parent) if ignored.contains(&parent))
+}
+
+/// Whether a box the size of our body would hit something moving `distance` along `direction` from where the body is
+pub fn probe(space: &Space, body: SyncRigidBodyHandle, half_extents: Vec2, direction: Vec2, distance: f32, ignored: &[RigidBodyHandle]) -> bool {
+
+    let position = space.sync_rigid_body_set.get_sync(body).unwrap().position();
+
+    let shape = Cuboid::new(vector![half_extents.x, half_extents.y]);
+
+    let predicate = ignore_parents(ignored);
+
+    space.query_pipeline.cast_shape(
+        &space.sync_rigid_body_set.rigid_body_set,
+        &space.sync_collider_set.collider_set,
+        position,
+        &vector![direction.x, direction.y],
+        &shape,
+        ShapeCastOptions::with_max_time_of_impact(distance),
+        QueryFilter::default().exclude_sensors().predicate(&predicate)
+    ).is_some()
+}
+
+/// Whether a box would fit at a position without overlapping anything, like when standing up from a crouch
+pub fn fits(space: &Space, position: &Isometry<f32>, half_extents: Vec2, ignored: &[RigidBodyHandle]) -> bool {
+
+    let shape = Cuboid::new(vector![half_extents.x, half_extents.y]);
+
+    let predicate = ignore_parents(ignored);
+
+    space.query_pipeline.intersection_with_shape(
+        &space.sync_rigid_body_set.rigid_body_set,
+        &space.sync_collider_set.collider_set,
+        position,
+        &shape,
+        QueryFilter::default().exclude_sensors().predicate(&predicate)
+    ).is_none()
+}
@@ -1,13 +1,12 @@
-use std::{collections::HashSet, f32::consts::PI, time::Instant};
+use std::{collections::{HashMap, HashSet}, f32::consts::PI, time::Instant};
 
 use chrono::TimeDelta;
 use diff::Diff;
-use gamelibrary::{animation::TrackedFrames, arenaiter::SyncArenaIterator, collider_top_left_pos, current_unix_millis, get_angle_between_rapier_points, get_angle_to_mouse, log, rapier_mouse_world_pos, rapier_to_macroquad, sound::soundmanager::{SoundHandle, SoundManager}, space::{Space, SyncColliderHandle, SyncImpulseJointHandle, SyncRigidBodyHandle}, swapiter::SwapIter, sync_arena::{Index, SyncArena}, texture_loader::TextureLoader, traits::HasPhysics, uuid_u32};
-use gilrs::Gamepad;
-use macroquad::{color::{GREEN, WHITE}, input::{is_key_down, is_key_released, is_mouse_button_down, is_mouse_button_released, KeyCode}, math::{vec2, Rect, Vec2}, shapes::draw_rectangle, time::get_frame_time};
+use gamelibrary::{arenaiter::SyncArenaIterator, collider_top_left_pos, current_unix_millis, get_angle_between_rapier_points, log, rapier_mouse_world_pos, rapier_to_macroquad, sound::soundmanager::{SoundHandle, SoundManager}, space::{Space, SyncColliderHandle, SyncImpulseJointHandle, SyncRigidBodyHandle}, swapiter::SwapIter, sync_arena::{Index, SyncArena}, texture_loader::TextureLoader, time::Time, traits::HasPhysics, uuid_u32};
+use macroquad::{color::{Color, GRAY, GREEN, SKYBLUE, WHITE}, input::{is_key_released, is_mouse_button_released, KeyCode}, math::{vec2, Rect, Vec2}, shapes::draw_rectangle, text::{draw_text, measure_text}, time::get_frame_time, window::screen_height};
 use nalgebra::vector;
 use parry2d::math::Rotation;
-use rapier2d::{crossbeam::epoch::Pointable, prelude::{Group, ImpulseJointHandle, InteractionGroups, RevoluteJointBuilder, RigidBody}};
+use rapier2d::{crossbeam::epoch::Pointable, prelude::{Group, ImpulseJointHandle, InteractionGroups, RevoluteJointBuilder, RigidBodyHandle, SharedShape}};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "3d-audio")]
@@ -16,9 +15,9 @@ use gamelibrary::sound::backends::ears::EarsSoundManager as SelectedSoundManager
 #[cfg(not(feature = "3d-audio"))]
 use gamelibrary::sound::backends::macroquad::MacroquadSoundManager as SelectedSoundManager;
 
-use crate::{blood::Blood, brick::Brick, bullet_trail::BulletTrail, collider_groups::{BODY_PART_GROUP, DETACHED_BODY_PART_GROUP}, damage_number::DamageNumber, enemy::Enemy, level::Level, pistol::Pistol, player, portal_bullet::PortalBullet, shotgun::Shotgun, structure::Structure, teleporter::Teleporter, weapon::BulletImpactData, TickContext};
+use crate::{bindings::{Action, Bindings}, blood::Blood, dirty::Dirty, cosmetics::Tint, brick::Brick, bullet_trail::BulletTrail, collider_groups::{BODY_PART_GROUP, DETACHED_BODY_PART_GROUP}, damage_number::DamageNumber, enemy::Enemy, game_mode::CombatEvent, level::Level, pistol::Pistol, player, portal_bullet::PortalBullet, sandbox::SandboxData, shop::EconomyRequest, shotgun::Shotgun, spawn_point::living_enemy_positions, structure::Structure, team::Team, teleporter::Teleporter, weapon::BulletImpactData, TickContext};
 
-use super::body_part::BodyPart;
+use super::{animation::{AnimationEvent, AnimationInputs, Animator}, body_part::BodyPart, movement::{fits, MovementSettings, MovementState, STANDING_HALF_EXTENTS}, ragdoll::{Limbs, Ragdoll, limp_interaction_groups}};
 
 #[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
 #[diff(attr(
@@ -42,7 +41,12 @@ pub enum PlayerWeapon {
 pub struct WeaponTickParameters<'a> {
     pub players: &'a mut SyncArena<Player>,
     pub enemies: &'a mut SyncArena<Enemy>,
-    pub structures: &'a mut SyncArena<Structure>
+    pub structures: &'a mut Dirty<SyncArena<Structure>>,
+    pub shooter_team: Option<Team>,
+    pub friendly_fire: bool,
+    pub can_fire: bool, // false while the holder is dead
+    pub infinite_ammo: bool, // sandbox only
+    pub hits: Vec<Hit> // players we shot this tick. the id is filled in when they are queued for the host
 }
 
 impl PlayerWeapon {
@@ -89,6 +93,13 @@ impl PlayerWeapon {
         }
     }
 
+    pub fn refill_ammo(&mut self, magazines: u32) {
+        match self {
+            PlayerWeapon::Shotgun(shotgun) => shotgun.refill_ammo(magazines),
+            PlayerWeapon::Pistol(pistol) => pistol.refill_ammo(magazines),
+        }
+    }
+
     pub fn set_facing(&mut self, facing: Facing) {
         match self {
             PlayerWeapon::Shotgun(shotgun) => shotgun.set_facing(facing),
@@ -96,6 +107,62 @@ impl PlayerWeapon {
         }
     }
 
+    pub fn name(&self) -> &String {
+        match self {
+            PlayerWeapon::Shotgun(shotgun) => shotgun.name(),
+            PlayerWeapon::Pistol(pistol) => pistol.name(),
+        }
+    }
+
+    pub fn set_owner(&mut self, owner: String) {
+        match self {
+            PlayerWeapon::Shotgun(shotgun) => shotgun.set_owner(owner),
+            PlayerWeapon::Pistol(pistol) => pistol.set_owner(owner),
+        }
+    }
+
+    pub fn collider(&self) -> SyncColliderHandle {
+        match self {
+            PlayerWeapon::Shotgun(shotgun) => shotgun.collider(),
+            PlayerWeapon::Pistol(pistol) => pistol.collider(),
+        }
+    }
+
+    pub fn attach(&mut self, space: &mut Space, player_rigid_body_handle: SyncRigidBodyHandle) {
+        match self {
+            PlayerWeapon::Shotgun(shotgun) => shotgun.attach(space, player_rigid_body_handle),
+            PlayerWeapon::Pistol(pistol) => pistol.attach(space, player_rigid_body_handle),
+        }
+    }
+
+    pub fn detach(&mut self, space: &mut Space, velocity: Vec2) {
+        match self {
+            PlayerWeapon::Shotgun(shotgun) => shotgun.detach(space, velocity),
+            PlayerWeapon::Pistol(pistol) => pistol.detach(space, velocity),
+        }
+    }
+
+    pub fn can_pick_up(&self) -> bool {
+        match self {
+            PlayerWeapon::Shotgun(shotgun) => shotgun.can_pick_up(),
+            PlayerWeapon::Pistol(pistol) => pistol.can_pick_up(),
+        }
+    }
+
+    pub fn last_fired(&self) -> Time {
+        match self {
+            PlayerWeapon::Shotgun(shotgun) => shotgun.last_fired(),
+            PlayerWeapon::Pistol(pistol) => pistol.last_fired(),
+        }
+    }
+
+    pub fn reloading(&self) -> bool {
+        match self {
+            PlayerWeapon::Shotgun(shotgun) => shotgun.reloading(),
+            PlayerWeapon::Pistol(pistol) => pistol.reloading(),
+        }
+    }
+
     pub async fn draw(&self, space: &Space, textures: &mut TextureLoader, flip_x: bool, flip_y: bool) {
         match self {
             PlayerWeapon::Shotgun(shotgun) => shotgun.draw(space, textures, flip_x, flip_y).await,
@@ -111,6 +178,29 @@ impl PlayerWeapon {
     }
 }
 
+// everyone can build this many structures before they have to buy more
+pub const STARTING_MATERIALS: u32 = 10;
+
+pub const MAX_HEALTH: u32 = 100;
+pub const MAX_ARMOR: u32 = 100;
+
+// the share of each hit armor soaks up while there is any left
+const ARMOR_ABSORPTION: f32 = 0.6;
+
+// regeneration kicks in after going this many ms without getting hurt, then heals a point every interval
+const REGEN_DELAY: i64 = 5000;
+const REGEN_INTERVAL: i64 = 100;
+
+// the held weapon plus whatever is stowed
+pub const INVENTORY_SLOTS: usize = 3;
+
+// how hard dropped weapons get thrown towards where we are aiming
+const THROW_SPEED: f32 = 800.;
+
+// aim assist only grabs targets this close and within this many radians of where the stick points
+const AIM_ASSIST_RANGE: f32 = 1200.;
+const AIM_ASSIST_ANGLE: f32 = 0.25;
+
 #[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
 #[diff(attr(
     #[derive(Serialize, Deserialize)]
@@ -125,24 +215,129 @@ pub struct Player {
     pub selected: bool,
     pub dragging: bool,
     pub drag_offset: Option<Vec2>,
-    pub max_speed: Vec2,
     weapon: Option<PlayerWeapon>,
-    //pub animation_handler: PlayerAnimationHandler,
-    pub walk_frame_progess: f32,
-    pub idle_frame_progress: f32,
+    #[serde(default)]
+    pub animator: Animator,
     pub facing: Facing,
     pub sound: SoundHandle,
     pub head_joint_handle: Option<SyncImpulseJointHandle>,
     pub teleporter_destination: Option<SyncRigidBodyHandle>,
-    pub money: u32,
     #[serde(default)]
-    pub mouse_pos: Vec2
+    pub mouse_pos: Vec2,
+    #[serde(default)]
+    pub last_sync_timestamp: u64, // unix millis of the owner's last sync on the owner's clock
+    #[serde(default)]
+    pub sync_echoes: HashMap<String, u64>, // the last sync timestamp we saw from each other player, sent back so they can time the round trip on their own clock
+    #[serde(default)]
+    pub ready_for_wave: u32, // the wave we readied up for. only the owner writes this, the host reads it into the ready list
+    #[serde(default)]
+    pub god_mode: bool, // copied from the sandbox settings by the owner so the host skips us in damage
+    #[serde(default)]
+    pub team: Option<Team>,
+    #[serde(default)]
+    pub kills: u32,
+    #[serde(default)]
+    pub deaths: u32,
+    #[serde(default)]
+    pub assists: u32,
+    #[serde(default)]
+    pub damagers: Vec<String>, // the owners of everyone who has damaged us this life
+    #[serde(default)]
+    pub hits: Vec<Hit>, // hits we landed that the host hasnt applied yet. only the owner writes this
+    #[serde(default)]
+    pub last_hit_id: u64,
+    #[serde(default)]
+    pub death: Option<DeathState>, // None while we are alive
+    #[serde(default)]
+    pub economy_request: Option<EconomyRequest>, // what we asked the host to spend our money or materials on. only the owner writes this
+    #[serde(default)]
+    pub planks: u32, // planks we can still put on boats
+    #[serde(default)]
+    pub time_stop_charges: u32,
+    #[serde(default)]
+    pub stowed: Vec<PlayerWeapon>, // the rest of the inventory. they stay jointed to us but arent drawn and dont fire
+    #[serde(default)]
+    pub armor: u32,
+    #[serde(default)]
+    pub last_damaged: Time,
+    #[serde(default)]
+    pub last_regen: Time,
+    #[serde(default)]
+    pub limbs: Limbs,
+    #[serde(default)]
+    pub limp: bool, // whether our body has already been turned into a ragdoll this life
+    #[serde(default)]
+    pub display_name: String, // empty until the owner sends their profile
+    #[serde(default)]
+    pub tint: Option<Tint>, // picked on the character screen. the sprites themselves are synced through the body parts
+    #[serde(default)]
+    pub movement: MovementSettings,
+    #[serde(default)]
+    pub movement_state: MovementState
+}
+
+/// A shot that landed on another player. The shooter queues these on their own player and the host applies the damage,
+/// so health is only ever written in one place
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Hit {
+    pub id: u64,
+    pub victim: String, // the owner of the player we hit
+    pub amount: u32,
+    pub weapon: String,
+    pub shooter_pos: Vec2 // limbs get blown off away from here
+}
+
+/// How and when a player died. Drives the respawn timer and the death cam
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct DeathState {
+    pub died_at: Time,
+    pub killer: Option<String>, // the owner of whoever killed us. None if we died to something else
+    pub spectating: Option<String>, // the owner of the teammate the death cam is following once the killer is gone
+    #[serde(default)]
+    pub weapon: Option<String>, // what killed us, for the kill feed
+    #[serde(default)]
+    pub recorded: bool // the host has counted this death in the scores and the kill feed
+}
+
+impl DeathState {
+    pub fn new(killer: Option<String>, weapon: Option<String>) -> Self {
+        Self {
+            died_at: Time::now(),
+            killer,
+            spectating: None,
+            weapon,
+            recorded: false
+        }
+    }
+
+    /// Whether we have been dead for at least `respawn_delay` ms
+    pub fn can_respawn(&self, respawn_delay: u32) -> bool {
+        self.died_at.elapsed().num_milliseconds() >= respawn_delay.into()
+    }
+
+    /// The number of whole seconds until we respawn
+    pub fn respawn_seconds(&self, respawn_delay: u32) -> i64 {
+        let remaining_millis = i64::from(respawn_delay) - self.died_at.elapsed().num_milliseconds();
+
+        (remaining_millis.max(0) + 999) / 1000
+    }
 }
 
 impl Player {
 
     pub fn move_camera(&mut self, camera_rect: &mut Rect, space: &Space) {
-        let position = space.sync_rigid_body_set.get_sync(self.body.body_handle).unwrap().translation();
+        Player::follow_with_camera(camera_rect, space, self.body.body_handle);
+    }
+
+    /// Scroll the camera so the body stays on screen
+    pub fn follow_with_camera(camera_rect: &mut Rect, space: &Space, body_handle: SyncRigidBodyHandle) {
+        let position = space.sync_rigid_body_set.get_sync(body_handle).unwrap().translation();
 
         let macroquad_position = rapier_to_macroquad(&vec2(position.x, position.y));
 
@@ -197,24 +392,48 @@ impl Player {
             true
         );
 
-        match self.weapon {
-            Some(weapon) => {
-                space.sync_rigid_body_set.remove_sync(
-                    weapon.rigid_body(), 
-                    &mut space.island_manager, 
-                    &mut space.sync_collider_set, 
-                    &mut space.sync_impulse_joint_set, 
-                    &mut space.multibody_joint_set, 
-                    true
-                );
-            },
-            None => {},
+        self.limbs.despawn(space);
+
+        for weapon in self.weapon.into_iter().chain(self.stowed) {
+            space.sync_rigid_body_set.remove_sync(
+                weapon.rigid_body(), 
+                &mut space.island_manager, 
+                &mut space.sync_collider_set, 
+                &mut space.sync_impulse_joint_set, 
+                &mut space.multibody_joint_set, 
+                true
+            );
         }
 
 
     } 
+
+    /// Get rid of our weapons but leave the body lying where we died
+    pub fn into_ragdoll(self, space: &mut Space) -> Ragdoll {
+
+        for weapon in self.weapon.into_iter().chain(self.stowed) {
+            space.sync_rigid_body_set.remove_sync(
+                weapon.rigid_body(), 
+                &mut space.island_manager, 
+                &mut space.sync_collider_set, 
+                &mut space.sync_impulse_joint_set, 
+                &mut space.multibody_joint_set, 
+                true
+            );
+        }
+
+        Ragdoll {
+            head: self.head,
+            body: self.body,
+            limbs: self.limbs,
+            facing: self.facing,
+            tint: self.tint,
+            died_at: self.death.map(|death| death.died_at).unwrap_or(Time::now()),
+            owner: self.owner
+        }
+    }
     
-    pub fn spawn(players: &mut SyncArena<Player>, space: &mut Space, owner: String, position: &Vec2, textures: &mut TextureLoader) {
+    pub fn spawn(players: &mut SyncArena<Player>, space: &mut Space, owner: String, position: &Vec2, textures: &mut TextureLoader) -> Index {
 
         let cat_head = BodyPart::new(
             "assets/cat/head.png".to_string(), 
@@ -257,8 +476,9 @@ impl Player {
             true
         );
 
-        let pistol = Pistol::new(space, *position, owner.clone(), Some(cat_body.body_handle), textures, Facing::Right);
-        //let pistol = Shotgun::new(space, *position, owner.clone(), Some(cat_body.body_handle), textures);
+        let limbs = Limbs::new(&cat_body, *position, space, textures, owner.clone());
+
+        let weapon = Player::default_loadout(space, *position, owner.clone(), cat_body.body_handle, textures);
 
         let sound = SoundHandle::new("assets/sounds/brick_land.wav", [0.,0.,0.]);
 
@@ -272,22 +492,238 @@ impl Player {
                 selected: false, 
                 dragging: false,
                 drag_offset: None,
-                max_speed: vec2(350., 80.),
-                weapon: Some(pistol.into()),
-                //animation_handler: PlayerAnimationHandler::new(PlayerAnimationState::Walking),
-                walk_frame_progess: 0.,
-                idle_frame_progress: 0.,
+                weapon: Some(weapon),
+                animator: Animator::default(),
                 facing: Facing::Right,
                 sound,
                 head_joint_handle: Some(head_joint_handle),
                 teleporter_destination: None,
-                health: 100,
-                money: 0,
-                mouse_pos: Vec2::ZERO
+                health: MAX_HEALTH,
+                mouse_pos: Vec2::ZERO,
+                last_sync_timestamp: 0,
+                sync_echoes: HashMap::new(),
+                ready_for_wave: 0,
+                god_mode: false,
+                team: None,
+                kills: 0,
+                deaths: 0,
+                assists: 0,
+                damagers: Vec::new(),
+                hits: Vec::new(),
+                last_hit_id: 0,
+                death: None,
+                economy_request: None,
+                planks: 0,
+                time_stop_charges: 0,
+                stowed: Vec::new(),
+                armor: 0,
+                last_damaged: Time::new(0),
+                last_regen: Time::new(0),
+                limbs,
+                limp: false,
+                display_name: String::new(),
+                tint: None,
+                movement: MovementSettings::default(),
+                movement_state: MovementState::default()
             }
+        )
+    }
+
+    /// The weapon everyone spawns and respawns with
+    pub fn default_loadout(space: &mut Space, position: Vec2, owner: String, body_handle: SyncRigidBodyHandle, textures: &mut TextureLoader) -> PlayerWeapon {
+        Pistol::new(space, position, owner, Some(body_handle), textures, Facing::Right).into()
+        //Shotgun::new(space, position, owner, Some(body_handle), textures).into()
+    }
+
+    /// The body the death cam should follow. Our killer while they are alive, otherwise a living teammate.
+    /// Clicking cycles through teammates
+    pub fn death_camera_target(&mut self, players: &SyncArena<Player>, bindings: &Bindings) -> Option<SyncRigidBodyHandle> {
+
+        let team = self.team;
+
+        let death = self.death.as_mut()?;
+
+        if let Some(killer) = &death.killer {
+            if let Some((_, killer)) = players.iter().find(|(_, player)| player.owner == *killer && player.health > 0) {
+                return Some(killer.body.body_handle);
+            }
+        }
+
+        // everyone counts as a teammate in free for all
+        let teammates: Vec<&Player> = players.iter()
+            .map(|(_, player)| player)
+            .filter(|player| player.health > 0 && (team.is_none() || player.team == team))
+            .collect();
+
+        if teammates.is_empty() {
+            death.spectating = None;
+
+            return None;
+        }
+
+        let current = teammates.iter().position(|player| Some(&player.owner) == death.spectating.as_ref());
+
+        let next = match current {
+            Some(current) if bindings.released(Action::Fire) => (current + 1) % teammates.len(),
+            Some(current) => current,
+            None => 0,
+        };
+
+        death.spectating = Some(teammates[next].owner.clone());
+
+        Some(teammates[next].body.body_handle)
+    }
+
+
+    /// What everyone else sees us as. Players who havent picked a name get one made from their uuid
+    pub fn name(&self) -> String {
+        match self.display_name.is_empty() {
+            true => format!("Player {}", self.owner.chars().take(4).collect::<String>()),
+            false => self.display_name.clone(),
+        }
+    }
+
+    /// Copy our local profile onto the synced player. Only writes when something changed so we arent cloning strings every tick
+    pub fn apply_profile(&mut self, ctx: &TickContext) {
+        if self.display_name != ctx.profile.display_name {
+            self.display_name = ctx.profile.display_name.clone();
+        }
+
+        let head_sprite = ctx.cosmetics.head_sprite(&ctx.profile.head);
+        let body_sprite = ctx.cosmetics.body_sprite(&ctx.profile.body);
+        let tint = ctx.cosmetics.tint(&ctx.profile.tint);
+
+        if self.head.sprite_path != head_sprite {
+            self.head.sprite_path = head_sprite.to_string();
+        }
+
+        if self.body.sprite_path != body_sprite {
+            self.body.sprite_path = body_sprite.to_string();
+        }
+
+        if self.tint != tint {
+            self.tint = tint;
+        }
+    }
+
+    /// What to multiply our sprites by. Team colors go on top of whatever tint we picked so teams stay readable
+    pub fn tint_color(&self) -> Option<Color> {
+        match (self.team, self.tint) {
+            (Some(team), Some(tint)) => {
+                let team = team.tint();
+                let tint = tint.color();
+
+                Some(Color::new(team.r * tint.r, team.g * tint.g, team.b * tint.b, 1.))
+            },
+            (Some(team), None) => Some(team.tint()),
+            (None, Some(tint)) => Some(tint.color()),
+            (None, None) => None,
+        }
+    }
+
+    /// Carry over everything that should survive a respawn
+    pub fn inherit_stats(&mut self, previous: &Player) {
+        self.display_name = previous.display_name.clone();
+        self.tint = previous.tint;
+        self.movement = previous.movement.clone();
+        self.team = previous.team;
+        self.kills = previous.kills;
+        self.deaths = previous.deaths;
+        self.assists = previous.assists;
+        self.ready_for_wave = previous.ready_for_wave;
+        self.economy_request = previous.economy_request.clone();
+        self.planks = previous.planks;
+        self.time_stop_charges = previous.time_stop_charges;
+        // hits we landed just before dying still count
+        self.hits = previous.hits.clone();
+        self.last_hit_id = previous.last_hit_id;
+    }
+
+    /// Every rigid body that makes up the player, weapon included
+    pub fn body_handles(&self) -> Vec<SyncRigidBodyHandle> {
+
+        let mut body_handles = vec![self.head.body_handle, self.body.body_handle];
+
+        body_handles.extend(self.limbs.body_handles());
+
+        for weapon in self.weapon.iter().chain(&self.stowed) {
+            body_handles.push(weapon.rigid_body());
+        }
+
+        body_handles
+    }
+
+    pub fn has_free_slot(&self) -> bool {
+        usize::from(self.weapon.is_some()) + self.stowed.len() < INVENTORY_SLOTS
+    }
+
+    /// Put a weapon from the world into our inventory. It goes in our hands if they are empty
+    pub fn pick_up(&mut self, space: &mut Space, mut weapon: PlayerWeapon) {
+
+        weapon.attach(space, self.body.body_handle);
+        weapon.set_owner(self.owner.clone());
+        weapon.set_facing(self.facing.clone());
+
+        match self.weapon {
+            Some(_) => self.stowed.push(weapon),
+            None => self.weapon = Some(weapon),
+        }
+    }
+
+    /// Throw the weapon we are holding towards where we are aiming and take out the next one
+    pub fn drop_weapon(&mut self, space: &mut Space) -> Option<PlayerWeapon> {
+
+        let mut weapon = self.weapon.take()?;
+
+        if !self.stowed.is_empty() {
+            self.weapon = Some(self.stowed.remove(0));
+        }
+
+        let body = space.sync_rigid_body_set.get_sync(self.body.body_handle).unwrap();
+
+        let body_pos = vec2(body.translation().x, body.translation().y);
+        let body_velocity = vec2(body.linvel().x, body.linvel().y);
+
+        let direction = (self.mouse_pos - body_pos).normalize_or_zero();
+
+        weapon.detach(space, body_velocity + (direction * THROW_SPEED));
+
+        self.sound = SoundHandle::new("assets/sounds/brick_land.wav", [0., 0., 0.]);
+
+        self.sound.play();
+
+        Some(weapon)
+    }
+
+    /// Put a new weapon in our hands. If the inventory is full the one we were holding is removed from the world
+    pub fn equip_weapon(&mut self, space: &mut Space, weapon: PlayerWeapon) {
+
+        let old_weapon = match self.weapon.replace(weapon) {
+            Some(old_weapon) => old_weapon,
+            None => return,
+        };
+
+        if self.has_free_slot() {
+            self.stowed.push(old_weapon);
+
+            return;
+        }
+
+        space.sync_rigid_body_set.remove_sync(
+            old_weapon.rigid_body(), 
+            &mut space.island_manager, 
+            &mut space.sync_collider_set, 
+            &mut space.sync_impulse_joint_set, 
+            &mut space.multibody_joint_set, 
+            true
         );
     }
 
+    pub fn refill_ammo(&mut self, magazines: u32) {
+        if let Some(weapon) = &mut self.weapon {
+            weapon.refill_ammo(magazines);
+        }
+    }
 
     pub async fn sync_sound(&mut self, ctx: &mut TickContext<'_>) {
         if let Some(weapon) = &mut self.weapon {
@@ -295,30 +731,22 @@ impl Player {
         }
     }
 
-    pub fn change_weapon(
-        &mut self, 
-        space: &mut Space,
-        textures: &mut TextureLoader
-    ) {
-        if !is_key_released(KeyCode::Q) {
+    /// Cycle to the next weapon in the inventory. The one we were holding goes to the back
+    pub fn change_weapon(&mut self, bindings: &Bindings) {
+
+        if !bindings.released(Action::SwitchWeapon) || self.stowed.is_empty() {
             return;
         }
 
-        let rigid_body_handle = self.rigid_body_handle().clone();
-        let owner = self.owner.clone();
+        if let Some(held) = self.weapon.take() {
+            self.stowed.push(held);
+        }
+
+        let mut next = self.stowed.remove(0);
 
-        if let Some(weapon) = &mut self.weapon {
-            let new_weapon = match weapon {
-                PlayerWeapon::Shotgun(_shotgun) => {
-                    Pistol::new(space, vec2(0., 0.), self.owner.clone(), Some(rigid_body_handle.clone()), textures, self.facing.clone()).into()
-                },
-                PlayerWeapon::Pistol(_pistol) => {
-                    Shotgun::new(space, vec2(0., 0.), self.owner.clone(), Some(rigid_body_handle.clone()), textures, self.facing.clone()).into()
-                },  
-            };
+        next.set_facing(self.facing.clone());
 
-            *weapon = new_weapon;
-        }
+        self.weapon = Some(next);
     }
 
     pub fn upright(&mut self, space: &mut Space, ctx: &mut TickContext) {
@@ -340,10 +768,56 @@ impl Player {
     pub fn update_mouse_pos(&mut self, camera_rect: &Rect) {
         self.mouse_pos = rapier_mouse_world_pos(camera_rect);
     }
+    /// Move our aim point to where the right stick points. Aim assist pulls it onto the closest target near that direction
+    pub fn aim_with_gamepad(&mut self, space: &Space, players: &SyncArena<Player>, enemies: &SyncArena<Enemy>, ctx: &TickContext) {
+
+        // without a weapon the stick aims from our head
+        let aim_origin = match &self.weapon {
+            Some(weapon) => weapon.rigid_body(),
+            None => self.head.body_handle,
+        };
+
+        let weapon_pos = space.sync_rigid_body_set.get_sync(aim_origin).unwrap().translation();
+
+        let weapon_pos = vec2(weapon_pos.x, weapon_pos.y);
+
+        let aim_point = match ctx.bindings.gamepad.aim_point(weapon_pos) {
+            Some(aim_point) => aim_point,
+            None => return,
+        };
+
+        self.mouse_pos = aim_point;
+
+        if !ctx.bindings.aim_assist {
+            return;
+        }
+
+        let aim_direction = (aim_point - weapon_pos).normalize();
+
+        // teammates arent targets
+        let mut targets: Vec<Vec2> = players.iter()
+            .filter(|(_, player)| player.owner != self.owner && player.health > 0)
+            .filter(|(_, player)| player.team.is_none() || player.team != self.team)
+            .filter_map(|(_, player)| space.sync_rigid_body_set.get_sync(player.body.body_handle))
+            .map(|body| vec2(body.translation().x, body.translation().y))
+            .collect();
+
+        targets.extend(living_enemy_positions(space, enemies));
+
+        let target = targets.into_iter()
+            .filter(|target| target.distance(weapon_pos) < AIM_ASSIST_RANGE)
+            .filter(|target| (*target - weapon_pos).normalize().dot(aim_direction) > AIM_ASSIST_ANGLE.cos())
+            .min_by(|a, b| a.distance(weapon_pos).total_cmp(&b.distance(weapon_pos)));
+
+        if let Some(target) = target {
+            self.mouse_pos = target;
+        }
+    }
+
     pub fn owner_tick(
         &mut self, 
         space: &mut Space, 
-        structures: &mut SyncArena<Structure>, 
+        structures: &mut Dirty<SyncArena<Structure>>, 
         bricks: &mut Vec<Brick>, 
         teleporters: &mut Vec<Teleporter>, 
         hit_markers: &mut Vec<Vec2>, 
@@ -352,37 +826,70 @@ impl Player {
         enemies: &mut SyncArena<Enemy>,
         damage_numbers: &mut HashSet<DamageNumber>,
         bullet_trails: &mut SyncArena<BulletTrail>,
-        blood: &mut HashSet<Blood>
+        blood: &mut HashSet<Blood>,
+        friendly_fire: bool,
+        sandbox: Option<&SandboxData>,
+        applied_hits: &HashMap<String, u64>
     ) {
         //self.launch_brick(level, ctx);
-        self.unlock_rotations(space);
+        self.apply_profile(ctx);
+        self.unlock_rotations(space, ctx.bindings);
         //self.upright(space, ctx);
-        self.change_weapon(space, ctx.textures);
-        self.control(space, ctx);
-        self.move_camera(ctx.camera_rect, space);
+        self.change_weapon(ctx.bindings);
+
+        // stowed weapons still hang off our body so we simulate them too
+        for weapon in &self.stowed {
+            ctx.owned_rigid_bodies.push(weapon.rigid_body());
+            ctx.owned_colliders.push(weapon.collider());
+        }
+
+        // anything that kills us without a bullet still needs a death state
+        if self.health == 0 && self.death.is_none() {
+            self.death = Some(DeathState::new(None, None));
+        }
+
+        match self.death.is_some() {
+            true => {
+                match self.death_camera_target(players, ctx.bindings) {
+                    Some(target) => Player::follow_with_camera(ctx.camera_rect, space, target),
+                    None => self.move_camera(ctx.camera_rect, space),
+                }
+            },
+            false => {
+                self.control(space, ctx);
+                self.move_camera(ctx.camera_rect, space);
+            },
+        }
         self.update_selected(space, &ctx.camera_rect);
         self.update_is_dragging(space, &ctx.camera_rect);
         self.update_drag(space, &ctx.camera_rect);
         self.update_mouse_pos(ctx.camera_rect);
+        self.aim_with_gamepad(space, players, enemies, ctx);
         // this needs to be fixed so moving structures dont change owners, it causes it to glitch because conflicting updates
         
         let then =web_time::Instant::now();
         self.own_nearby_structures(space, structures, ctx, players);
 
-        //self.update_walk_animation(space);
-        //self.update_idle_animation(space);
-        self.change_facing_direction(&space);
+        self.change_facing_direction(&space, ctx.bindings);
         //self.delete_structure(structures, space, ctx);
-        self.angle_head_to_mouse(space, ctx.camera_rect);
+        self.angle_head_to_mouse(space);
         self.place_teleporter(ctx, teleporters, space);
         //self.launch_brick(bricks, space, ctx);
         
-        self.detach_head_if_dead(space);
+        self.go_limp_if_dead(space);
+
+        self.god_mode = sandbox.is_some_and(|sandbox| sandbox.god_mode);
 
         let mut weapon_tick_parameters = WeaponTickParameters {
             players, 
             enemies,
-            structures
+            structures,
+            shooter_team: self.team,
+            friendly_fire,
+            // the dead cant shoot. clicking cycles the death cam instead
+            can_fire: self.death.is_none(),
+            infinite_ammo: sandbox.is_some_and(|sandbox| sandbox.infinite_ammo),
+            hits: Vec::new()
         };
 
         if let Some(weapon) = &mut self.weapon {
@@ -397,22 +904,38 @@ impl Player {
             );
         }  
 
-        
-
-        if is_key_released(KeyCode::N) {
-
-            self.sound = SoundHandle::new("assets/sounds/brick_land.wav", [0., 0., 0.]);
-            
-            self.sound.play();
-        }
         //self.update_hitbox_size(space, ctx);
 
         self.head.tick(space, ctx);
         self.body.tick(space, ctx);
+        self.limbs.tick(space, ctx);
+
+        self.queue_hits(weapon_tick_parameters.hits, applied_hits);
     }
 
-    pub fn unlock_rotations(&mut self, space: &mut Space) {
-        if is_key_released(KeyCode::F) {
+    /// Send our new hits to the host and forget the ones it has already applied
+    pub fn queue_hits(&mut self, new_hits: Vec<Hit>, applied_hits: &HashMap<String, u64>) {
+
+        let applied_id = applied_hits.get(&self.owner).copied().unwrap_or(0);
+
+        self.hits.retain(|hit| hit.id > applied_id);
+
+        for mut hit in new_hits {
+            self.last_hit_id += 1;
+
+            hit.id = self.last_hit_id;
+
+            self.hits.push(hit);
+        }
+    }
+
+    pub fn unlock_rotations(&mut self, space: &mut Space, bindings: &Bindings) {
+        // ragdolls stay floppy
+        if self.limp {
+            return;
+        }
+
+        if bindings.released(Action::UnlockRotations) {
 
             let body = space.sync_rigid_body_set.get_sync_mut(self.body.body_handle).unwrap();
 
@@ -428,8 +951,9 @@ impl Player {
         }
     }
 
+    /// How much damage a bullet does to us. The host applies it through `damage` once the shooter reports the hit
     #[inline]
-    pub fn handle_bullet_impact(&mut self, space: &Space, bullet_impact: BulletImpactData) {
+    pub fn bullet_damage(&self, space: &Space, bullet_impact: &BulletImpactData) -> u32 {
 
         let our_pos = space.sync_collider_set.get_sync(bullet_impact.impacted_collider).unwrap().position().translation;
 
@@ -437,26 +961,119 @@ impl Player {
 
         let fall_off_multiplier = (-0.01 * distance.norm()).exp();
 
-        if bullet_impact.impacted_collider == self.body.collider_handle {
-
-            let damage = (50.0 * fall_off_multiplier).round() as u32;
+        let mut damage = 0;
 
-            self.health = self.health.saturating_sub(damage);
+        if bullet_impact.impacted_collider == self.body.collider_handle {
+            damage += (50.0 * fall_off_multiplier).round() as u32;
         }
 
         // headshot
         if bullet_impact.impacted_collider == self.head.collider_handle {
+            damage += (100.0 * fall_off_multiplier).round() as u32;
+        }
+
+        damage
+    }
+
+    /// Every bit of damage a player takes goes through here, on the host. Armor soaks up its share first.
+    /// Reports what happened to `combat_events` for the mode. Returns true if this killed us
+    pub fn damage(&mut self, amount: u32, attacker: Option<&String>, weapon: Option<&String>, combat_events: &mut Vec<CombatEvent>) -> bool {
+
+        // already dead
+        if self.health == 0 {
+            return false;
+        }
+
+        // sandbox god mode, whatever the damage came from
+        if self.god_mode {
+            return false;
+        }
+
+        if let Some(attacker) = attacker {
+            if *attacker != self.owner && !self.damagers.contains(attacker) {
+                self.damagers.push(attacker.clone());
+            }
+        }
 
-            let damage = (100.0 * fall_off_multiplier).round() as u32;
+        if amount == 0 {
+            return false;
+        }
+
+        let absorbed = ((amount as f32 * ARMOR_ABSORPTION).round() as u32).min(self.armor);
+
+        // armor soaking up a hit still counts as damage dealt
+        let health_before = self.health + self.armor;
+
+        self.armor -= absorbed;
+        self.health = self.health.saturating_sub(amount - absorbed);
+
+        self.last_damaged = Time::now();
+
+        combat_events.push(
+            CombatEvent::Damage { attacker: attacker.cloned(), victim: self.owner.clone(), amount: health_before - (self.health + self.armor) }
+        );
+
+        if self.health == 0 {
+            self.death = Some(DeathState::new(attacker.cloned(), weapon.cloned()));
 
-            self.health = self.health.saturating_sub(damage);
+            combat_events.push(
+                CombatEvent::Kill { killer: attacker.cloned(), victim: self.owner.clone(), weapon: weapon.cloned() }
+            );
+
+            return true;
         }
+
+        false
+    }
+
+    /// Every bit of healing goes through here. The dead stay dead. Returns how much health we actually gained
+    pub fn heal(&mut self, amount: u32) -> u32 {
+
+        if self.health == 0 {
+            return 0;
+        }
+
+        let healed = amount.min(MAX_HEALTH.saturating_sub(self.health));
+
+        self.health += healed;
+
+        healed
+    }
+
+    /// Returns how much armor we actually gained
+    pub fn add_armor(&mut self, amount: u32) -> u32 {
+
+        if self.health == 0 {
+            return 0;
+        }
+
+        let added = amount.min(MAX_ARMOR.saturating_sub(self.armor));
+
+        self.armor += added;
+
+        added
+    }
+
+    /// Slowly heal once we have been out of the fight for a while. Only used in modes that turn it on
+    pub fn regenerate(&mut self) {
+
+        if self.health == 0 || self.health >= MAX_HEALTH {
+            return;
+        }
+
+        if self.last_damaged.elapsed().num_milliseconds() < REGEN_DELAY || self.last_regen.elapsed().num_milliseconds() < REGEN_INTERVAL {
+            return;
+        }
+
+        self.last_regen = Time::now();
+
+        self.heal(1);
     }
 
     pub fn tick(
         &mut self, 
         space: &mut Space, 
-        structures: &mut SyncArena<Structure>, 
+        structures: &mut Dirty<SyncArena<Structure>>, 
         bricks: &mut Vec<Brick>, 
         teleporters: &mut Vec<Teleporter>, 
         hit_markers: &mut Vec<Vec2>, 
@@ -465,24 +1082,72 @@ impl Player {
         enemies: &mut SyncArena<Enemy>,
         damage_numbers: &mut HashSet<DamageNumber>,
         bullet_trails: &mut SyncArena<BulletTrail>,
-        blood: &mut HashSet<Blood>
+        blood: &mut HashSet<Blood>,
+        friendly_fire: bool,
+        sandbox: Option<&SandboxData>,
+        applied_hits: &HashMap<String, u64>
     ) {
-        
+
         if self.owner == *ctx.uuid {
-            self.owner_tick(space, structures, bricks, teleporters, hit_markers, ctx, players, enemies, damage_numbers, bullet_trails, blood);
+            self.owner_tick(space, structures, bricks, teleporters, hit_markers, ctx, players, enemies, damage_numbers, bullet_trails, blood, friendly_fire, sandbox, applied_hits);
         }
 
         self.all_tick(space, ctx);
-        
     }
 
     pub fn all_tick(&mut self, space: &mut Space, ctx: &TickContext) {
         self.angle_weapon_to_mouse(space, ctx.camera_rect);
+        self.animate(space, ctx);
+    }
+
+    /// What the animation state machine needs, all from synced state so remote players animate the same as they do for their owner
+    pub fn animation_inputs(&self, space: &Space) -> AnimationInputs {
+
+        let velocity = space.sync_rigid_body_set.get_sync(self.body.body_handle).unwrap().linvel();
+
+        AnimationInputs {
+            speed: velocity.x,
+            grounded: self.movement_state.grounded,
+            last_fired: self.weapon.as_ref().map(|weapon| weapon.last_fired()),
+            reloading: self.weapon.as_ref().is_some_and(|weapon| weapon.reloading())
+        }
+    }
+
+    /// Step the animation. The owner poses the limbs since they simulate them, and plays the frame sounds which then sync to everyone else
+    pub fn animate(&mut self, space: &mut Space, ctx: &TickContext) {
+
+        // ragdolls arent animated
+        if self.limp {
+            return;
+        }
+
+        let inputs = self.animation_inputs(space);
+
+        let events = self.animator.tick(ctx.animations, &inputs);
+
+        if self.owner != *ctx.uuid {
+            return;
+        }
+
+        if let Some(pose) = self.animator.pose(ctx.animations) {
+            self.limbs.set_pose(space, pose, &self.facing);
+        }
+
+        for event in events {
+            let sound_path = match event {
+                AnimationEvent::Footstep => "assets/sounds/brick_land.wav",
+                AnimationEvent::ReloadClick => "assets/sounds/pistol_dry_fire.wav",
+            };
+
+            self.sound = SoundHandle::new(sound_path, [0., 0., 0.]);
+
+            self.sound.play();
+        }
     }
 
     pub fn place_teleporter(&mut self, ctx: &TickContext, teleporters: &mut Vec<Teleporter>, space: &mut Space) {
 
-        if !is_key_released(KeyCode::B) {
+        if !ctx.bindings.released(Action::PlaceTeleporter) {
             return
         }
 
@@ -522,20 +1187,29 @@ impl Player {
 
     pub fn angle_weapon_to_mouse(&mut self, space: &mut Space, camera_rect: &Rect) {
 
-        let shotgun_joint_handle = match self.weapon.as_ref().unwrap().player_joint_handle() {
+        // we might have dropped everything
+        let weapon = match &self.weapon {
+            Some(weapon) => weapon,
+            None => return,
+        };
+
+        let shotgun_joint_handle = match weapon.player_joint_handle() {
             Some(shotgun_joint_handle) => shotgun_joint_handle,
             None => return,
         };
 
-        let aim_angle_offset = self.weapon.as_ref().unwrap().aim_angle_offset();
+        let aim_angle_offset = weapon.aim_angle_offset();
+
+        let weapon_rigid_body = weapon.rigid_body();
 
         // lol
         let body_body = space.sync_rigid_body_set.get_sync_mut(self.body.body_handle).unwrap();
 
         let body_body_pos = Vec2::new(body_body.translation().x, body_body.translation().y);
 
-        let weapon_pos = space.sync_rigid_body_set.get_sync(self.weapon.as_ref().unwrap().rigid_body()).unwrap().translation();
+        let weapon_pos = space.sync_rigid_body_set.get_sync(weapon_rigid_body).unwrap().translation();
 
+        // mouse_pos is where the right stick points when aiming with a gamepad
         let angle_to_mouse = get_angle_between_rapier_points(Vec2::new(weapon_pos.x, weapon_pos.y), self.mouse_pos);
 
         let shotgun_joint = space.sync_impulse_joint_set.get_sync_mut(shotgun_joint_handle).unwrap();
@@ -570,7 +1244,13 @@ impl Player {
         return;
     }
 
-    pub fn angle_head_to_mouse(&mut self, space: &mut Space, camera_rect: &Rect) {
+    /// Point the head at `mouse_pos`, which is where the right stick points when aiming with a gamepad, same as the weapon
+    pub fn angle_head_to_mouse(&mut self, space: &mut Space) {
+
+        // the head motor would hold a dead head up
+        if self.limp {
+            return;
+        }
 
         let head_joint_handle = match self.head_joint_handle {
             Some(head_joint_handle) => head_joint_handle,
@@ -581,7 +1261,7 @@ impl Player {
 
         let head_body_pos = Vec2::new(head_body.translation().x, head_body.translation().y);
 
-        let angle_to_mouse = get_angle_to_mouse(head_body_pos, camera_rect);
+        let angle_to_mouse = get_angle_between_rapier_points(head_body_pos, self.mouse_pos);
 
         let head_joint = space.sync_impulse_joint_set.get_sync_mut(head_joint_handle).unwrap();
 
@@ -606,35 +1286,27 @@ impl Player {
 
     }
 
-    pub fn detach_head_if_dead(&mut self, space: &mut Space) {
+    /// Turn the whole body into a ragdoll once we die. Everything stays jointed together, the joint limits keep it looking like a cat
+    pub fn go_limp_if_dead(&mut self, space: &mut Space) {
 
-        let head_joint_handle = match self.head_joint_handle {
-            Some(head_joint_handle) => {
-                head_joint_handle
-            },
-            None => {
-                return;
-            },
-        };
-
-        if self.health <= 0 {
+        if self.death.is_none() || self.limp {
+            return;
+        }
 
-            //let head_joint = space.sync_impulse_joint_set.remove_sync(head_joint_handle, true);
+        self.limp = true;
 
-            //self.head_joint_handle = None;
+        space.sync_rigid_body_set.get_sync_mut(self.body.body_handle).unwrap().lock_rotations(false, true);
 
-            // let new_interaction_groups = InteractionGroups::none()
-            //     .with_memberships(DETACHED_BODY_PART_GROUP)
-            //     .with_filter(
-            //         Group::ALL
-            //             .difference(DETACHED_BODY_PART_GROUP)
-            //             .difference(BODY_PART_GROUP)
-            //     );
+        if let Some(head_joint_handle) = self.head_joint_handle {
+            let head_joint = space.sync_impulse_joint_set.get_sync_mut(head_joint_handle).unwrap();
 
-            
-            //space.sync_collider_set.get_sync_mut(self.head.collider_handle).unwrap().set_collision_groups(new_interaction_groups);
-            //space.sync_collider_set.get_sync_mut(self.body.collider_handle).unwrap().set_collision_groups(new_interaction_groups);
+            head_joint.data.as_revolute_mut().unwrap().set_motor_position(0., 0., 0.);
         }
+
+        space.sync_collider_set.get_sync_mut(self.head.collider_handle).unwrap().set_collision_groups(limp_interaction_groups());
+        space.sync_collider_set.get_sync_mut(self.body.collider_handle).unwrap().set_collision_groups(limp_interaction_groups());
+
+        self.limbs.go_limp(space);
     }
 
     // pub fn update_arm_angle(&mut self, space: &mut Space, camera_rect: &Rect) {
@@ -655,13 +1327,13 @@ impl Player {
     //     // need to restrict arm angle depending on facing
     // }
 
-    pub fn change_facing_direction(&mut self, space: &Space) {
+    pub fn change_facing_direction(&mut self, space: &Space, bindings: &Bindings) {
         let velocity = space.sync_rigid_body_set.get_sync(*self.rigid_body_handle()).unwrap().linvel();
 
 
         if velocity.x > 100. {
 
-            if !is_key_down(KeyCode::D) {
+            if !bindings.down(Action::MoveRight) {
                 return;
             }
 
@@ -675,7 +1347,7 @@ impl Player {
 
         if velocity.x < -100. {
 
-            if !is_key_down(KeyCode::A) {
+            if !bindings.down(Action::MoveLeft) {
                 return;
             }
 
@@ -686,24 +1358,6 @@ impl Player {
             };
         }
     }
-    // pub fn update_idle_animation(&mut self, space: &mut Space) {
-
-    //     let velocity = space.sync_rigid_body_set.get_sync(*self.rigid_body_handle()).unwrap().linvel();
-
-    //     if velocity.x == 0. {
-
-    //         self.idle_frame_progress += 5. * get_frame_time(); 
-            
-    //         //self.animation_handler.set_animation_state(PlayerAnimationState::Idle);
-
-    //         if self.idle_frame_progress > 1. {
-    //             self.animation_handler.next_frame(PlayerAnimationState::Idle);
-    //             self.idle_frame_progress = 0.;
-    //         }
-            
-    //     }
-
-    // }
     /// Update arm anchor pos depending on facing direction
     // pub fn update_arm_anchor_pos(&mut self, space: &mut Space) {
     //     let arm_anchor_pos = match self.facing {
@@ -732,39 +1386,13 @@ impl Player {
     //     right_arm_joint.set_local_anchor1(arm_anchor_pos);
         
     // }
-    // pub fn update_walk_animation(&mut self, space: &mut Space) {
-    //     let velocity = space.sync_rigid_body_set.get_sync(*self.rigid_body_handle()).unwrap().linvel();
-
-    //     if velocity.x.abs() > 0. {
-    //         self.walk_frame_progess += (velocity.x.abs() * get_frame_time()) / 20.;
-
-    //         self.animation_handler.set_animation_state(PlayerAnimationState::GunRun);
-    //     }
-
-    //     if self.walk_frame_progess > 1. {
-
-    //         // this all seems pretty stupid
-    //         let animation_state = self.animation_handler.get_animation_state();
-
-    //         match animation_state {
-    //             PlayerAnimationState::Walking => {},
-    //             PlayerAnimationState::GunRun => {},
-    //             _ => return
-    //         }
-
-    //         self.animation_handler.next_frame(animation_state);
-
-    //         self.walk_frame_progess = 0.;
-    //     }
-        
-    // }
-
     
-    pub fn own_nearby_structures(&mut self, space: &mut Space, structures: &mut SyncArena<Structure>, ctx: &mut TickContext, other_players: &mut SyncArena<Player>) {
+    pub fn own_nearby_structures(&mut self, space: &mut Space, structures: &mut Dirty<SyncArena<Structure>>, ctx: &mut TickContext, other_players: &mut SyncArena<Player>) {
         // take ownership of nearby structures to avoid network physics delay
 
+        let mut ownership_changes: Vec<(Index, String)> = Vec::new();
 
-        for (_, structure) in structures {
+        for (structure_index, structure) in structures.iter() {
 
             let structure_body = space.sync_rigid_body_set.get_sync(structure.rigid_body_handle).unwrap();
 
@@ -803,36 +1431,21 @@ impl Player {
 
             if structure.owner.clone().unwrap() != closest_owner {
                 
-
-                structure.owner = Some(closest_owner);
+                ownership_changes.push((structure_index, closest_owner));
 
             }
 
-            
-
-            
-
-            // // if the other body is within 100 units, we take ownership of it
-            // if body_distance.abs().magnitude() > 100. {
-            //     continue;
-            // }
-
-            // match &mut structure.owner {
-            //     Some(owner) => {
-
-            //         // take ownership if we dont already own it
-            //         if owner == ctx.uuid {
-            //             continue;    
-            //         }
-                        
-            //         *owner = ctx.uuid.clone();
+        }
 
-            //     }
-            //     None => {
-            //         structure.owner = Some(ctx.uuid.clone())
-            //     },
-            // }
+        if ownership_changes.is_empty() {
+            return;
+        }
 
+        // only borrowing mutably here keeps the structures from being diffed when nobody changed hands
+        for (structure_index, new_owner) in ownership_changes {
+            if let Some(structure) = structures.get_mut(&structure_index) {
+                structure.owner = Some(new_owner);
+            }
         }
     }
     pub fn fire_portal_gun(&mut self, _camera_rect: &Rect, _portal_bullets: &mut Vec<PortalBullet>) {
@@ -843,7 +1456,7 @@ impl Player {
     
     pub fn launch_brick(&mut self, bricks: &mut Vec<Brick>, space: &mut Space, ctx: &mut TickContext) {
 
-        if !is_mouse_button_down(macroquad::input::MouseButton::Left) {
+        if !ctx.bindings.down(Action::Fire) {
             return;
         }
         let (player_pos, player_rotation, player_velocity) = {
@@ -874,50 +1487,128 @@ impl Player {
 
     }
 
-    pub fn jump(&mut self, rigid_body: &mut RigidBody, gamepad: Option<Gamepad>) {
-        if is_key_down(KeyCode::Space) || gamepad.map_or(false, |gamepad| {gamepad.is_pressed(gilrs::Button::South)}) {
+    /// Every body that belongs to us, so the movement probes dont find our own head or weapon
+    pub fn own_local_bodies(&self, space: &Space) -> Vec<RigidBodyHandle> {
 
-            // dont allow if moving if falling or jumping
-            if rigid_body.linvel().y.abs() > 0.5 {
-                return
-            }
+        let mut bodies = vec![self.body.body_handle, self.head.body_handle];
 
-            // stop any y axis movement if going down
-            if rigid_body.linvel().y.is_sign_negative() {
-                rigid_body.set_linvel(
-                    vector![rigid_body.linvel().x, 0.],
-                    true
-                )
-            }
+        bodies.extend(self.limbs.body_handles());
+        bodies.extend(self.weapon.iter().chain(&self.stowed).map(|weapon| weapon.rigid_body()));
 
-            rigid_body.set_linvel(
-                vector![rigid_body.linvel().x, rigid_body.linvel().y + 700.],
-                true
-            );
+        bodies.into_iter().map(|body| space.sync_rigid_body_set.get_local_handle(body)).collect()
+    }
 
-            
+    /// Squash the body collider while crouch is held. We only stand back up if there is room above us
+    pub fn crouch(&mut self, space: &mut Space, bindings: &Bindings, ignored: &[RigidBodyHandle]) {
+
+        let wants_to_crouch = bindings.down(Action::Crouch);
+
+        if wants_to_crouch == self.movement_state.crouching {
+            return;
         }
+
+        let standing = STANDING_HALF_EXTENTS;
+        let crouched = vec2(standing.x, standing.y * self.movement.crouch_height);
+
+        // move the body by the difference so our feet stay where they are
+        let height_change = standing.y - crouched.y;
+
+        let (half_extents, shift) = match wants_to_crouch {
+            true => (crouched, -height_change),
+            false => (standing, height_change),
+        };
+
+        let mut position = *space.sync_rigid_body_set.get_sync(self.body.body_handle).unwrap().position();
+
+        position.translation.y += shift;
+
+        if !wants_to_crouch && !fits(space, &position, standing, ignored) {
+            return;
+        }
+
+        space.sync_collider_set.get_sync_mut(self.body.collider_handle).unwrap().set_shape(SharedShape::cuboid(half_extents.x, half_extents.y));
+
+        space.sync_rigid_body_set.get_sync_mut(self.body.body_handle).unwrap().set_translation(position.translation.vector, true);
+
+        self.movement_state.crouching = wants_to_crouch;
     }
 
-    pub fn control(&mut self, space: &mut Space, _ctx: &mut TickContext) {
+    /// Jump off the ground, a little after leaving it, or off a wall. Pressing jump just before landing still counts
+    pub fn jump(&mut self, space: &mut Space, bindings: &Bindings) {
+
+        if bindings.pressed(Action::Jump) {
+            self.movement_state.jump_pressed_at = Some(Time::now());
+        }
+
+        if !self.movement_state.jump_buffered(&self.movement) {
+            return;
+        }
 
         let rigid_body = space.sync_rigid_body_set.get_sync_mut(self.body.body_handle).unwrap();
 
-        let gamepad: Option<Gamepad<'_>> = None;
+        let velocity = *rigid_body.linvel();
+
+        let new_velocity = if self.movement_state.grounded || self.movement_state.in_coyote_time(&self.movement) {
+            // dont let falling eat into the jump
+            vector![velocity.x, velocity.y.max(0.) + self.movement.jump_velocity]
+        }
+
+        else if let Some(wall) = self.movement_state.wall {
+            self.movement_state.wall_jumped_at = Some(Time::now());
+
+            vector![-wall.direction() * self.movement.wall_jump_velocity.x, self.movement.wall_jump_velocity.y]
+        }
+
+        else {
+            return;
+        };
+
+        rigid_body.set_linvel(new_velocity, true);
+
+        self.movement_state.grounded = false;
+        self.movement_state.left_ground_at = None;
+        self.movement_state.jump_pressed_at = None;
+        self.movement_state.jumped_at = Some(Time::now());
+    }
+
+    pub fn control(&mut self, space: &mut Space, ctx: &mut TickContext) {
+
+        let ignored = self.own_local_bodies(space);
 
-        // sprinting but a little dumb
-        // let speed = match is_key_down(KeyCode::LeftShift) {
-        //     true => 100.,
-        //     false => 20.,
-        // };
+        let half_extents = space.sync_collider_set.get_sync(self.body.collider_handle).unwrap().shape().as_cuboid().unwrap().half_extents;
 
-        let speed = 50.;
+        self.movement_state.update_contacts(space, self.body.body_handle, vec2(half_extents.x, half_extents.y), &ignored, &self.movement);
 
-        self.jump(rigid_body, gamepad);
+        self.crouch(space, ctx.bindings, &ignored);
 
-        if is_key_down(KeyCode::A) || gamepad.map_or(false, |gamepad| {gamepad.is_pressed(gilrs::Button::DPadLeft)}) {
+        self.movement_state.sprinting = ctx.bindings.down(Action::Sprint) && !self.movement_state.crouching;
 
-            if rigid_body.linvel().x < -self.max_speed.x {
+        self.jump(space, ctx.bindings);
+
+        // holding towards the wall would cancel a wall jump straight away
+        if self.movement_state.steering_locked(&self.movement) {
+            return;
+        }
+
+        let (acceleration, top_speed) = match (self.movement_state.crouching, self.movement_state.sprinting) {
+            (true, _) => (self.movement.acceleration, self.movement.crouch_speed),
+            (false, true) => (self.movement.sprint_acceleration, self.movement.sprint_speed),
+            (false, false) => (self.movement.acceleration, self.movement.walk_speed),
+        };
+
+        let rigid_body = space.sync_rigid_body_set.get_sync_mut(self.body.body_handle).unwrap();
+
+        // keys are all or nothing but the stick can be pushed part of the way
+        let stick = ctx.bindings.gamepad.left_stick.x.abs();
+
+        let speed = match stick > 0. {
+            true => acceleration * stick,
+            false => acceleration,
+        };
+
+        if ctx.bindings.down(Action::MoveLeft) {
+
+            if rigid_body.linvel().x < -top_speed {
                 return
             }
 
@@ -936,9 +1627,9 @@ impl Player {
 
         }
 
-        if is_key_down(KeyCode::D) || gamepad.map_or(false, |gamepad| {gamepad.is_pressed(gilrs::Button::DPadRight)}) {
+        if ctx.bindings.down(Action::MoveRight) {
 
-            if rigid_body.linvel().x > self.max_speed.x {
+            if rigid_body.linvel().x > top_speed {
                 return
             }
 
@@ -956,18 +1647,32 @@ impl Player {
             )
         }
 
-        // if gamepad.map_or(false, |gamepad| {gamepad.is_pressed(gilrs::Button::RightTrigger)}) {
-        //     rigid_body.set_rotation(
-        //         Rotation, wake_up
-        //     );
-        // }
-
     }
 
     pub async fn draw_hud(&self, ctx: &mut TickContext<'_>) {
         if let Some(weapon) = &self.weapon {
             weapon.draw_hud(ctx).await
         }
+
+        self.draw_inventory(ctx);
+    }
+
+    /// The weapons we are carrying above the ammo counter, the held one highlighted
+    pub fn draw_inventory(&self, ctx: &TickContext) {
+
+        if self.owner != *ctx.uuid {
+            return;
+        }
+
+        for (slot, weapon) in self.weapon.iter().chain(&self.stowed).enumerate() {
+
+            let color = match slot {
+                0 => WHITE,
+                _ => GRAY,
+            };
+
+            draw_text(weapon.name(), 10., screen_height() - 140. - (slot as f32 * 25.), 25., color);
+        }
     }
     pub async fn draw(&self, space: &Space, textures: &mut TextureLoader, _camera_rect: &Rect) {
 
@@ -993,8 +1698,18 @@ impl Player {
         //     None => {},
         // }
 
-        self.body.draw(textures, space, flip_x).await;
-        self.head.draw(textures, space, flip_x).await;
+        match self.tint_color() {
+            Some(tint) => {
+                self.limbs.draw_tinted(textures, space, flip_x, tint).await;
+                self.body.draw_tinted(textures, space, flip_x, tint).await;
+                self.head.draw_tinted(textures, space, flip_x, tint).await;
+            },
+            None => {
+                self.limbs.draw(textures, space, flip_x).await;
+                self.body.draw(textures, space, flip_x).await;
+                self.head.draw(textures, space, flip_x).await;
+            },
+        }
        
         if let Some(weapon) = &self.weapon {
 
@@ -1010,10 +1725,31 @@ impl Player {
             
         };
 
-        draw_rectangle(head_pos.x - 25., head_pos.y - 40., (self.health as f32 / 100 as f32) as f32 * 50., 10., GREEN);
+        draw_rectangle(head_pos.x - 25., head_pos.y - 40., (self.health as f32 / MAX_HEALTH as f32) * 50., 10., GREEN);
+
+        if self.armor > 0 {
+            draw_rectangle(head_pos.x - 25., head_pos.y - 28., (self.armor as f32 / MAX_ARMOR as f32) * 50., 4., SKYBLUE);
+        }
+
+        self.draw_nameplate(head_pos);
         
         
     }
+
+    /// Our name above the health bar, in our team's color
+    pub fn draw_nameplate(&self, head_pos: Vec2) {
+
+        let name = self.name();
+
+        let color = match self.team {
+            Some(team) => team.hud_color(),
+            None => WHITE,
+        };
+
+        let width = measure_text(&name, None, 20, 1.).width;
+
+        draw_text(&name, head_pos.x - (width / 2.), head_pos.y - 46., 20., color);
+    }
 }
 
 impl HasPhysics for Player {
@@ -1041,88 +1777,3 @@ impl HasPhysics for Player {
         &mut self.drag_offset
     }
 }
-
-#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
-#[diff(attr(
-    #[derive(Serialize, Deserialize)]
-))]
-pub enum PlayerAnimationState {
-    Walking,
-    Idle,
-    GunRun
-}
-
-#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
-#[diff(attr(
-    #[derive(Serialize, Deserialize)]
-))]
-// used to track the state of player animation
-pub struct PlayerAnimationHandler {
-    state: PlayerAnimationState,
-    walking_frames: TrackedFrames,
-    idle_frames: TrackedFrames,
-    gunrun_frames: TrackedFrames
-}
-
-impl PlayerAnimationHandler {
-
-    pub fn new(initial_state: PlayerAnimationState) -> Self {
-        
-        Self {
-            state: initial_state,
-            walking_frames: TrackedFrames::load_from_directory(&"assets/player/walk".to_string()),
-            idle_frames: TrackedFrames::load_from_directory(&"assets/player/idle".to_string()),
-            gunrun_frames: TrackedFrames::load_from_directory(&"assets/player/gunrun".to_string())
-        }
-    }
-
-    pub fn get_current_frame(&self) -> &String {
-        match self.state {
-            PlayerAnimationState::Walking => {
-                self.walking_frames.current_frame()
-            },
-            PlayerAnimationState::Idle => {
-                self.idle_frames.current_frame()
-            },
-            PlayerAnimationState::GunRun => {
-                self.gunrun_frames.current_frame()
-            }
-        }
-    }
-
-    pub fn set_frame(&mut self, state: PlayerAnimationState, new_frame_index: usize) {
-        match state {
-            PlayerAnimationState::Walking => {
-                self.walking_frames.set_frame(new_frame_index);
-            },
-            PlayerAnimationState::Idle => {
-                self.idle_frames.set_frame(new_frame_index);
-            },
-            PlayerAnimationState::GunRun => {
-                self.gunrun_frames.set_frame(new_frame_index);
-            }
-        }
-    }
-
-    pub fn set_animation_state(&mut self, new_state: PlayerAnimationState) {
-        self.state = new_state;
-    }
-
-    pub fn get_animation_state(&self) -> PlayerAnimationState {
-        self.state.clone()
-    }
-
-    pub fn next_frame(&mut self, state: PlayerAnimationState) {
-        match state {
-            PlayerAnimationState::Walking => {
-                self.walking_frames.next_frame();
-            },
-            PlayerAnimationState::Idle => {
-                self.idle_frames.next_frame();
-            },
-            PlayerAnimationState::GunRun => {
-                self.gunrun_frames.next_frame();
-            }
-        }
-    }
-}
\ No newline at end of file
@@ -0,0 +1,336 @@
+use diff::Diff;
+use gamelibrary::{space::{Space, SyncImpulseJointHandle, SyncRigidBodyHandle}, texture_loader::TextureLoader, time::Time};
+use macroquad::{color::Color, math::Vec2};
+use nalgebra::vector;
+use rapier2d::prelude::{Group, InteractionGroups, RevoluteJointBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{collider_groups::{BODY_PART_GROUP, DETACHED_BODY_PART_GROUP}, cosmetics::Tint, TickContext};
+
+use super::{animation::Pose, body_part::BodyPart, player::Facing};
+
+// bodies lie around this many ms after dying before they get cleaned up
+pub const RAGDOLL_LIFETIME: i64 = 15000;
+
+// how hard limbs are pulled towards their animation pose
+const POSE_STIFFNESS: f32 = 300.;
+const POSE_DAMPING: f32 = 10.;
+
+// a killing hit doing at least this much damage knocks off the limb closest to the shooter
+pub const LIMB_SEVER_DAMAGE: u32 = 45;
+
+/// Dead body parts collide with the world but not with each other or the living
+pub fn limp_interaction_groups() -> InteractionGroups {
+    InteractionGroups::none()
+        .with_memberships(DETACHED_BODY_PART_GROUP)
+        .with_filter(
+            Group::ALL
+                .difference(DETACHED_BODY_PART_GROUP)
+                .difference(BODY_PART_GROUP)
+        )
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Copy)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub enum LimbKind {
+    LeftArm,
+    RightArm,
+    LeftFoot,
+    RightFoot
+}
+
+impl LimbKind {
+    pub const ALL: [LimbKind; 4] = [
+        LimbKind::LeftArm,
+        LimbKind::RightArm,
+        LimbKind::LeftFoot,
+        LimbKind::RightFoot
+    ];
+
+    pub fn sprite_path(&self) -> &'static str {
+        match self {
+            LimbKind::LeftArm | LimbKind::RightArm => "assets/cat/arm.png",
+            LimbKind::LeftFoot => "assets/cat/left_foot.png",
+            LimbKind::RightFoot => "assets/cat/right_foot.png",
+        }
+    }
+
+    pub fn texture_size(&self) -> Vec2 {
+        match self {
+            LimbKind::LeftArm | LimbKind::RightArm => Vec2::new(7., 7.),
+            LimbKind::LeftFoot | LimbKind::RightFoot => Vec2::new(8., 8.),
+        }
+    }
+
+    /// Where the limb hangs off the torso, relative to the torso's middle
+    pub fn torso_anchor(&self) -> Vec2 {
+        match self {
+            LimbKind::LeftArm => Vec2::new(-16., 4.),
+            LimbKind::RightArm => Vec2::new(16., 4.),
+            LimbKind::LeftFoot => Vec2::new(-10., -19.),
+            LimbKind::RightFoot => Vec2::new(10., -19.),
+        }
+    }
+
+    /// The point on the limb that is pinned to the torso
+    pub fn limb_anchor(&self) -> Vec2 {
+        match self {
+            LimbKind::LeftArm | LimbKind::RightArm => Vec2::new(0., 5.),
+            LimbKind::LeftFoot | LimbKind::RightFoot => Vec2::new(0., 6.),
+        }
+    }
+
+    /// How far the joint can bend either way in radians. Feet bend a lot less than arms
+    pub fn limits(&self) -> [f32; 2] {
+        match self {
+            LimbKind::LeftArm | LimbKind::RightArm => [-1.2, 1.2],
+            LimbKind::LeftFoot | LimbKind::RightFoot => [-0.5, 0.5],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Limb {
+    pub kind: LimbKind,
+    pub part: BodyPart,
+    pub joint: Option<SyncImpulseJointHandle>, // None once it has come off
+    pub severed: bool // set by whoever dealt the hit, the owner removes the joint on their next tick
+}
+
+/// The arms and feet hanging off a torso. They dont collide with anything while alive so they never get in the way of movement
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Default)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Limbs {
+    pub limbs: Vec<Limb>
+}
+
+impl Limbs {
+    pub fn new(torso: &BodyPart, position: Vec2, space: &mut Space, textures: &mut TextureLoader, owner: String) -> Self {
+
+        let local_torso = space.sync_rigid_body_set.get_local_handle(torso.body_handle);
+
+        let mut limbs = Vec::new();
+
+        for kind in LimbKind::ALL {
+
+            let part = BodyPart::new(
+                kind.sprite_path().to_string(),
+                2,
+                1.,
+                position + kind.torso_anchor(),
+                space,
+                textures,
+                owner.clone(),
+                kind.texture_size()
+            );
+
+            space.sync_collider_set.get_sync_mut(part.collider_handle).unwrap().set_collision_groups(InteractionGroups::none());
+
+            let torso_anchor = kind.torso_anchor();
+            let limb_anchor = kind.limb_anchor();
+
+            let joint = space.sync_impulse_joint_set.insert_sync(
+                local_torso,
+                space.sync_rigid_body_set.get_local_handle(part.body_handle),
+                RevoluteJointBuilder::new()
+                    .local_anchor1(vector![torso_anchor.x, torso_anchor.y].into())
+                    .local_anchor2(vector![limb_anchor.x, limb_anchor.y].into())
+                    .limits(kind.limits())
+                    .contacts_enabled(false)
+                .build(),
+                true
+            );
+
+            limbs.push(
+                Limb {
+                    kind,
+                    part,
+                    joint: Some(joint),
+                    severed: false
+                }
+            );
+        }
+
+        Self {
+            limbs
+        }
+    }
+
+    pub fn body_handles(&self) -> Vec<SyncRigidBodyHandle> {
+        self.limbs.iter().map(|limb| limb.part.body_handle).collect()
+    }
+
+    /// Let the limbs flop onto the ground
+    pub fn go_limp(&self, space: &mut Space) {
+        for limb in &self.limbs {
+            space.sync_collider_set.get_sync_mut(limb.part.collider_handle).unwrap().set_collision_groups(limp_interaction_groups());
+
+            // stop the animation holding them in place
+            if let Some(joint) = limb.joint {
+                let joint = space.sync_impulse_joint_set.get_sync_mut(joint).unwrap();
+
+                joint.data.as_revolute_mut().unwrap().set_motor_position(0., 0., 0.);
+            }
+        }
+    }
+
+    /// Drive the joints towards an animation pose. Facing left mirrors it
+    pub fn set_pose(&self, space: &mut Space, pose: &Pose, facing: &Facing) {
+
+        let mirror = match facing {
+            Facing::Right => 1.,
+            Facing::Left => -1.,
+        };
+
+        for limb in &self.limbs {
+
+            let joint = match limb.joint {
+                Some(joint) => joint,
+                None => continue,
+            };
+
+            let angle = match limb.kind {
+                LimbKind::LeftArm => pose.left_arm,
+                LimbKind::RightArm => pose.right_arm,
+                LimbKind::LeftFoot => pose.left_foot,
+                LimbKind::RightFoot => pose.right_foot,
+            };
+
+            let joint = space.sync_impulse_joint_set.get_sync_mut(joint).unwrap();
+
+            joint.data.as_revolute_mut().unwrap().set_motor_position(angle * mirror, POSE_STIFFNESS, POSE_DAMPING);
+        }
+    }
+
+    /// Mark the attached limb closest to a point to come off
+    pub fn sever_nearest(&mut self, space: &Space, point: Vec2) {
+
+        let nearest = self.limbs.iter_mut()
+            .filter(|limb| !limb.severed)
+            .min_by(|a, b| {
+                let a = space.sync_rigid_body_set.get_sync(a.part.body_handle).unwrap().translation();
+                let b = space.sync_rigid_body_set.get_sync(b.part.body_handle).unwrap().translation();
+
+                Vec2::new(a.x, a.y).distance(point).total_cmp(&Vec2::new(b.x, b.y).distance(point))
+            });
+
+        if let Some(limb) = nearest {
+            limb.severed = true;
+        }
+    }
+
+    pub fn tick(&mut self, space: &mut Space, ctx: &mut TickContext) {
+
+        for limb in &mut self.limbs {
+
+            limb.part.tick(space, ctx);
+
+            if limb.part.owner != *ctx.uuid || !limb.severed {
+                continue;
+            }
+
+            if let Some(joint) = limb.joint.take() {
+                space.sync_impulse_joint_set.remove_sync(joint, true);
+
+                space.sync_collider_set.get_sync_mut(limb.part.collider_handle).unwrap().set_collision_groups(limp_interaction_groups());
+            }
+        }
+    }
+
+    pub fn despawn(self, space: &mut Space) {
+        for limb in self.limbs {
+            space.sync_rigid_body_set.remove_sync(
+                limb.part.body_handle,
+                &mut space.island_manager,
+                &mut space.sync_collider_set,
+                &mut space.sync_impulse_joint_set,
+                &mut space.multibody_joint_set,
+                true
+            );
+        }
+    }
+
+    pub async fn draw(&self, textures: &mut TextureLoader, space: &Space, flip_x: bool) {
+        for limb in &self.limbs {
+            limb.part.draw(textures, space, flip_x).await;
+        }
+    }
+
+    pub async fn draw_tinted(&self, textures: &mut TextureLoader, space: &Space, flip_x: bool, tint: Color) {
+        for limb in &self.limbs {
+            limb.part.draw_tinted(textures, space, flip_x, tint).await;
+        }
+    }
+}
+
+/// What is left of a player after they respawn. It lies around for a while and then gets cleaned up by its owner
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Ragdoll {
+    pub head: BodyPart,
+    pub body: BodyPart,
+    pub limbs: Limbs,
+    pub facing: Facing,
+    #[serde(default)]
+    pub tint: Option<Tint>,
+    pub died_at: Time,
+    pub owner: String
+}
+
+impl Ragdoll {
+    pub fn expired(&self) -> bool {
+        self.died_at.elapsed().num_milliseconds() > RAGDOLL_LIFETIME
+    }
+
+    pub fn tick(&mut self, space: &mut Space, ctx: &mut TickContext) {
+        self.head.tick(space, ctx);
+        self.body.tick(space, ctx);
+        self.limbs.tick(space, ctx);
+    }
+
+    pub fn despawn(self, space: &mut Space) {
+
+        for part in [self.head, self.body] {
+            space.sync_rigid_body_set.remove_sync(
+                part.body_handle,
+                &mut space.island_manager,
+                &mut space.sync_collider_set,
+                &mut space.sync_impulse_joint_set,
+                &mut space.multibody_joint_set,
+                true
+            );
+        }
+
+        self.limbs.despawn(space);
+    }
+
+    pub async fn draw(&self, textures: &mut TextureLoader, space: &Space) {
+
+        let flip_x = match self.facing {
+            Facing::Right => false,
+            Facing::Left => true,
+        };
+
+        match self.tint {
+            Some(tint) => {
+                self.limbs.draw_tinted(textures, space, flip_x, tint.color()).await;
+                self.body.draw_tinted(textures, space, flip_x, tint.color()).await;
+                self.head.draw_tinted(textures, space, flip_x, tint.color()).await;
+            },
+            None => {
+                self.limbs.draw(textures, space, flip_x).await;
+                self.body.draw(textures, space, flip_x).await;
+                self.head.draw(textures, space, flip_x).await;
+            },
+        }
+    }
+}
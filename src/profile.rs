@@ -0,0 +1,60 @@
+use std::fs;
+
+use gamelibrary::log;
+use serde::{Deserialize, Serialize};
+
+pub const PROFILE_PATH: &str = "profile.yaml";
+
+pub const MAX_NAME_LENGTH: usize = 16;
+
+/// Who we are to other players. Picked in the main menu and kept on this machine
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Profile {
+    #[serde(default)]
+    pub display_name: String,
+    // names of the cosmetics picked on the character screen. empty means the first one in the catalog
+    #[serde(default)]
+    pub head: String,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub tint: String
+}
+
+impl Profile {
+
+    pub fn load() -> Self {
+        match fs::read(PROFILE_PATH) {
+            Ok(bytes) => match serde_yaml::from_slice::<Self>(&bytes) {
+                Ok(profile) => profile,
+                Err(error) => {
+                    log(&format!("failed to parse profile {}: {}", PROFILE_PATH, error));
+
+                    Self::default()
+                },
+            },
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+
+        let yaml = match serde_yaml::to_string(self) {
+            Ok(yaml) => yaml,
+            Err(error) => {
+                log(&format!("failed to serialize profile: {}", error));
+
+                return;
+            },
+        };
+
+        if let Err(error) = fs::write(PROFILE_PATH, yaml) {
+            log(&format!("failed to write profile {}: {}", PROFILE_PATH, error));
+        }
+    }
+
+    /// Trim the name down to something that fits on a nameplate
+    pub fn set_display_name(&mut self, name: &str) {
+        self.display_name = name.trim().chars().filter(|character| !character.is_control()).take(MAX_NAME_LENGTH).collect();
+    }
+}
@@ -0,0 +1,128 @@
+use diff::Diff;
+use gamelibrary::sync_arena::SyncArena;
+use macroquad::color::{Color, WHITE, YELLOW};
+use serde::{Deserialize, Serialize};
+
+use crate::{player::player::Player, team::Team};
+
+/// Whoever can score in a round. Team modes use teams and free for all uses individual players
+#[derive(Serialize, Deserialize, Diff, PartialEq, Eq, Hash, Clone, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub enum Scorer {
+    Team(Team),
+    Player(String) // the owner of the player
+}
+
+impl Scorer {
+
+    pub fn name(&self, players: &SyncArena<Player>) -> String {
+        match self {
+            Scorer::Team(team) => format!("{} team", team.name()),
+            Scorer::Player(owner) => {
+                match players.iter().find(|(_, player)| player.owner == *owner) {
+                    Some((_, player)) => player.name(),
+                    None => "Nobody".to_string(),
+                }
+            },
+        }
+    }
+
+    pub fn color(&self, uuid: &String) -> Color {
+        match self {
+            Scorer::Team(team) => team.hud_color(),
+            Scorer::Player(owner) => {
+                match owner == uuid {
+                    true => YELLOW,
+                    false => WHITE,
+                }
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Diff, Clone, PartialEq)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Score {
+    pub scorer: Scorer,
+    pub score: u32
+}
+
+/// The scores of the current round and who won it. Shared by every mode where the first to the score limit wins
+#[derive(Serialize, Deserialize, Diff, Clone, PartialEq, Default)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct RoundScore {
+    pub scores: Vec<Score>,
+    pub winner: Option<Scorer>
+}
+
+impl RoundScore {
+
+    pub fn new() -> Self {
+        Self {
+            scores: Vec::new(),
+            winner: None
+        }
+    }
+
+    pub fn score(&self, scorer: &Scorer) -> u32 {
+        match self.scores.iter().find(|score| score.scorer == *scorer) {
+            Some(score) => score.score,
+            None => 0,
+        }
+    }
+
+    /// Give someone a point. The first to reach `score_limit` wins the round
+    pub fn add_point(&mut self, scorer: &Scorer, score_limit: u32) {
+        match self.scores.iter_mut().find(|score| score.scorer == *scorer) {
+            Some(score) => score.score += 1,
+            None => self.scores.push(Score { scorer: scorer.clone(), score: 1 }),
+        }
+
+        if self.score(scorer) >= score_limit && self.winner.is_none() {
+            self.winner = Some(scorer.clone());
+        }
+    }
+
+    /// Free for all scores belong to the player so they leave with them
+    pub fn remove(&mut self, scorer: &Scorer) {
+        self.scores.retain(|score| score.scorer != *scorer);
+    }
+
+    pub fn round_over(&self) -> bool {
+        self.winner.is_some()
+    }
+
+    pub fn reset(&mut self) {
+        *self = RoundScore::new();
+    }
+
+    /// Highest score first
+    pub fn standings(&self) -> Vec<&Score> {
+        let mut scores: Vec<&Score> = self.scores.iter().collect();
+
+        scores.sort_by(|a, b| b.score.cmp(&a.score));
+
+        scores
+    }
+
+    /// The winner, or whoever is ahead when time runs out
+    pub fn headline(&self, players: &SyncArena<Player>) -> String {
+
+        if let Some(winner) = &self.winner {
+            return format!("{} wins!", winner.name(players));
+        }
+
+        match self.standings().as_slice() {
+            [] => "Draw!".to_string(),
+            [leader] => format!("{} wins!", leader.scorer.name(players)),
+            [leader, runner_up, ..] if leader.score == runner_up.score => "Draw!".to_string(),
+            [leader, ..] => format!("{} wins!", leader.scorer.name(players)),
+        }
+    }
+}
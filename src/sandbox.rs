@@ -0,0 +1,257 @@
+use diff::Diff;
+use gamelibrary::sync_arena::SyncArena;
+use macroquad::{color::{Color, GRAY, WHITE, YELLOW}, shapes::draw_rectangle, text::draw_text, window::screen_width};
+use serde::{Deserialize, Serialize};
+
+use crate::{bindings::{Action, Bindings}, game_mode::{GameMode, ModeContext}, level::Level, player::player::Player, TickContext};
+
+/// Settings that only exist in sandbox. Every other mode plays with these off, so there is nothing to toggle in deathmatch
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct SandboxData {
+    pub god_mode: bool, // players dont take damage
+    pub infinite_ammo: bool,
+    pub gravity: bool
+}
+
+impl SandboxData {
+    pub fn new() -> Self {
+        Self {
+            god_mode: false,
+            infinite_ammo: false,
+            gravity: true
+        }
+    }
+
+    /// The y gravity the level should have with the current settings
+    pub fn gravity_y(&self) -> f32 {
+        match self.gravity {
+            true => -980.,
+            false => 0.,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SpawnableEntity {
+    Enemy,
+    Grenade,
+    Pixel,
+    DamageNumber,
+    Brick,
+    Structure,
+    Shotgun,
+    Boat,
+    Radio,
+    Teleporter,
+    Portal,
+    Flag
+}
+
+impl SpawnableEntity {
+    pub const ALL: [SpawnableEntity; 12] = [
+        SpawnableEntity::Enemy,
+        SpawnableEntity::Grenade,
+        SpawnableEntity::Pixel,
+        SpawnableEntity::DamageNumber,
+        SpawnableEntity::Brick,
+        SpawnableEntity::Structure,
+        SpawnableEntity::Shotgun,
+        SpawnableEntity::Boat,
+        SpawnableEntity::Radio,
+        SpawnableEntity::Teleporter,
+        SpawnableEntity::Portal,
+        SpawnableEntity::Flag
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpawnableEntity::Enemy => "Enemy",
+            SpawnableEntity::Grenade => "Grenade",
+            SpawnableEntity::Pixel => "Pixel",
+            SpawnableEntity::DamageNumber => "Damage number",
+            SpawnableEntity::Brick => "Brick",
+            SpawnableEntity::Structure => "Structure",
+            SpawnableEntity::Shotgun => "Shotgun",
+            SpawnableEntity::Boat => "Boat",
+            SpawnableEntity::Radio => "Radio",
+            SpawnableEntity::Teleporter => "Teleporter",
+            SpawnableEntity::Portal => "Portal (on a structure)",
+            SpawnableEntity::Flag => "Flag",
+        }
+    }
+}
+
+impl GameMode for SandboxData {
+
+    fn name(&self) -> &'static str {
+        "Sandbox"
+    }
+
+    fn min_players(&self) -> usize {
+        0
+    }
+
+    fn has_rounds(&self) -> bool {
+        false
+    }
+
+    fn timed_rounds(&self) -> bool {
+        false
+    }
+
+    fn sandbox(&self) -> Option<&SandboxData> {
+        Some(self)
+    }
+
+    fn health_regen(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {}
+
+    fn round_over(&self, _players: &SyncArena<Player>) -> bool {
+        false
+    }
+
+    /// Spawn menu and settings toggles
+    fn tick(&mut self, mode_ctx: &mut ModeContext, ctx: &mut TickContext) {
+
+        ctx.spawn_menu.tick(ctx.bindings);
+
+        match ctx.spawn_menu.requested_toggle(ctx.bindings) {
+            Some(SandboxToggle::GodMode) => self.god_mode = !self.god_mode,
+            Some(SandboxToggle::InfiniteAmmo) => self.infinite_ammo = !self.infinite_ammo,
+            Some(SandboxToggle::Gravity) => self.gravity = !self.gravity,
+            None => {},
+        }
+
+        // the space is synced so only the host touches gravity, and only when it actually changes
+        if *ctx.is_host && mode_ctx.level.space.gravity.y != self.gravity_y() {
+            mode_ctx.level.space.gravity.y = self.gravity_y();
+        }
+    }
+
+    fn draw_hud(&self, _level: &Level, ctx: &TickContext) {
+        ctx.spawn_menu.draw(self, ctx.bindings);
+    }
+}
+
+/// Which sandbox setting the player asked to flip this tick
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SandboxToggle {
+    GodMode,
+    InfiniteAmmo,
+    Gravity
+}
+
+/// The local sandbox spawn menu. Each client has its own so this isnt synced
+pub struct SpawnMenu {
+    pub open: bool,
+    pub selected: SpawnableEntity
+}
+
+impl SpawnMenu {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            selected: SpawnableEntity::Enemy
+        }
+    }
+
+    /// Open and close the menu and scroll through the entities
+    pub fn tick(&mut self, bindings: &Bindings) {
+
+        if bindings.released(Action::SpawnMenu) {
+            self.open = !self.open;
+        }
+
+        if !self.open {
+            return;
+        }
+
+        let selected_index = SpawnableEntity::ALL.iter().position(|entity| *entity == self.selected).unwrap_or(0);
+
+        let entity_count = SpawnableEntity::ALL.len();
+
+        if bindings.pressed(Action::NextSpawnable) {
+            self.selected = SpawnableEntity::ALL[(selected_index + 1) % entity_count];
+        }
+
+        if bindings.pressed(Action::PreviousSpawnable) {
+            self.selected = SpawnableEntity::ALL[(selected_index + entity_count - 1) % entity_count];
+        }
+    }
+
+    /// Whether the player wants to spawn the selected entity at the mouse
+    pub fn spawn_requested(&self, bindings: &Bindings) -> bool {
+        self.open && bindings.released(Action::SpawnAtMouse)
+    }
+
+    pub fn requested_toggle(&self, bindings: &Bindings) -> Option<SandboxToggle> {
+
+        if !self.open {
+            return None;
+        }
+
+        if bindings.released(Action::ToggleGodMode) {
+            return Some(SandboxToggle::GodMode);
+        }
+
+        if bindings.released(Action::ToggleInfiniteAmmo) {
+            return Some(SandboxToggle::InfiniteAmmo);
+        }
+
+        if bindings.released(Action::ToggleGravity) {
+            return Some(SandboxToggle::Gravity);
+        }
+
+        None
+    }
+
+    pub fn draw(&self, data: &SandboxData, bindings: &Bindings) {
+
+        if !self.open {
+            draw_text(&format!("[{}] spawn menu", bindings.key_name(Action::SpawnMenu)), screen_width() - 200., 60., 25., GRAY);
+
+            return;
+        }
+
+        let width = 300.;
+        let x = screen_width() - width - 20.;
+        let y = 60.;
+
+        let height = 80. + ((SpawnableEntity::ALL.len() + 5) as f32 * 25.);
+
+        draw_rectangle(x, y, width, height, Color::from_rgba(0, 0, 0, 180));
+
+        draw_text("Spawn menu", x + 10., y + 30., 30., WHITE);
+
+        for (index, entity) in SpawnableEntity::ALL.iter().enumerate() {
+
+            let (marker, color) = match *entity == self.selected {
+                true => (">", YELLOW),
+                false => (" ", WHITE),
+            };
+
+            draw_text(&format!("{} {}", marker, entity.name()), x + 10., y + 60. + (index as f32 * 25.), 25., color);
+        }
+
+        let toggles_y = y + 70. + (SpawnableEntity::ALL.len() as f32 * 25.);
+
+        let on_off = |value: bool| {
+            match value {
+                true => "on",
+                false => "off",
+            }
+        };
+
+        draw_text(&format!("[{}/{}] select", bindings.key_name(Action::PreviousSpawnable), bindings.key_name(Action::NextSpawnable)), x + 10., toggles_y, 25., GRAY);
+        draw_text(&format!("[{}] spawn at mouse", bindings.key_name(Action::SpawnAtMouse)), x + 10., toggles_y + 25., 25., GRAY);
+        draw_text(&format!("[{}] god mode: {}", bindings.key_name(Action::ToggleGodMode), on_off(data.god_mode)), x + 10., toggles_y + 50., 25., WHITE);
+        draw_text(&format!("[{}] infinite ammo: {}", bindings.key_name(Action::ToggleInfiniteAmmo), on_off(data.infinite_ammo)), x + 10., toggles_y + 75., 25., WHITE);
+        draw_text(&format!("[{}] gravity: {}", bindings.key_name(Action::ToggleGravity), on_off(data.gravity)), x + 10., toggles_y + 100., 25., WHITE);
+    }
+}
@@ -3,7 +3,7 @@ use std::{net::{SocketAddr, TcpListener, TcpStream}, time::Duration};
 use gamelibrary::sync::server::SyncServer;
 use lz4_flex::decompress_size_prepended;
 use tungstenite::{Message, WebSocket};
-use crate::{game_state::GameState, level::Level, updates::Update};
+use crate::{game_state::GameState, level::Level, match_state::MatchRotation, updates::Update};
 
 pub struct Server {
     game_state: GameState,
@@ -17,7 +17,14 @@ impl Server {
 
         let mut game_state = GameState::empty();
 
-        game_state.level = Level::from_save("level.yaml".to_string());
+        // cycle through levels and modes if the server has a rotation configured
+        match MatchRotation::from_file("rotation.yaml") {
+            Some(rotation) => game_state.start_rotation(rotation),
+            None => match Level::from_save("level.yaml") {
+                Ok(level) => game_state.level = level,
+                Err(error) => println!("{}, starting with an empty level", error),
+            },
+        }
 
         let listener = match TcpListener::bind(address) {
             Ok(listener) => listener,
@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+
+use diff::Diff;
+use gamelibrary::{rapier_to_macroquad, space::Space, sync_arena::SyncArena, texture_loader::TextureLoader, time::Time, uuid_u32};
+use macroquad::{color::{Color, GOLD, GRAY, GREEN, RED, WHITE}, math::Vec2, shapes::{draw_rectangle, draw_rectangle_lines}, text::draw_text, window::{screen_height, screen_width}};
+use serde::{Deserialize, Serialize};
+
+use crate::{bindings::{Action, Bindings}, boat::Boat, level::Level, pistol::Pistol, player::player::{Player, MAX_HEALTH, STARTING_MATERIALS}, shotgun::Shotgun, structure::Structure, team::Team};
+
+// money handed out by the host
+pub const KILL_REWARD: u32 = 100;
+pub const ASSIST_REWARD: u32 = 50;
+pub const WAVE_REWARD: u32 = 250;
+pub const OBJECTIVE_REWARD: u32 = 300;
+
+// how close you need to be to a shop to use it
+const SHOP_RANGE: f32 = 150.;
+
+// the action that buys each item, in the same order as ShopItem::ALL
+const SHOP_SLOTS: [Action; 8] = [
+    Action::ShopSlot1,
+    Action::ShopSlot2,
+    Action::ShopSlot3,
+    Action::ShopSlot4,
+    Action::ShopSlot5,
+    Action::ShopSlot6,
+    Action::ShopSlot7,
+    Action::ShopSlot8
+];
+
+// how many magazines an ammo refill adds to the reserve
+const AMMO_REFILL_MAGAZINES: u32 = 3;
+
+const MATERIALS_PER_PURCHASE: u32 = 5;
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Eq, Clone, Copy, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub enum ShopItem {
+    Pistol,
+    Shotgun,
+    Ammo,
+    Health,
+    Materials,
+    Plank,
+    Boat,
+    TimeStop
+}
+
+impl ShopItem {
+    pub const ALL: [ShopItem; 8] = [
+        ShopItem::Pistol,
+        ShopItem::Shotgun,
+        ShopItem::Ammo,
+        ShopItem::Health,
+        ShopItem::Materials,
+        ShopItem::Plank,
+        ShopItem::Boat,
+        ShopItem::TimeStop
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShopItem::Pistol => "Pistol",
+            ShopItem::Shotgun => "Shotgun",
+            ShopItem::Ammo => "Ammo refill",
+            ShopItem::Health => "Full health",
+            ShopItem::Materials => "Building materials",
+            ShopItem::Plank => "Boat plank",
+            ShopItem::Boat => "Boat",
+            ShopItem::TimeStop => "Time stop",
+        }
+    }
+
+    pub fn price(&self) -> u32 {
+        match self {
+            ShopItem::Pistol => 200,
+            ShopItem::Shotgun => 500,
+            ShopItem::Ammo => 100,
+            ShopItem::Health => 150,
+            ShopItem::Materials => 100,
+            ShopItem::Plank => 50,
+            ShopItem::Boat => 1000,
+            ShopItem::TimeStop => 750,
+        }
+    }
+
+    /// Hand the item to a player once the host has charged for it. Only the player's owner should do this since they simulate the player.
+    /// Health is the exception, the host heals when it approves the purchase so health keeps one writer
+    pub fn give(&self, player: &mut Player, space: &mut Space, boats: &mut Vec<Boat>, textures: &mut TextureLoader) {
+
+        let body_handle = player.body.body_handle;
+
+        let position = *space.sync_rigid_body_set.get_sync(body_handle).unwrap().translation();
+
+        let position = Vec2::new(position.x, position.y);
+
+        match self {
+            ShopItem::Pistol => {
+                let pistol = Pistol::new(space, position, player.owner.clone(), Some(body_handle), textures, player.facing.clone());
+
+                player.equip_weapon(space, pistol.into());
+            },
+            ShopItem::Shotgun => {
+                let shotgun = Shotgun::new(space, position, player.owner.clone(), Some(body_handle), textures, player.facing.clone());
+
+                player.equip_weapon(space, shotgun.into());
+            },
+            ShopItem::Ammo => player.refill_ammo(AMMO_REFILL_MAGAZINES),
+            // the host already handed these out
+            ShopItem::Health | ShopItem::Materials => {},
+            ShopItem::Plank => player.planks += 1,
+            // the boat shows up above us and is ours to sail
+            ShopItem::Boat => boats.push(Boat::new(space, position + Vec2::new(0., 200.), player.owner.clone())),
+            ShopItem::TimeStop => player.time_stop_charges += 1,
+        }
+    }
+}
+
+/// A place in the level players can buy things. Placed in the editor
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Shop {
+    pub position: Vec2
+}
+
+impl Shop {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            position
+        }
+    }
+
+    pub fn in_range(&self, position: Vec2) -> bool {
+        self.position.distance(position) <= SHOP_RANGE
+    }
+
+    pub fn draw(&self) {
+        let pos = rapier_to_macroquad(&self.position);
+
+        draw_rectangle(pos.x - 30., pos.y - 30., 60., 60., Color::from_rgba(40, 30, 0, 200));
+        draw_rectangle_lines(pos.x - 30., pos.y - 30., 60., 60., 3., GOLD);
+        draw_text("$", pos.x - 10., pos.y + 12., 40., GOLD);
+    }
+}
+
+/// Whether the player is standing close enough to any shop
+pub fn near_shop(shops: &[Shop], space: &Space, player: &Player) -> bool {
+
+    let position = match space.sync_rigid_body_set.get_sync(player.body.body_handle) {
+        Some(body) => Vec2::new(body.translation().x, body.translation().y),
+        None => return false,
+    };
+
+    shops.iter().any(|shop| shop.in_range(position))
+}
+
+pub fn award_money(economy: &mut Economy, owner: &String, amount: u32) {
+    economy.wallet_mut(owner).money += amount;
+}
+
+pub fn award_team_money(economy: &mut Economy, players: &SyncArena<Player>, team: Team, amount: u32) {
+    for (_, player) in players.iter() {
+        if player.team == Some(team) {
+            award_money(economy, &player.owner, amount);
+        }
+    }
+}
+
+pub fn award_everyone_money(economy: &mut Economy, players: &SyncArena<Player>, amount: u32) {
+    for (_, player) in players.iter() {
+        award_money(economy, &player.owner, amount);
+    }
+}
+
+/// What a player wants the host to spend their money or materials on
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub enum EconomyRequestKind {
+    Buy(ShopItem),
+    Build(Vec2) // a structure at this position for one material
+}
+
+/// Written by the owner on their own player. The host answers with a receipt that has the same id
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct EconomyRequest {
+    pub id: u64,
+    pub kind: EconomyRequestKind
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub enum ReceiptStatus {
+    Approved, // paid for, waiting for the owner to pick it up
+    Denied(String) // the reason
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Receipt {
+    pub id: u64, // the id of the request this answers
+    pub status: ReceiptStatus
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Wallet {
+    pub money: u32,
+    pub materials: u32 // structures we can still build
+}
+
+impl Default for Wallet {
+    fn default() -> Self {
+        Self {
+            money: 0,
+            materials: STARTING_MATERIALS
+        }
+    }
+}
+
+/// The kills and assists we have already paid someone for
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct PaidStats {
+    pub owner: String,
+    pub kills: u32,
+    pub assists: u32
+}
+
+/// Money and materials only ever change on the host. Clients put a request on their own player and the host answers with a receipt
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct Economy {
+    #[serde(default)]
+    pub wallets: HashMap<String, Wallet>, // keyed by owner
+    #[serde(default)]
+    pub receipts: HashMap<String, Receipt>, // the answer to each owner's last request
+    pub paid_stats: Vec<PaidStats>
+}
+
+impl Default for Economy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Economy {
+    pub fn new() -> Self {
+        Self {
+            wallets: HashMap::new(),
+            receipts: HashMap::new(),
+            paid_stats: Vec::new()
+        }
+    }
+
+    /// What someone has to spend. Players the host hasnt opened a wallet for yet get the starting one
+    pub fn wallet(&self, owner: &String) -> Wallet {
+        self.wallets.get(owner).cloned().unwrap_or_default()
+    }
+
+    pub fn wallet_mut(&mut self, owner: &String) -> &mut Wallet {
+        self.wallets.entry(owner.clone()).or_default()
+    }
+
+    /// Ask the host to spend something for us. Only one request is in flight at a time so anything else is dropped until it is answered
+    pub fn request(player: &mut Player, kind: EconomyRequestKind) {
+
+        if player.economy_request.is_some() {
+            return;
+        }
+
+        player.economy_request = Some(
+            EconomyRequest {
+                id: uuid_u32() as u64,
+                kind
+            }
+        );
+    }
+
+    /// Pay everyone for kills and assists they got since last tick. Host only
+    pub fn pay_for_stats(&mut self, players: &SyncArena<Player>) {
+
+        // forget people who left
+        self.paid_stats.retain(|paid| players.iter().any(|(_, player)| player.owner == paid.owner));
+
+        for (_, player) in players.iter() {
+
+            let paid = match self.paid_stats.iter_mut().find(|paid| paid.owner == player.owner) {
+                Some(paid) => paid,
+                None => {
+                    // dont pay for anything that happened before we started watching
+                    self.paid_stats.push(PaidStats { owner: player.owner.clone(), kills: player.kills, assists: player.assists });
+
+                    continue;
+                },
+            };
+
+            // stats go back to zero when a round starts
+            paid.kills = paid.kills.min(player.kills);
+            paid.assists = paid.assists.min(player.assists);
+
+            let earned = (player.kills - paid.kills) * KILL_REWARD + (player.assists - paid.assists) * ASSIST_REWARD;
+
+            paid.kills = player.kills;
+            paid.assists = player.assists;
+
+            if earned > 0 {
+                award_money(self, &player.owner, earned);
+            }
+        }
+    }
+
+    /// Answer every request we havent answered yet, charging for the ones that go through. Host only
+    pub fn process_requests(&mut self, players: &mut SyncArena<Player>, can_shop: impl Fn(&Player) -> bool) {
+
+        for (_, player) in players.iter_mut() {
+
+            let request = match &player.economy_request {
+                Some(request) => request.clone(),
+                None => continue,
+            };
+
+            if self.receipts.get(&player.owner).is_some_and(|receipt| receipt.id == request.id) {
+                continue;
+            }
+
+            let wallet = self.wallet_mut(&player.owner);
+
+            let status = match &request.kind {
+                _ if player.death.is_some() => ReceiptStatus::Denied("You need to be alive to do that".to_string()),
+                EconomyRequestKind::Buy(_) if !can_shop(player) => ReceiptStatus::Denied("You are too far from a shop".to_string()),
+                EconomyRequestKind::Buy(ShopItem::Health) if player.health >= MAX_HEALTH => ReceiptStatus::Denied("Already at full health".to_string()),
+                EconomyRequestKind::Buy(item) if wallet.money < item.price() => ReceiptStatus::Denied("Not enough money".to_string()),
+                EconomyRequestKind::Buy(item) => {
+                    wallet.money -= item.price();
+
+                    // materials live in the wallet so they are handed out here
+                    if *item == ShopItem::Materials {
+                        wallet.materials += MATERIALS_PER_PURCHASE;
+                    }
+
+                    // health only has one writer so the host heals here instead of the owner
+                    if *item == ShopItem::Health {
+                        player.heal(MAX_HEALTH);
+                    }
+
+                    ReceiptStatus::Approved
+                },
+                EconomyRequestKind::Build(_) if wallet.materials == 0 => ReceiptStatus::Denied("Out of materials".to_string()),
+                EconomyRequestKind::Build(_) => {
+                    wallet.materials -= 1;
+
+                    ReceiptStatus::Approved
+                },
+            };
+
+            self.receipts.insert(player.owner.clone(), Receipt { id: request.id, status });
+        }
+
+        // nobody is coming to collect these
+        self.receipts.retain(|owner, _| players.iter().any(|(_, player)| player.owner == *owner));
+    }
+
+    /// Pick up the answer to our request. This is the only place bought items and built structures come from
+    pub fn collect_purchases(&self, level: &mut Level, textures: &mut TextureLoader, uuid: &String, shop_menu: &mut ShopMenu) {
+
+        let receipt = match self.receipts.get(uuid) {
+            Some(receipt) => receipt,
+            None => return,
+        };
+
+        let player = match level.players.iter_mut().find(|(_, player)| player.owner == *uuid) {
+            Some((_, player)) => player,
+            None => return,
+        };
+
+        let request = match &player.economy_request {
+            Some(request) if request.id == receipt.id => request.clone(),
+            _ => return,
+        };
+
+        player.economy_request = None;
+
+        match (&receipt.status, request.kind) {
+            (ReceiptStatus::Approved, EconomyRequestKind::Buy(item)) => {
+                item.give(player, &mut level.space, &mut level.boats, textures);
+
+                shop_menu.notify(format!("Bought {}", item.name()), GREEN);
+            },
+            (ReceiptStatus::Approved, EconomyRequestKind::Build(position)) => {
+                level.structures.insert(
+                    Structure::new(position, &mut level.space, uuid.clone())
+                );
+            },
+            (ReceiptStatus::Denied(reason), _) => shop_menu.notify(reason.clone(), RED),
+        }
+    }
+}
+
+/// The local shop window. Each client has its own so this isnt synced
+pub struct ShopMenu {
+    pub open: bool,
+    pub notice: Option<(String, Color, Time)>
+}
+
+impl ShopMenu {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            notice: None
+        }
+    }
+
+    pub fn notify(&mut self, text: String, color: Color) {
+        self.notice = Some((text, color, Time::now()));
+    }
+
+    /// Open and close the shop and return whatever the player wants to buy
+    pub fn tick(&mut self, available: bool, bindings: &Bindings) -> Option<ShopItem> {
+
+        // walking away closes it
+        if !available {
+            self.open = false;
+
+            return None;
+        }
+
+        if bindings.released(Action::Shop) {
+            self.open = !self.open;
+        }
+
+        if !self.open {
+            return None;
+        }
+
+        SHOP_SLOTS.iter().zip(ShopItem::ALL).find(|(slot, _)| bindings.released(**slot)).map(|(_, item)| item)
+    }
+
+    pub fn draw(&self, available: bool, money: u32, materials: u32, bindings: &Bindings) {
+
+        draw_text(&format!("${}", money), screen_width() - 200., screen_height() - 50., 35., GOLD);
+        draw_text(&format!("Materials: {}", materials), screen_width() - 200., screen_height() - 20., 25., WHITE);
+
+        if let Some((text, color, time)) = &self.notice {
+            if time.elapsed().num_milliseconds() < 3000 {
+                draw_text(text, (screen_width() / 2.) - 150., 250., 30., *color);
+            }
+        }
+
+        if !available {
+            return;
+        }
+
+        if !self.open {
+            draw_text(&format!("[{}] shop", bindings.key_name(Action::Shop)), screen_width() - 200., screen_height() - 85., 25., GRAY);
+
+            return;
+        }
+
+        let width = 350.;
+        let height = 60. + (ShopItem::ALL.len() as f32 * 30.);
+
+        let x = (screen_width() / 2.) - (width / 2.);
+        let y = screen_height() - height - 100.;
+
+        draw_rectangle(x, y, width, height, Color::from_rgba(0, 0, 0, 200));
+
+        draw_text("Shop", x + 10., y + 30., 30., WHITE);
+
+        for (index, item) in ShopItem::ALL.iter().enumerate() {
+
+            let color = match money >= item.price() {
+                true => WHITE,
+                false => GRAY,
+            };
+
+            draw_text(&format!("[{}] {} - ${}", bindings.key_name(SHOP_SLOTS[index]), item.name(), item.price()), x + 10., y + 65. + (index as f32 * 30.), 25., color);
+        }
+    }
+}
@@ -2,15 +2,18 @@ use std::{collections::HashSet, time::{Duration, Instant}};
 
 use diff::Diff;
 use gamelibrary::{get_angle_to_mouse, mouse_world_pos, rapier_mouse_world_pos, rapier_to_macroquad, sound::soundmanager::SoundHandle, space::{Space, SyncColliderHandle, SyncImpulseJointHandle, SyncRigidBodyHandle}, sync_arena::{Index, SyncArena}, texture_loader::TextureLoader, time::Time, traits::{draw_texture_onto_physics_body, HasPhysics}};
-use macroquad::{color::{RED, WHITE}, input::is_mouse_button_released, math::{vec2, Vec2}, miniquad::TextureParams, shapes::{draw_circle, draw_rectangle}, texture::{draw_texture_ex, DrawTextureParams}};
+use macroquad::{color::{RED, WHITE}, math::{vec2, Vec2}, miniquad::TextureParams, shapes::{draw_circle, draw_rectangle}, texture::{draw_texture_ex, DrawTextureParams}};
 use nalgebra::{point, vector, Const, OPoint};
 use parry2d::{query::Ray, shape::Shape};
 use rapier2d::prelude::{ColliderHandle, QueryFilter, RigidBodyBuilder, RigidBodyHandle};
 use serde::{Deserialize, Serialize};
 
-use crate::{blood::Blood, bullet_trail::BulletTrail, collider_from_texture_size, damage_number::{self, DamageNumber}, enemy::Enemy, muzzle_flash::MuzzleFlash, player::player::{Facing, Player, PlayerWeapon, WeaponTickParameters}, weapon::Weapon, Grabbable, TickContext};
+use crate::{bindings::Action, blood::Blood, bullet_trail::BulletTrail, collider_from_texture_size, damage_number::{self, DamageNumber}, enemy::Enemy, muzzle_flash::MuzzleFlash, player::player::{Facing, Player, PlayerWeapon, WeaponTickParameters}, weapon::Weapon, Grabbable, TickContext};
+
+pub const SHOTGUN_NAME: &str = "Shotgun";
 
 #[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[serde(from = "SavedShotgun")]
 #[diff(attr(
     #[derive(Serialize, Deserialize)]
 ))]
@@ -18,6 +21,24 @@ pub struct Shotgun {
     pub weapon: Weapon
 }
 
+// shotguns saved before weapons had names load with an empty one
+#[derive(Deserialize)]
+struct SavedShotgun {
+    weapon: Weapon
+}
+
+impl From<SavedShotgun> for Shotgun {
+    fn from(saved: SavedShotgun) -> Self {
+        let mut weapon = saved.weapon;
+
+        if weapon.name.is_empty() {
+            weapon.name = SHOTGUN_NAME.to_string();
+        }
+
+        Self { weapon }
+    }
+}
+
 impl Shotgun {
 
     pub fn rigid_body(&self) -> SyncRigidBodyHandle {
@@ -38,14 +59,42 @@ impl Shotgun {
         &self.weapon.facing
     }
 
+    pub fn refill_ammo(&mut self, magazines: u32) {
+        self.weapon.refill_ammo(magazines)
+    }
+
     pub fn set_facing(&mut self, facing: Facing) {
         self.weapon.facing = facing
     }
 
+    pub fn attach(&mut self, space: &mut Space, player_rigid_body_handle: SyncRigidBodyHandle) {
+        self.weapon.attach(space, player_rigid_body_handle)
+    }
+
+    pub fn detach(&mut self, space: &mut Space, velocity: Vec2) {
+        self.weapon.detach(space, velocity)
+    }
+
+    pub fn can_pick_up(&self) -> bool {
+        self.weapon.can_pick_up()
+    }
+
+    pub fn last_fired(&self) -> Time {
+        self.weapon.last_fired()
+    }
+
+    pub fn reloading(&self) -> bool {
+        self.weapon.reloading()
+    }
+
     pub fn owner(&self) -> &String {
         &self.weapon.owner
     }
 
+    pub fn name(&self) -> &String {
+        &self.weapon.name
+    }
+
     pub fn set_owner(&mut self, owner: String) {
         self.weapon.owner = owner
     }
@@ -73,8 +122,8 @@ impl Shotgun {
                 700,
                 2,
                 2,
-                24
-
+                24,
+                SHOTGUN_NAME
             ),
         }
         
@@ -126,7 +175,7 @@ impl Shotgun {
         ctx: &mut TickContext,
         weapon_tick_parameters: &mut WeaponTickParameters
     ) {
-        if !is_mouse_button_released(macroquad::input::MouseButton::Left) {
+        if !weapon_tick_parameters.can_fire || !ctx.bindings.released(Action::Fire) {
             return;
         }
         
@@ -0,0 +1,121 @@
+use diff::Diff;
+use gamelibrary::{rapier_to_macroquad, space::Space, sync_arena::SyncArena};
+use macroquad::{color::{Color, GREEN, PURPLE}, math::{vec2, Vec2}, shapes::draw_circle_lines, text::draw_text};
+use serde::{Deserialize, Serialize};
+
+use crate::{enemy::Enemy, player::player::Player, team::Team};
+
+// used when the level doesnt have any spawn points
+pub const DEFAULT_PLAYER_SPAWN: Vec2 = Vec2::new(100., 300.);
+
+// anything alive closer than this counts as standing on the spawn
+const OCCUPIED_DISTANCE: f32 = 50.;
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Eq, Clone, Copy, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub enum SpawnPurpose {
+    Player, // anyone can spawn here
+    Team(Team), // only players on this team spawn here
+    EnemyWave
+}
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct SpawnPoint {
+    pub position: Vec2,
+    pub purpose: SpawnPurpose
+}
+
+impl SpawnPoint {
+    pub fn new(position: Vec2, purpose: SpawnPurpose) -> Self {
+        Self {
+            position,
+            purpose
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self.purpose {
+            SpawnPurpose::Player => GREEN,
+            SpawnPurpose::Team(team) => team.hud_color(),
+            SpawnPurpose::EnemyWave => PURPLE,
+        }
+    }
+
+    pub fn draw(&self) {
+        let pos = rapier_to_macroquad(&self.position);
+
+        let label = match self.purpose {
+            SpawnPurpose::Player => "player".to_string(),
+            SpawnPurpose::Team(team) => team.name().to_lowercase(),
+            SpawnPurpose::EnemyWave => "enemy".to_string(),
+        };
+
+        draw_circle_lines(pos.x, pos.y, 20., 3., self.color());
+        draw_text(&label, pos.x - 20., pos.y - 25., 20., self.color());
+    }
+}
+
+/// Pick the candidate furthest from any threat, skipping occupied spots when we can
+pub fn choose_spawn_position(candidates: &[Vec2], threats: &[Vec2], occupied: &[Vec2]) -> Option<Vec2> {
+
+    let distance_to_nearest = |position: &Vec2, others: &[Vec2]| {
+        others.iter()
+            .map(|other| position.distance(*other))
+            .fold(f32::MAX, f32::min)
+    };
+
+    let free_candidates: Vec<Vec2> = candidates.iter()
+        .filter(|candidate| distance_to_nearest(candidate, occupied) > OCCUPIED_DISTANCE)
+        .cloned()
+        .collect();
+
+    // if everything is occupied we would rather spawn on top of someone than not at all
+    let candidates = match free_candidates.is_empty() {
+        true => candidates.to_vec(),
+        false => free_candidates,
+    };
+
+    candidates.into_iter()
+        .max_by(|a, b| {
+            distance_to_nearest(a, threats).total_cmp(&distance_to_nearest(b, threats))
+        })
+}
+
+pub fn living_player_positions(space: &Space, players: &SyncArena<Player>) -> Vec<Vec2> {
+
+    let mut positions = Vec::new();
+
+    for (_, player) in players {
+        if player.health == 0 {
+            continue;
+        }
+
+        let position = space.sync_rigid_body_set.get_sync(player.body.body_handle).unwrap().translation();
+
+        positions.push(vec2(position.x, position.y));
+    }
+
+    positions
+}
+
+pub fn living_enemy_positions(space: &Space, enemies: &SyncArena<Enemy>) -> Vec<Vec2> {
+
+    let mut positions = Vec::new();
+
+    for (_, enemy) in enemies {
+        if enemy.health <= 0 {
+            continue;
+        }
+
+        let position = space.sync_rigid_body_set.get_sync(enemy.body.body_handle).unwrap().translation();
+
+        positions.push(vec2(position.x, position.y));
+    }
+
+    positions
+}
@@ -0,0 +1,101 @@
+use std::{collections::HashMap, time::Duration};
+
+/// Decides how often the client should send game state diffs.
+///
+/// We start at 120 syncs per second and back off when the connection can't keep up.
+/// The time spent inside `SyncClient::sync` is used as a stand in for upstream bandwidth because the socket write stalls once the send buffer is full.
+/// Round trip time is measured by stamping our `Player` with our own clock and waiting for other players to echo the stamp back, so clock differences between clients dont matter
+pub struct SyncRate {
+    pub min_interval: Duration,
+    pub max_interval: Duration,
+    // if the round trip is longer than this we start sending less often
+    pub target_round_trip: Duration,
+    interval: Duration,
+    average_sync_duration: Duration,
+    average_round_trip: Option<Duration>,
+    // the last echo of our timestamp we got from each remote player
+    last_echoes: HashMap<String, u64>
+}
+
+impl SyncRate {
+    pub fn new() -> Self {
+        Self {
+            min_interval: Duration::from_secs_f32(1. / 120.),
+            max_interval: Duration::from_secs_f32(1. / 15.),
+            target_round_trip: Duration::from_millis(150),
+            interval: Duration::from_secs_f32(1. / 120.),
+            average_sync_duration: Duration::ZERO,
+            average_round_trip: None,
+            last_echoes: HashMap::new(),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn should_sync(&self, last_sync: &web_time::Instant) -> bool {
+        last_sync.elapsed() >= self.interval
+    }
+
+    /// Record how long a call to `SyncClient::sync` took
+    pub fn record_sync(&mut self, sync_duration: Duration) {
+        self.average_sync_duration = ewma(self.average_sync_duration, sync_duration, 0.1);
+
+        self.adapt();
+    }
+
+    /// Sample the round trip time from a remote player echoing back one of our own sync timestamps. `now` is on the same clock as the timestamp
+    pub fn record_echo(&mut self, owner: &String, echoed_timestamp: u64, now: u64) {
+
+        // we only want to sample each echo once, otherwise a player that stopped syncing looks like a huge round trip
+        if self.last_echoes.get(owner) == Some(&echoed_timestamp) {
+            return;
+        }
+
+        self.last_echoes.insert(owner.clone(), echoed_timestamp);
+
+        let round_trip = Duration::from_millis(now.saturating_sub(echoed_timestamp));
+
+        if round_trip > Duration::from_secs(5) {
+            return;
+        }
+
+        self.average_round_trip = Some(match self.average_round_trip {
+            Some(average_round_trip) => ewma(average_round_trip, round_trip, 0.1),
+            None => round_trip,
+        });
+    }
+
+    fn congested(&self) -> bool {
+
+        // spending more than a quarter of the interval blocked on the socket means we are sending faster than the connection can take
+        if self.average_sync_duration > self.interval / 4 {
+            return true;
+        }
+
+        if let Some(average_round_trip) = self.average_round_trip {
+            if average_round_trip > self.target_round_trip {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn adapt(&mut self) {
+
+        // back off quickly and recover slowly so we dont oscillate
+        let new_interval = if self.congested() {
+            self.interval.mul_f32(1.25)
+        } else {
+            self.interval.saturating_sub(Duration::from_micros(100))
+        };
+
+        self.interval = new_interval.clamp(self.min_interval, self.max_interval);
+    }
+}
+
+fn ewma(average: Duration, sample: Duration, weight: f32) -> Duration {
+    average.mul_f32(1. - weight) + sample.mul_f32(weight)
+}
@@ -0,0 +1,153 @@
+use diff::Diff;
+use gamelibrary::sync_arena::SyncArena;
+use macroquad::color::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::player::player::Player;
+
+#[derive(Serialize, Deserialize, Diff, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub enum Team {
+    Red,
+    Blue
+}
+
+impl Team {
+
+    pub const ALL: [Team; 2] = [Team::Red, Team::Blue];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Team::Red => "Red",
+            Team::Blue => "Blue",
+        }
+    }
+
+    /// The color players on this team are tinted with
+    pub fn tint(&self) -> Color {
+        match self {
+            Team::Red => Color::new(1., 0.6, 0.6, 1.),
+            Team::Blue => Color::new(0.6, 0.7, 1., 1.),
+        }
+    }
+
+    /// The color used for this team in the hud
+    pub fn hud_color(&self) -> Color {
+        match self {
+            Team::Red => Color::from_rgba(220, 50, 50, 255),
+            Team::Blue => Color::from_rgba(60, 110, 230, 255),
+        }
+    }
+
+    pub fn opponent(&self) -> Team {
+        match self {
+            Team::Red => Team::Blue,
+            Team::Blue => Team::Red,
+        }
+    }
+}
+
+/// Put players without a team on the smaller team, then move players until the teams are even
+pub fn assign_teams(players: &mut SyncArena<Player>) {
+
+    let mut red_count = players.iter().filter(|(_, player)| player.team == Some(Team::Red)).count();
+    let mut blue_count = players.iter().filter(|(_, player)| player.team == Some(Team::Blue)).count();
+
+    for (_, player) in players.iter_mut() {
+        if player.team.is_some() {
+            continue;
+        }
+
+        if red_count <= blue_count {
+            player.team = Some(Team::Red);
+            red_count += 1;
+        } else {
+            player.team = Some(Team::Blue);
+            blue_count += 1;
+        }
+    }
+
+    // auto balance
+    while red_count.abs_diff(blue_count) > 1 {
+
+        let larger_team = match red_count > blue_count {
+            true => Team::Red,
+            false => Team::Blue,
+        };
+
+        // prefer moving dead players so nobody gets swapped mid fight
+        let moved_player = players.iter()
+            .filter(|(_, player)| player.team == Some(larger_team))
+            .min_by_key(|(_, player)| player.health)
+            .map(|(player_index, _)| player_index);
+
+        let moved_player = match moved_player {
+            Some(moved_player) => moved_player,
+            None => break,
+        };
+
+        players.get_mut(&moved_player).unwrap().team = Some(larger_team.opponent());
+
+        match larger_team {
+            Team::Red => {
+                red_count -= 1;
+                blue_count += 1;
+            },
+            Team::Blue => {
+                blue_count -= 1;
+                red_count += 1;
+            },
+        }
+    }
+}
+
+/// Whether killing or helping kill a player on `victim_team` counts towards the stats of a player on `credited_team`.
+/// Team kills and assists on teammates dont, otherwise friendly fire would pay out kill rewards. Players without a team can be credited for anyone
+pub fn earns_kill_credit(credited_team: Option<Team>, victim_team: Option<Team>) -> bool {
+    credited_team.is_none() || credited_team != victim_team
+}
+
+/// The team that scores when `victim` is killed by `killer`. Suicides, team kills and deaths to anything but an enemy player dont score
+pub fn kill_scoring_team(killer: Option<&String>, victim: &String, players: &SyncArena<Player>) -> Option<Team> {
+
+    let killer = killer?;
+
+    if killer == victim {
+        return None;
+    }
+
+    let (_, victim) = players.iter().find(|(_, player)| player.owner == *victim)?;
+
+    let victim_team = victim.team?;
+
+    let (_, killer) = players.iter().find(|(_, player)| player.owner == *killer)?;
+
+    match killer.team == Some(victim_team.opponent()) {
+        true => killer.team,
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{earns_kill_credit, Team};
+
+    #[test]
+    fn enemy_kills_earn_credit() {
+        assert!(earns_kill_credit(Some(Team::Red), Some(Team::Blue)));
+    }
+
+    #[test]
+    fn team_kills_dont_earn_credit() {
+        assert!(!earns_kill_credit(Some(Team::Red), Some(Team::Red)));
+        assert!(!earns_kill_credit(Some(Team::Blue), Some(Team::Blue)));
+    }
+
+    #[test]
+    fn players_without_a_team_always_earn_credit() {
+        assert!(earns_kill_credit(None, None));
+        assert!(earns_kill_credit(None, Some(Team::Red)));
+    }
+}
@@ -0,0 +1,169 @@
+use diff::Diff;
+use gamelibrary::{rapier_to_macroquad, space::{Space, SyncRigidBodyHandle}, time::Time};
+use macroquad::{color::Color, math::vec2, shapes::draw_rectangle, text::draw_text, window::{screen_height, screen_width}};
+use nalgebra::Vector2;
+use rapier2d::prelude::RigidBodyType;
+use serde::{Deserialize, Serialize};
+
+// the freeze starts this many ms after the host sees the activation so it reaches every client before anyone freezes
+const TIME_STOP_WINDUP: u32 = 300;
+
+const TIME_STOP_DURATION: u32 = 5000;
+
+const FROZEN_TINT: Color = Color::new(0.6, 0.8, 1., 0.35);
+
+/// A time stop someone activated. Client clocks dont agree so only the host times it, and everyone freezes off the flags it writes
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct TimeStop {
+    pub activator: String, // owner of the player who keeps moving
+    pub started: Option<Time>, // when the host first saw it, on the host's clock. nobody else reads this
+    pub windup: u32,
+    pub duration: u32,
+    pub frozen: bool, // whether everything should be frozen right now. only the host writes this
+    pub over: bool,
+    pub remaining_seconds: i64 // whole seconds until time starts again, for the hud
+}
+
+impl TimeStop {
+    pub fn new(activator: String) -> Self {
+        Self {
+            activator,
+            started: None,
+            windup: TIME_STOP_WINDUP,
+            duration: TIME_STOP_DURATION,
+            frozen: false,
+            over: false,
+            remaining_seconds: i64::from(TIME_STOP_DURATION / 1000)
+        }
+    }
+
+    /// Start the clock the first time we see this and update the flags from it. Host only
+    pub fn host_tick(&mut self) {
+
+        let elapsed = self.started.get_or_insert_with(Time::now).elapsed().num_milliseconds();
+
+        let end = i64::from(self.windup + self.duration);
+
+        self.frozen = elapsed >= self.windup.into() && elapsed < end;
+        self.over = elapsed >= end;
+        self.remaining_seconds = ((end - elapsed).max(0).min(self.duration.into()) + 999) / 1000;
+    }
+
+    /// Pale boxes over everything that isnt moving
+    pub fn draw(&self, space: &Space, exempt: &[SyncRigidBodyHandle]) {
+
+        if !self.frozen {
+            return;
+        }
+
+        let exempt: Vec<_> = exempt.iter().map(|handle| space.sync_rigid_body_set.get_local_handle(*handle)).collect();
+
+        for (_, collider) in space.sync_collider_set.collider_set.iter() {
+
+            let parent = match collider.parent() {
+                Some(parent) => parent,
+                None => continue,
+            };
+
+            if exempt.contains(&parent) || collider.is_sensor() {
+                continue;
+            }
+
+            // level geometry never moves anyway
+            match space.sync_rigid_body_set.rigid_body_set.get(parent) {
+                Some(body) if !body.is_fixed() => {},
+                _ => continue,
+            }
+
+            let aabb = collider.compute_aabb();
+
+            let top_left = rapier_to_macroquad(&vec2(aabb.mins.x, aabb.maxs.y));
+
+            draw_rectangle(top_left.x, top_left.y, aabb.maxs.x - aabb.mins.x, aabb.maxs.y - aabb.mins.y, FROZEN_TINT);
+        }
+    }
+
+    pub fn draw_hud(&self) {
+
+        if !self.frozen {
+            return;
+        }
+
+        draw_rectangle(0., 0., screen_width(), screen_height(), Color::new(0.3, 0.3, 0.45, 0.25));
+
+        draw_text(&format!("TIME STOPPED {}", self.remaining_seconds), (screen_width() / 2.) - 130., screen_height() - 120., 40., FROZEN_TINT);
+    }
+}
+
+/// A body we froze and the velocity to give back to it
+pub struct FrozenBody {
+    pub body_handle: SyncRigidBodyHandle,
+    pub linvel: Vector2<f32>,
+    pub angvel: f32
+}
+
+/// The bodies this client froze. Each owner freezes its own bodies so this is local
+pub struct Freezer {
+    pub frozen: Vec<FrozenBody>
+}
+
+impl Freezer {
+    pub fn new() -> Self {
+        Self {
+            frozen: Vec::new()
+        }
+    }
+
+    /// Stop a body where it is. It stays kinematic so things still bump into it
+    pub fn freeze(&mut self, space: &mut Space, body_handle: SyncRigidBodyHandle) {
+
+        let body = match space.sync_rigid_body_set.get_sync_mut(body_handle) {
+            Some(body) => body,
+            None => return,
+        };
+
+        // already frozen. undo anything that tried to move it this tick, like player controls
+        if self.frozen.iter().any(|frozen| frozen.body_handle == body_handle) {
+            body.set_linvel(Vector2::zeros(), true);
+            body.set_angvel(0., true);
+
+            return;
+        }
+
+        if !body.is_dynamic() {
+            return;
+        }
+
+        self.frozen.push(
+            FrozenBody {
+                body_handle,
+                linvel: *body.linvel(),
+                angvel: body.angvel()
+            }
+        );
+
+        body.set_body_type(RigidBodyType::KinematicVelocityBased, true);
+        body.set_linvel(Vector2::zeros(), true);
+        body.set_angvel(0., true);
+    }
+
+    /// Start everything moving again the way it was moving before
+    pub fn thaw_all(&mut self, space: &mut Space) {
+
+        for frozen in self.frozen.drain(..) {
+
+            // it might have been despawned while frozen
+            let body = match space.sync_rigid_body_set.get_sync_mut(frozen.body_handle) {
+                Some(body) => body,
+                None => continue,
+            };
+
+            body.set_body_type(RigidBodyType::Dynamic, true);
+            body.set_linvel(frozen.linvel, true);
+            body.set_angvel(frozen.angvel, true);
+        }
+    }
+}
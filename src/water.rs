@@ -0,0 +1,292 @@
+use diff::Diff;
+use gamelibrary::{rapier_to_macroquad, space::Space, time::Time};
+use macroquad::{color::{Color, WHITE}, math::{vec2, Vec2}, shapes::{draw_line, draw_rectangle, draw_rectangle_lines}};
+use nalgebra::{point, vector};
+use serde::{Deserialize, Serialize};
+
+use crate::TickContext;
+
+const WATER_COLOR: Color = Color::new(0.2, 0.45, 0.85, 0.45);
+const SURFACE_COLOR: Color = Color::new(0.6, 0.8, 1., 0.9);
+
+// splash droplets live this many ms
+const SPLASH_LIFETIME: i64 = 800;
+
+// bodies slower than this dont splash
+const MIN_SPLASH_SPEED: f32 = 150.;
+
+// how close the mouse needs to be to the corner handle to grab it in the editor
+const HANDLE_SIZE: f32 = 15.;
+
+const MIN_HALF_EXTENTS: Vec2 = vec2(20., 20.);
+
+/// An axis aligned pool of water. Anything dynamic inside it gets pushed up by how much of it is under the surface
+#[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
+#[diff(attr(
+    #[derive(Serialize, Deserialize)]
+))]
+pub struct WaterVolume {
+    pub position: Vec2, // the middle of the volume in rapier coords
+    pub half_extents: Vec2,
+    pub density: f32, // bodies with less mass per unit of area than this float
+    pub linear_drag: f32,
+    pub angular_drag: f32
+}
+
+impl WaterVolume {
+    pub fn new(position: Vec2, half_extents: Vec2) -> Self {
+        Self {
+            position,
+            half_extents,
+            density: 0.05,
+            linear_drag: 2.,
+            angular_drag: 2.
+        }
+    }
+
+    /// The top right corner. Dragging it in the editor resizes the volume while the bottom left corner stays put
+    pub fn handle(&self) -> Vec2 {
+        self.position + self.half_extents
+    }
+
+    /// Whether the mouse is close enough to the corner handle to grab it
+    pub fn handle_contains(&self, point: Vec2) -> bool {
+        self.handle().distance(point) <= HANDLE_SIZE
+    }
+
+    /// Move the corner handle to `corner`, keeping the bottom left corner where it is
+    pub fn resize(&mut self, corner: Vec2) {
+
+        let bottom_left = self.position - self.half_extents;
+
+        self.half_extents = ((corner - bottom_left) / 2.).max(MIN_HALF_EXTENTS);
+        self.position = bottom_left + self.half_extents;
+    }
+
+    pub fn surface(&self) -> f32 {
+        self.position.y + self.half_extents.y
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        (point.x - self.position.x).abs() <= self.half_extents.x && (point.y - self.position.y).abs() <= self.half_extents.y
+    }
+
+    /// Push our bodies around. Only owned bodies are touched since their owner simulates them
+    pub fn tick(&self, space: &mut Space, ctx: &mut TickContext) {
+
+        let delta_seconds = ctx.last_tick_duration.as_secs_f32();
+
+        let gravity = space.gravity.y.abs();
+
+        let water_min = self.position - self.half_extents;
+        let water_max = self.position + self.half_extents;
+
+        for body_handle in ctx.owned_rigid_bodies.iter() {
+
+            // colliders are looked up separately so we need these before borrowing the body mutably
+            let body = match space.sync_rigid_body_set.get_sync(*body_handle) {
+                Some(body) => body,
+                None => continue,
+            };
+
+            if !body.is_dynamic() {
+                continue;
+            }
+
+            let mut submerged_area = 0.;
+            let mut total_area = 0.;
+            let mut submerged_centroid = Vec2::ZERO;
+
+            for collider_handle in body.colliders() {
+
+                let collider = match space.sync_collider_set.collider_set.get(*collider_handle) {
+                    Some(collider) => collider,
+                    None => continue,
+                };
+
+                // particles and sensors dont float
+                if collider.is_sensor() {
+                    continue;
+                }
+
+                let area = collider.shape().mass_properties(1.).mass();
+
+                let aabb = collider.compute_aabb();
+
+                let aabb_area = (aabb.maxs.x - aabb.mins.x) * (aabb.maxs.y - aabb.mins.y);
+
+                if aabb_area <= 0. {
+                    continue;
+                }
+
+                // how much of the bounding box is under water, scaled down to the real shape's area
+                let overlap_min = vec2(aabb.mins.x.max(water_min.x), aabb.mins.y.max(water_min.y));
+                let overlap_max = vec2(aabb.maxs.x.min(water_max.x), aabb.maxs.y.min(water_max.y));
+
+                total_area += area;
+
+                if overlap_max.x <= overlap_min.x || overlap_max.y <= overlap_min.y {
+                    continue;
+                }
+
+                let overlap_area = (overlap_max.x - overlap_min.x) * (overlap_max.y - overlap_min.y);
+
+                let collider_submerged_area = area * (overlap_area / aabb_area);
+
+                submerged_centroid += ((overlap_min + overlap_max) / 2.) * collider_submerged_area;
+                submerged_area += collider_submerged_area;
+            }
+
+            let velocity = vec2(body.linvel().x, body.linvel().y);
+
+            if submerged_area <= 0. {
+                continue;
+            }
+
+            submerged_centroid /= submerged_area;
+
+            let submerged_fraction = (submerged_area / total_area).min(1.);
+
+            let body = space.sync_rigid_body_set.get_sync_mut(*body_handle).unwrap();
+
+            // pushing at the middle of the submerged part is what rights boats
+            let buoyancy = self.density * submerged_area * gravity * delta_seconds;
+
+            body.apply_impulse_at_point(vector![0., buoyancy], point![submerged_centroid.x, submerged_centroid.y], true);
+
+            let drag = -velocity * self.linear_drag * submerged_fraction * body.mass() * delta_seconds;
+
+            body.apply_impulse(vector![drag.x, drag.y], true);
+
+            let angular_drag = -body.angvel() * self.angular_drag * submerged_fraction * body.mass_properties().effective_angular_inertia() * delta_seconds;
+
+            body.apply_torque_impulse(angular_drag, true);
+        }
+    }
+
+    /// Splash wherever any body went through the surface since last tick, whoever owns it
+    pub fn splash_tick(&self, space: &Space, splashes: &mut Splashes, delta_seconds: f32) {
+
+        for (_, body) in space.sync_rigid_body_set.rigid_body_set.iter() {
+
+            if !body.is_dynamic() {
+                continue;
+            }
+
+            self.splash(vec2(body.translation().x, body.translation().y), vec2(body.linvel().x, body.linvel().y), delta_seconds, splashes);
+        }
+    }
+
+    /// Add droplets if a body went through the surface since last tick
+    pub fn splash(&self, position: Vec2, velocity: Vec2, delta_seconds: f32, splashes: &mut Splashes) {
+
+        if (position.x - self.position.x).abs() > self.half_extents.x {
+            return;
+        }
+
+        let speed = velocity.y.abs();
+
+        if speed < MIN_SPLASH_SPEED {
+            return;
+        }
+
+        let previous_y = position.y - (velocity.y * delta_seconds);
+
+        let surface = self.surface();
+
+        // still on the same side of the surface
+        if (previous_y > surface) == (position.y > surface) {
+            return;
+        }
+
+        let droplets = ((speed / 100.) as usize).clamp(2, 10);
+
+        for droplet in 0..droplets {
+
+            // spread the droplets out both ways
+            let spread = (droplet as f32 / (droplets - 1) as f32) - 0.5;
+
+            splashes.droplets.push(
+                Splash::new(
+                    vec2(position.x, surface),
+                    vec2(spread * speed, speed * 0.5 * (1. - spread.abs()))
+                )
+            );
+        }
+    }
+
+    pub fn draw(&self) {
+
+        // rapier y is up so the top left corner is at the surface
+        let top_left = rapier_to_macroquad(&vec2(self.position.x - self.half_extents.x, self.surface()));
+
+        let size = self.half_extents * 2.;
+
+        draw_rectangle(top_left.x, top_left.y, size.x, size.y, WATER_COLOR);
+        draw_line(top_left.x, top_left.y, top_left.x + size.x, top_left.y, 3., SURFACE_COLOR);
+    }
+
+    pub fn draw_handle(&self) {
+
+        let handle = rapier_to_macroquad(&self.handle());
+
+        draw_rectangle_lines(handle.x - (HANDLE_SIZE / 2.), handle.y - (HANDLE_SIZE / 2.), HANDLE_SIZE, HANDLE_SIZE, 2., WHITE);
+    }
+}
+
+/// Splashes are only for looks so every client makes its own from the bodies it sees instead of syncing them
+pub struct Splashes {
+    pub droplets: Vec<Splash>
+}
+
+impl Splashes {
+    pub fn new() -> Self {
+        Self {
+            droplets: Vec::new()
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.droplets.retain(|splash| !splash.expired());
+    }
+
+    pub fn draw(&self, gravity: f32) {
+        for splash in &self.droplets {
+            splash.draw(gravity);
+        }
+    }
+}
+
+/// A droplet of water thrown up from the surface. They dont collide with anything so their path is worked out from when they spawned instead of simulated
+pub struct Splash {
+    pub origin: Vec2,
+    pub velocity: Vec2,
+    pub spawned: Time
+}
+
+impl Splash {
+    pub fn new(origin: Vec2, velocity: Vec2) -> Self {
+        Self {
+            origin,
+            velocity,
+            spawned: Time::now()
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        self.spawned.elapsed().num_milliseconds() > SPLASH_LIFETIME
+    }
+
+    pub fn draw(&self, gravity: f32) {
+
+        let seconds = self.spawned.elapsed().num_milliseconds() as f32 / 1000.;
+
+        let position = self.origin + (self.velocity * seconds) + (vec2(0., gravity) * 0.5 * seconds * seconds);
+
+        let draw_pos = rapier_to_macroquad(&position);
+
+        let fade = 1. - (seconds * 1000. / SPLASH_LIFETIME as f32).min(1.);
+
+        draw_rectangle(draw_pos.x - 2., draw_pos.y - 2., 4., 4., Color { a: fade, ..SURFACE_COLOR });
+    }
+}
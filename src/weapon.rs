@@ -2,14 +2,17 @@ use std::{collections::HashSet, time::{Duration, Instant}};
 
 use diff::Diff;
 use gamelibrary::{get_angle_to_mouse, mouse_world_pos, rapier_mouse_world_pos, rapier_to_macroquad, sound::soundmanager::SoundHandle, space::{Space, SyncColliderHandle, SyncImpulseJointHandle, SyncRigidBodyHandle}, sync_arena::{Index, SyncArena}, texture_loader::TextureLoader, time::Time, traits::{draw_texture_onto_physics_body, HasPhysics}};
-use macroquad::{color::{RED, WHITE}, input::{is_key_released, is_mouse_button_released}, math::{vec2, Vec2}, miniquad::TextureParams, shapes::{draw_circle, draw_rectangle}, text::{draw_text_ex, TextParams}, texture::{draw_texture, draw_texture_ex, DrawTextureParams}, window::screen_height};
+use macroquad::{color::{RED, WHITE}, input::is_mouse_button_released, math::{vec2, Vec2}, miniquad::TextureParams, shapes::{draw_circle, draw_rectangle}, text::{draw_text_ex, TextParams}, texture::{draw_texture, draw_texture_ex, DrawTextureParams}, window::screen_height};
 use nalgebra::{point, vector, Const, OPoint};
 use parry2d::{math::{Translation, Vector}, query::Ray, shape::Shape};
 use rapier2d::prelude::{ColliderHandle, InteractionGroups, QueryFilter, RevoluteJointBuilder, RigidBodyBuilder, RigidBodyHandle};
 use serde::{Deserialize, Serialize};
 use gamelibrary::sound::soundmanager::SoundManager;
 
-use crate::{blood::Blood, bullet_casing::BulletCasing, bullet_trail::BulletTrail, collider_from_texture_size, damage_number::{self, DamageNumber}, enemy::Enemy, muzzle_flash::MuzzleFlash, player::{self, player::{Facing, Player, PlayerWeapon, WeaponTickParameters}}, structure::Structure, Grabbable, TickContext};
+use crate::{bindings::Action, blood::Blood, bullet_casing::BulletCasing, bullet_trail::BulletTrail, collider_from_texture_size, damage_number::{self, DamageNumber}, enemy::Enemy, muzzle_flash::MuzzleFlash, player::{self, player::{Facing, Hit, Player, PlayerWeapon, WeaponTickParameters}}, structure::Structure, Grabbable, TickContext};
+
+// millis before a dropped weapon can be picked up again by walking into it
+const PICKUP_COOLDOWN: i64 = 1000;
 
 #[derive(Serialize, Deserialize, Diff, PartialEq, Clone)]
 #[diff(attr(
@@ -58,6 +61,10 @@ pub struct Weapon {
     reserve_capacity: u32,
     #[serde(default)]
     reload_duration: u32, // reload duration in millis
+    #[serde(default)]
+    pub name: String, // shown in the kill feed
+    #[serde(default)]
+    pub dropped_at: Time
 }
 
 impl Grabbable for Weapon {
@@ -89,8 +96,8 @@ impl Weapon {
         reload_duration: u32,
         rounds: u32,
         capacity: u32,
-        reserve_capacity: u32
-        
+        reserve_capacity: u32,
+        name: &str
 
     ) -> Self {
 
@@ -120,37 +127,8 @@ impl Weapon {
             None => 0.,
         };
 
-        // if we are attaching the weapon to the player we need to do some epic stuff!
-        let player_joint_handle: Option<SyncImpulseJointHandle> = if let Some(player_rigid_body_handle) = player_rigid_body_handle {
-
-            // make the shotgun not collide with anything
-            space.sync_collider_set.get_sync_mut(collider).unwrap().set_collision_groups(InteractionGroups::none());
-
-            let local_player_rigid_body_handle = space.sync_rigid_body_set.get_local_handle(player_rigid_body_handle);
-            let local_weapon_rigid_body_handle = space.sync_rigid_body_set.get_local_handle(rigid_body);
-
-            // joint the shotgun to the player
-            Some(space.sync_impulse_joint_set.insert_sync(
-                local_player_rigid_body_handle,
-                local_weapon_rigid_body_handle,
-                RevoluteJointBuilder::new()
-                    .local_anchor1(vector![0., 0.].into())
-                    .local_anchor2(vector![30., 0.].into())
-                    .limits([-0.8, 0.8])
-                    .contacts_enabled(false)
-                .build(),
-                true
-            ))
-            
-
-        } 
-
-        else {
-            None
-        };
-
-        Self {
-            player_rigid_body_handle,
+        let mut weapon = Self {
+            player_rigid_body_handle: None,
             picked_up: false,
             collider,
             rigid_body,
@@ -172,16 +150,85 @@ impl Weapon {
             y_screen_shake_intensity,
             shell_sprite: shell_sprite_path,
             bullet_casings: HashSet::new(),
-            player_joint_handle: player_joint_handle,
+            player_joint_handle: None,
             last_reload: Time::new(0),
             rounds,
             capacity,
             reserve_capacity,
             reload_duration,
-            
-            
-            
+            name: name.to_string(),
+            dropped_at: Time::new(0)
+        };
+
+        // if we are attaching the weapon to the player we need to do some epic stuff!
+        if let Some(player_rigid_body_handle) = player_rigid_body_handle {
+            weapon.attach(space, player_rigid_body_handle);
+        }
+
+        weapon
+    }
+
+    /// Joint the weapon onto a player's body so they can hold it
+    pub fn attach(&mut self, space: &mut Space, player_rigid_body_handle: SyncRigidBodyHandle) {
+
+        // make the weapon not collide with anything
+        space.sync_collider_set.get_sync_mut(self.collider).unwrap().set_collision_groups(InteractionGroups::none());
+
+        let local_player_rigid_body_handle = space.sync_rigid_body_set.get_local_handle(player_rigid_body_handle);
+        let local_weapon_rigid_body_handle = space.sync_rigid_body_set.get_local_handle(self.rigid_body);
+
+        // joint the weapon to the player
+        self.player_joint_handle = Some(space.sync_impulse_joint_set.insert_sync(
+            local_player_rigid_body_handle,
+            local_weapon_rigid_body_handle,
+            RevoluteJointBuilder::new()
+                .local_anchor1(vector![0., 0.].into())
+                .local_anchor2(vector![30., 0.].into())
+                .limits([-0.8, 0.8])
+                .contacts_enabled(false)
+            .build(),
+            true
+        ));
+
+        self.player_rigid_body_handle = Some(player_rigid_body_handle);
+        self.picked_up = true;
+    }
+
+    /// Let go of the weapon so it becomes its own body in the world. The ammo stays with it
+    pub fn detach(&mut self, space: &mut Space, velocity: Vec2) {
+
+        if let Some(player_joint_handle) = self.player_joint_handle.take() {
+            space.sync_impulse_joint_set.remove_sync(player_joint_handle, true);
         }
+
+        self.player_rigid_body_handle = None;
+        self.picked_up = false;
+        self.dropped_at = Time::now();
+
+        space.sync_collider_set.get_sync_mut(self.collider).unwrap().set_collision_groups(InteractionGroups::all());
+
+        let body = space.sync_rigid_body_set.get_sync_mut(self.rigid_body).unwrap();
+
+        body.set_linvel(vector![velocity.x, velocity.y], true);
+    }
+
+    /// Whether walking into this weapon picks it up. Freshly dropped weapons wait a bit so we dont grab them right back
+    pub fn can_pick_up(&self) -> bool {
+        !self.picked_up && self.dropped_at.elapsed().num_milliseconds() > PICKUP_COOLDOWN
+    }
+
+    /// When we last fired. Comes from the muzzle flash since that is what gets synced
+    pub fn last_fired(&self) -> Time {
+        self.muzzle_flash.last_flash()
+    }
+
+    pub fn reloading(&self) -> bool {
+        self.last_reload.elapsed().num_milliseconds() < self.reload_duration.into()
+    }
+
+    /// Add `magazines` full magazines to the reserve
+    pub fn refill_ammo(&mut self, magazines: u32) {
+        self.reserve_capacity += self.capacity * magazines;
     }
 
     pub fn reload(&mut self, ctx: &mut TickContext) {
@@ -229,7 +276,8 @@ impl Weapon {
         ctx.owned_rigid_bodies.push(self.rigid_body);
         ctx.owned_colliders.push(self.collider);
 
-        if is_key_released(macroquad::input::KeyCode::R) {
+        // the owner of a weapon lying on the ground is whoever dropped it, they shouldnt be able to reload it from across the map
+        if self.picked_up && ctx.bindings.released(Action::Reload) {
             self.reload(ctx);
         }
         
@@ -360,7 +408,9 @@ impl Weapon {
         }
 
         
-        self.rounds -= 1;
+        if !weapon_tick_parameters.infinite_ammo {
+            self.rounds -= 1;
+        }
 
         // automatically reload if zero bullets
         if self.rounds == 0 {
@@ -481,69 +531,91 @@ impl Weapon {
 
         
         // STRUCTURES
-        for (_, structure) in &mut *weapon_tick_parameters.structures {
+        // find the hits first so the structures only get marked dirty when one of them was actually shot
+        let hit_structures: Vec<Index> = weapon_tick_parameters.structures.iter()
+            .filter(|(_, structure)| sync_intersections.contains(&structure.collider_handle))
+            .map(|(structure_index, _)| structure_index)
+            .collect();
 
-            if sync_intersections.contains(&structure.collider_handle) {
+        for structure_index in hit_structures {
+
+            if let Some(structure) = weapon_tick_parameters.structures.get_mut(&structure_index) {
 
                 let bullet_impact_data = BulletImpactData{ 
                     shooter_pos: shotgun_pos, 
-                    impacted_collider: structure.collider_handle.clone() 
+                    impacted_collider: structure.collider_handle.clone(),
+                    shooter: self.owner.clone(),
+                    weapon: self.name.clone()
                 };
 
                 structure.handle_bullet(&bullet_impact_data, space);
-                
-                continue 
-                
             }
-                
         }
 
         // ENEMIES
         for (_, enemy) in &mut *weapon_tick_parameters.enemies {
 
-            if sync_intersections.contains(&enemy.body.collider_handle) {
-                let bullet_impact_data = BulletImpactData{ 
-                    shooter_pos: shotgun_pos, 
-                    impacted_collider: enemy.body.collider_handle.clone() 
-                };
-
-                enemy.handle_bullet_impact(space, bullet_impact_data);
+            let impacted_collider = if sync_intersections.contains(&enemy.body.collider_handle) {
+                enemy.body.collider_handle.clone()
             }
-            
-            else if sync_intersections.contains(&enemy.head.collider_handle)  {
+            else if sync_intersections.contains(&enemy.head.collider_handle) {
+                enemy.head.collider_handle.clone()
+            }
+            else {
+                continue;
+            };
 
-                let bullet_impact_data = BulletImpactData{ 
-                    shooter_pos: shotgun_pos, 
-                    impacted_collider: enemy.head.collider_handle.clone() 
-                };
+            let bullet_impact_data = BulletImpactData{ 
+                shooter_pos: shotgun_pos, 
+                impacted_collider,
+                shooter: self.owner.clone(),
+                weapon: self.name.clone()
+            };
 
-                enemy.handle_bullet_impact(space, bullet_impact_data);
-            }
+            enemy.handle_bullet_impact(space, bullet_impact_data);
 
         }
         
         // PLAYERS
         for (_, player) in &mut *weapon_tick_parameters.players {
 
+            // teammates dont take damage unless friendly fire is on
+            if !weapon_tick_parameters.friendly_fire && player.team.is_some() && player.team == weapon_tick_parameters.shooter_team {
+                continue;
+            }
+
+            let mut damage = 0;
+
             if sync_intersections.contains(&player.body.collider_handle) {
                 let bullet_impact_data = BulletImpactData{ 
                     shooter_pos: shotgun_pos, 
-                    impacted_collider: player.collider_handle().clone() 
+                    impacted_collider: player.collider_handle().clone(),
+                    shooter: self.owner.clone(),
+                    weapon: self.name.clone()
                 };
 
-                player.handle_bullet_impact(space, bullet_impact_data);
+                damage += player.bullet_damage(space, &bullet_impact_data);
 
             }
 
             if sync_intersections.contains(&player.head.collider_handle) {
                 let bullet_impact_data = BulletImpactData{ 
                     shooter_pos: shotgun_pos, 
-                    impacted_collider: player.collider_handle().clone() 
+                    impacted_collider: player.head.collider_handle.clone(),
+                    shooter: self.owner.clone(),
+                    weapon: self.name.clone()
                 };
 
-                player.handle_bullet_impact(space, bullet_impact_data);
+                damage += player.bullet_damage(space, &bullet_impact_data);
 
             }
+
+            // the host applies the damage so health only has one writer
+            if damage > 0 && player.health > 0 {
+                weapon_tick_parameters.hits.push(
+                    Hit { id: 0, victim: player.owner.clone(), amount: damage, weapon: self.name.clone(), shooter_pos: Vec2::new(shotgun_pos.x, shotgun_pos.y) }
+                );
+            }
             
         }
 
@@ -703,5 +775,7 @@ impl HasPhysics for Weapon {
 #[derive(Clone)]
 pub struct BulletImpactData {
     pub shooter_pos: Translation<f32>,
-    pub impacted_collider: SyncColliderHandle
+    pub impacted_collider: SyncColliderHandle,
+    pub shooter: String, // the owner of the weapon that fired
+    pub weapon: String
 }
\ No newline at end of file